@@ -0,0 +1,227 @@
+//! Audit trail of file operations: who opened, created, read, wrote, renamed, deleted or changed
+//! the attributes or ACL of which path, in which share, and with which result.
+use chrono::NaiveTime;
+use std::collections::HashMap;
+
+use super::{
+    opens::OpenTracker,
+    sessions::{SessionTracker, User},
+    ConnKey, Frame,
+};
+use crate::smb::{
+    body::{setinfo::SetInfo, Body},
+    status::NtStatus,
+    SMBHeader,
+};
+
+const SMB2_SHARE_TYPE_PIPE: u8 = 0x02;
+
+/// CreateOptions bit asking for the file to be deleted when the open is closed.
+const FILE_DELETE_ON_CLOSE: u32 = 0x00001000;
+
+// CreateDisposition
+const FILE_SUPERSEDE: u32 = 0;
+const FILE_CREATE: u32 = 2;
+const FILE_OVERWRITE: u32 = 4;
+const FILE_OVERWRITE_IF: u32 = 5;
+
+// CreateAction
+const FILE_SUPERSEDED: u32 = 0;
+const FILE_CREATED: u32 = 2;
+const FILE_OVERWRITTEN: u32 = 3;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum AuditAction {
+    Open,
+    Create,
+    Overwrite,
+    /// Bytes read or written, over `AuditEntry::count` consecutive operations.
+    Read(u64),
+    Write(u64),
+    Rename {
+        to: String,
+    },
+    Link {
+        to: String,
+    },
+    /// Delete on close, asked for at CREATE time or by setting the disposition.
+    Delete,
+    SetAttributes {
+        attributes: u32,
+    },
+    SetSize(u64),
+    /// `AdditionalInformation` of the SET_INFO, which parts of the security descriptor changed.
+    SetSecurity {
+        parts: u32,
+    },
+}
+
+impl AuditAction {
+    /// Short lowercase name, the same in the text timeline and the CSV export.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Create => "create",
+            Self::Overwrite => "overwrite",
+            Self::Read(_) => "read",
+            Self::Write(_) => "write",
+            Self::Rename { .. } => "rename",
+            Self::Link { .. } => "link",
+            Self::Delete => "delete",
+            Self::SetAttributes { .. } => "attributes",
+            Self::SetSize(_) => "truncate",
+            Self::SetSecurity { .. } => "acl",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AuditEntry {
+    /// Time and frame of the request.
+    pub time: NaiveTime,
+    pub frame: usize,
+    pub conn: ConnKey,
+    pub user: Option<User>,
+    /// `\\server\share`, if the TREE_CONNECT was captured.
+    pub share: Option<String>,
+    /// Relative to the share root.
+    pub path: String,
+    pub action: AuditAction,
+    pub status: NtStatus,
+    /// Number of operations merged in the entry, consecutive reads and writes of an open are.
+    pub count: usize,
+}
+
+#[derive(Default)]
+pub struct AuditTracker {
+    pending: HashMap<(ConnKey, u64), AuditEntry>,
+    /// Index of the last entry of each connection, to merge reads and writes.
+    last: HashMap<ConnKey, usize>,
+    entries: Vec<AuditEntry>,
+}
+
+impl AuditTracker {
+    /// Fed before the `OpenTracker`, so that the path of an open being closed is still known.
+    pub fn feed(
+        &mut self,
+        frame: &Frame,
+        header: &SMBHeader,
+        body: &Body,
+        opens: &OpenTracker,
+        sessions: &SessionTracker,
+    ) {
+        let conn = frame.conn;
+        let key = (conn, header.cmd_seq);
+
+        if !header.is_response() {
+            let ids = opens.ids(conn, header);
+
+            let (path, action) = match body {
+                Body::CreateRequest(req) => {
+                    let action = if req.options & FILE_DELETE_ON_CLOSE != 0 {
+                        AuditAction::Delete
+                    } else {
+                        match req.disposition {
+                            FILE_CREATE => AuditAction::Create,
+                            FILE_SUPERSEDE | FILE_OVERWRITE | FILE_OVERWRITE_IF => {
+                                AuditAction::Overwrite
+                            }
+                            _ => AuditAction::Open,
+                        }
+                    };
+                    (req.name.clone(), action)
+                }
+                Body::ReadRequest(req) => (opens.path(conn, req.file_id), AuditAction::Read(0)),
+                Body::WriteRequest(req) => (opens.path(conn, req.file_id), AuditAction::Write(0)),
+                Body::SetInfoRequest(req) => {
+                    let action = match &req.info {
+                        SetInfo::Rename { name, .. } => AuditAction::Rename { to: name.clone() },
+                        SetInfo::Link { name, .. } => AuditAction::Link { to: name.clone() },
+                        SetInfo::Disposition { delete: true } => AuditAction::Delete,
+                        SetInfo::Basic {
+                            file_attributes, ..
+                        } => AuditAction::SetAttributes {
+                            attributes: *file_attributes,
+                        },
+                        SetInfo::EndOfFile(size) => AuditAction::SetSize(*size),
+                        SetInfo::Security { .. } => AuditAction::SetSecurity {
+                            parts: req.additional_information,
+                        },
+                        _ => return,
+                    };
+                    (opens.path(conn, req.file_id), action)
+                }
+                _ => return,
+            };
+
+            let tree = sessions.tree(conn, ids.0, ids.1);
+            if tree.is_some_and(|t| t.share_type == SMB2_SHARE_TYPE_PIPE) {
+                // named pipes are RPC traffic, not files
+                return;
+            }
+            let session = sessions.session(conn, ids.0);
+            self.pending.insert(
+                key,
+                AuditEntry {
+                    time: frame.time,
+                    frame: frame.number,
+                    conn,
+                    user: session.and_then(|s| s.user.clone()),
+                    share: tree.map(|t| t.path.clone()),
+                    path,
+                    action,
+                    status: NtStatus::SUCCESS,
+                    count: 1,
+                },
+            );
+            return;
+        }
+
+        if header.status() == NtStatus::PENDING {
+            return;
+        }
+        let Some(mut entry) = self.pending.remove(&key) else {
+            return;
+        };
+        entry.status = header.status();
+
+        match (&mut entry.action, body) {
+            // the request only tells what the client asked for
+            (
+                AuditAction::Open | AuditAction::Overwrite | AuditAction::Create,
+                Body::CreateResponse(res),
+            ) => {
+                entry.action = match res.action {
+                    FILE_CREATED => AuditAction::Create,
+                    FILE_SUPERSEDED | FILE_OVERWRITTEN => AuditAction::Overwrite,
+                    _ => AuditAction::Open,
+                };
+            }
+            (AuditAction::Read(bytes), Body::ReadResponse(res)) => *bytes = res.data.len() as u64,
+            (AuditAction::Write(bytes), Body::WriteResponse(res)) => *bytes = res.count.into(),
+            _ => {}
+        }
+
+        if let Some(last) = self.last.get(&conn).and_then(|i| self.entries.get_mut(*i)) {
+            let same_open = last.path == entry.path && last.status == entry.status;
+            match (&mut last.action, &entry.action) {
+                (AuditAction::Read(total), AuditAction::Read(bytes))
+                | (AuditAction::Write(total), AuditAction::Write(bytes))
+                    if same_open && entry.status.is_success() =>
+                {
+                    *total += bytes;
+                    last.count += 1;
+                    return;
+                }
+                _ => {}
+            }
+        }
+        self.last.insert(conn, self.entries.len());
+        self.entries.push(entry);
+    }
+
+    /// In the order the results came in.
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.entries
+    }
+}
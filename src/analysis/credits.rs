@@ -0,0 +1,247 @@
+//! Credit window accounting per connection ([MS-SMB2] 3.2.4.1.5 and 3.3.1.1), to spot clients
+//! stalled on credits and requests sent outside of the granted sequence window.
+use chrono::{NaiveTime, TimeDelta};
+use std::collections::{BTreeSet, HashMap};
+
+use super::{ConnKey, Frame};
+use crate::smb::{opcodes::Opcodes, status::NtStatus, SMBHeader};
+
+/// How long the server has to keep granting less than asked for before it gets reported.
+const UNDERGRANT_PERIOD: TimeDelta = TimeDelta::seconds(1);
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CreditEvent {
+    /// The client used its last credit with other requests still in flight, it can't send
+    /// anything else until a response grants more.
+    Exhausted { outstanding: usize },
+    /// A response granted credits again after the client ran out.
+    Replenished {
+        granted: u16,
+        /// Time the client had no credits.
+        blocked: TimeDelta,
+        since_frame: usize,
+    },
+    /// Responses have been granting less than requested for `UNDERGRANT_PERIOD` or more.
+    Undergranted {
+        since_frame: usize,
+        responses: usize,
+        requested: u64,
+        granted: u64,
+    },
+    /// A request used MessageIds the server never granted, or used them twice.
+    OutOfWindow {
+        msg_id: u64,
+        charge: u16,
+        low: u64,
+        high: u64,
+        reused: bool,
+    },
+}
+
+/// Running counters of a connection.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CreditState {
+    /// Whether the connection was seen from its NEGOTIATE, otherwise the window bounds and the
+    /// credits granted are only a guess and neither exhaustion nor out of window requests are
+    /// reported.
+    pub synced: bool,
+    pub granted: u64,
+    pub consumed: u64,
+    /// Lowest MessageId not yet used.
+    pub low: u64,
+    /// First MessageId past the granted window.
+    pub high: u64,
+    pub in_flight: usize,
+    pub max_in_flight: usize,
+    pub exhausted: usize,
+    pub blocked: TimeDelta,
+    pub out_of_window: usize,
+    pub undergranted: usize,
+}
+
+impl CreditState {
+    fn new(synced: bool) -> Self {
+        Self {
+            synced,
+            // every connection starts with the single credit NEGOTIATE uses
+            granted: 1,
+            consumed: 0,
+            low: 0,
+            high: 1,
+            in_flight: 0,
+            max_in_flight: 0,
+            exhausted: 0,
+            blocked: TimeDelta::zero(),
+            out_of_window: 0,
+            undergranted: 0,
+        }
+    }
+
+    /// Credits the client can still spend.
+    pub fn available(&self) -> u64 {
+        self.granted.saturating_sub(self.consumed)
+    }
+}
+
+struct Undergrant {
+    since_frame: usize,
+    since: NaiveTime,
+    responses: usize,
+    requested: u64,
+    granted: u64,
+    reported: bool,
+}
+
+#[derive(Default)]
+struct Conn {
+    state: Option<CreditState>,
+    /// MessageIds at or above `low` that were already used.
+    used: BTreeSet<u64>,
+    /// CreditRequest of each request still waiting for its final response.
+    asked: HashMap<u64, u16>,
+    /// When and where the client ran out of credits.
+    exhausted: Option<(usize, NaiveTime)>,
+    undergrant: Option<Undergrant>,
+}
+
+#[derive(Default)]
+pub struct CreditTracker {
+    conns: HashMap<ConnKey, Conn>,
+}
+
+impl CreditTracker {
+    pub fn feed(&mut self, frame: &Frame, header: &SMBHeader) -> Vec<CreditEvent> {
+        let conn = self.conns.entry(frame.conn).or_default();
+        let state = conn.state.get_or_insert_with(|| {
+            let mut state = CreditState::new(
+                header.opcode == Opcodes::NegotiateProtocol && header.cmd_seq == 0,
+            );
+            if !state.synced {
+                state.low = header.cmd_seq;
+                state.high = header.cmd_seq;
+            } else if header.is_response() {
+                // the NEGOTIATE took MessageId 0 without being seen as an SMB2 request, it was
+                // an SMB1 negotiate offering SMB2 (or it isn't in the capture)
+                state.low = 1;
+                state.consumed = 1;
+            }
+            state
+        });
+        let mut events = vec![];
+
+        if !header.is_response() {
+            // CANCEL reuses the MessageId of the request it cancels
+            if header.opcode == Opcodes::Cancel {
+                return events;
+            }
+            // CreditCharge is 0 on 2.0.2, where every request takes a single credit
+            let charge = header.cred_charge.max(1);
+            let ids = header.cmd_seq..header.cmd_seq.saturating_add(charge.into());
+            let reused = ids
+                .clone()
+                .any(|id| id < state.low || conn.used.contains(&id));
+            if !state.synced {
+                // without the start of the connection, all we can do is follow the client
+                state.high = state.high.max(ids.end);
+                state.granted = state.granted.max(state.consumed + u64::from(charge));
+            } else if reused || ids.end > state.high {
+                state.out_of_window += 1;
+                events.push(CreditEvent::OutOfWindow {
+                    msg_id: header.cmd_seq,
+                    charge,
+                    low: state.low,
+                    high: state.high,
+                    reused,
+                });
+            }
+
+            conn.used.extend(ids);
+            while conn.used.remove(&state.low) {
+                state.low += 1;
+            }
+            state.consumed += u64::from(charge);
+            conn.asked.insert(header.cmd_seq, header.cred_req_res);
+            state.in_flight += 1;
+            state.max_in_flight = state.max_in_flight.max(state.in_flight);
+
+            // a client doing one thing at a time on a single credit isn't held back by it, only
+            // report clients that were pipelining when they hit the wall
+            if state.synced
+                && state.in_flight > 1
+                && state.available() == 0
+                && conn.exhausted.is_none()
+            {
+                state.exhausted += 1;
+                conn.exhausted = Some((frame.number, frame.time));
+                events.push(CreditEvent::Exhausted {
+                    outstanding: state.in_flight,
+                });
+            }
+            return events;
+        }
+
+        if header.is_unsolicited() {
+            return events;
+        }
+
+        let granted = header.cred_req_res;
+        state.granted += u64::from(granted);
+        state.high += u64::from(granted);
+        let interim = header.status() == NtStatus::PENDING;
+        let asked = if interim {
+            conn.asked.get(&header.cmd_seq).copied()
+        } else {
+            let asked = conn.asked.remove(&header.cmd_seq);
+            if asked.is_some() {
+                state.in_flight -= 1;
+            }
+            asked
+        };
+
+        if granted > 0 {
+            if let Some((since_frame, since)) = conn.exhausted.take() {
+                state.blocked += frame.since(since);
+                events.push(CreditEvent::Replenished {
+                    granted,
+                    blocked: frame.since(since),
+                    since_frame,
+                });
+            }
+        }
+
+        if let Some(asked) = asked {
+            if granted < asked {
+                let streak = conn.undergrant.get_or_insert(Undergrant {
+                    since_frame: frame.number,
+                    since: frame.time,
+                    responses: 0,
+                    requested: 0,
+                    granted: 0,
+                    reported: false,
+                });
+                streak.responses += 1;
+                streak.requested += u64::from(asked);
+                streak.granted += u64::from(granted);
+                if !streak.reported && frame.since(streak.since) >= UNDERGRANT_PERIOD {
+                    streak.reported = true;
+                    state.undergranted += 1;
+                    events.push(CreditEvent::Undergranted {
+                        since_frame: streak.since_frame,
+                        responses: streak.responses,
+                        requested: streak.requested,
+                        granted: streak.granted,
+                    });
+                }
+            } else {
+                conn.undergrant = None;
+            }
+        }
+        events
+    }
+
+    pub fn states(&self) -> impl Iterator<Item = (&ConnKey, &CreditState)> {
+        self.conns
+            .iter()
+            .filter_map(|(key, conn)| Some((key, conn.state.as_ref()?)))
+    }
+}
@@ -0,0 +1,396 @@
+//! Findings explaining why a share doesn't load, each one pointing at the frames that prove it.
+use chrono::{NaiveTime, TimeDelta};
+use std::collections::HashMap;
+
+use super::{
+    elapsed,
+    matcher::{Match, Matcher},
+    sessions::{SessionEvent, SessionTracker, User},
+    ConnKey, Event, Frame, Severity,
+};
+use crate::{
+    prettify::conn::Direction,
+    smb::{
+        body::{
+            ioctl::FSCTL_VALIDATE_NEGOTIATE_INFO,
+            negotiate::{Dialect, SecurityMode},
+            session::SessionFlags,
+            Body,
+        },
+        flags::Flags,
+        opcodes::Opcodes,
+        status::NtStatus,
+        SMBHeader,
+    },
+    tcp::flags::{Flag, FlagCollection},
+};
+
+/// Requests still unanswered this long before the capture (or the connection) ended are timeouts.
+const RESPONSE_TIMEOUT: TimeDelta = TimeDelta::seconds(5);
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Finding {
+    /// The server answered NEGOTIATE with an error, usually no dialect in common.
+    NegotiateFailed {
+        offered: Vec<Dialect>,
+        status: NtStatus,
+    },
+    /// The server answered an SMB2 NEGOTIATE in SMB1, or only ever spoke SMB1.
+    Smb1OnlyServer,
+    /// The client requires signing and the server doesn't even enable it.
+    SigningNotSupported,
+    /// The server requires signing but the client sends unsigned requests on a user session.
+    UnsignedRequests {
+        count: usize,
+    },
+    /// FSCTL_VALIDATE_NEGOTIATE_INFO failed, `None` when the connection was torn down instead.
+    ValidateNegotiateFailed {
+        status: Option<NtStatus>,
+    },
+    LogonFailed {
+        user: Option<User>,
+        status: NtStatus,
+    },
+    /// The server fell back to a guest session and the client couldn't go on with it (insecure
+    /// guest logons are blocked by default on recent Windows).
+    GuestBlocked,
+    BadNetworkName {
+        path: String,
+    },
+    TreeAccessDenied {
+        path: String,
+        user: Option<User>,
+    },
+    CreateAccessDenied {
+        path: String,
+        user: Option<User>,
+    },
+    /// The server reset the connection right after the NEGOTIATE, `answered` telling whether it
+    /// sent a response first.
+    ResetAfterNegotiate {
+        answered: bool,
+    },
+    NoResponse {
+        opcode: Opcodes,
+        msg_id: u64,
+        waited: TimeDelta,
+    },
+}
+
+impl Finding {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::UnsignedRequests { .. } => Severity::Warning,
+            Self::NoResponse { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Diagnosis {
+    pub finding: Finding,
+    pub conn: ConnKey,
+    /// Evidence, in capture order.
+    pub frames: Vec<usize>,
+}
+
+#[derive(Default)]
+struct ConnState {
+    offered: Vec<Dialect>,
+    client_security: SecurityMode,
+    /// Frame of the NEGOTIATE request and of its response, if any.
+    negotiate: Option<(usize, Option<usize>)>,
+    /// Opcode of the last SMB2 message seen.
+    last_opcode: Option<Opcodes>,
+    smb2_from_server: bool,
+    smb1_from_server: bool,
+    unsigned: Vec<usize>,
+    /// Frame of the response that made a guest session, until something works on it.
+    guest: Option<usize>,
+    /// Pending FSCTL_VALIDATE_NEGOTIATE_INFO request frame.
+    validate: HashMap<u64, usize>,
+    creates: HashMap<u64, String>,
+    /// When the connection was closed or reset.
+    closed: Option<NaiveTime>,
+}
+
+#[derive(Default)]
+pub struct DiagnosisEngine {
+    conns: HashMap<ConnKey, ConnState>,
+    diagnoses: Vec<Diagnosis>,
+}
+
+impl DiagnosisEngine {
+    /// Feeds a message along with what the other trackers had to say about it.
+    pub fn feed(
+        &mut self,
+        frame: &Frame,
+        header: &SMBHeader,
+        body: &Body,
+        events: &[Event],
+        sessions: &SessionTracker,
+    ) {
+        let conn = self.conns.entry(frame.conn).or_default();
+        let mut found = vec![];
+        let request_frame = events.iter().find_map(|e| match e {
+            Event::Match(Match::Response { request, .. }) => Some(request.frame),
+            _ => None,
+        });
+        let evidence = |request: Option<usize>| -> Vec<usize> {
+            request.into_iter().chain([frame.number]).collect()
+        };
+
+        conn.last_opcode = Some(header.opcode);
+        if header.is_response() {
+            conn.smb2_from_server = true;
+        }
+
+        match body {
+            Body::NegotiateRequest(req) => {
+                conn.offered = req.dialects.clone();
+                conn.client_security = req.security_mode;
+                conn.negotiate = Some((frame.number, None));
+            }
+            Body::NegotiateResponse(res) => {
+                if let Some((_, answer)) = &mut conn.negotiate {
+                    *answer = Some(frame.number);
+                }
+                if conn.client_security.contains(SecurityMode::SigningRequired)
+                    && !res.security_mode.contains(SecurityMode::SigningEnabled)
+                {
+                    found.push((Finding::SigningNotSupported, evidence(request_frame)));
+                }
+            }
+            Body::IoctlRequest(req) if req.ctl_code == FSCTL_VALIDATE_NEGOTIATE_INFO => {
+                conn.validate.insert(header.cmd_seq, frame.number);
+            }
+            Body::CreateRequest(req) => {
+                conn.creates.insert(header.cmd_seq, req.name.clone());
+            }
+            _ => {}
+        }
+
+        if header.is_response() && header.status() != NtStatus::PENDING {
+            let path = conn.creates.remove(&header.cmd_seq);
+            let validate = conn.validate.remove(&header.cmd_seq);
+            match header.opcode {
+                Opcodes::NegotiateProtocol if header.nt_status != 0 => found.push((
+                    Finding::NegotiateFailed {
+                        offered: conn.offered.clone(),
+                        status: header.status(),
+                    },
+                    evidence(request_frame),
+                )),
+                Opcodes::Ioctl if header.nt_status != 0 && validate.is_some() => found.push((
+                    Finding::ValidateNegotiateFailed {
+                        status: Some(header.status()),
+                    },
+                    evidence(validate),
+                )),
+                Opcodes::Create if header.status() == NtStatus::ACCESS_DENIED => found.push((
+                    Finding::CreateAccessDenied {
+                        path: path.unwrap_or_default(),
+                        user: user(sessions, frame.conn, header.uid),
+                    },
+                    evidence(request_frame),
+                )),
+                Opcodes::TreeConnect | Opcodes::Create if header.nt_status == 0 => {
+                    conn.guest = None;
+                }
+                _ => {}
+            }
+        }
+
+        // unsigned requests on a session that is supposed to be signed, guest and anonymous
+        // sessions can't sign
+        if !header.is_response()
+            && !header.flags.contains(Flags::FlagsSigned)
+            && !matches!(
+                header.opcode,
+                Opcodes::NegotiateProtocol | Opcodes::SessionSetup | Opcodes::Cancel
+            )
+        {
+            let state = sessions.conn(frame.conn);
+            let session = sessions.session(frame.conn, header.uid);
+            let signed_session = session.is_some_and(|s| {
+                s.established
+                    && !s
+                        .flags
+                        .intersects(SessionFlags::IsGuest | SessionFlags::IsNull)
+            });
+            if state.is_some_and(|s| s.signing_required()) && signed_session {
+                conn.unsigned.push(frame.number);
+            }
+        }
+
+        for event in events {
+            let Event::Session(event) = event else {
+                continue;
+            };
+            match event {
+                SessionEvent::AuthFailed { user, status, .. } => found.push((
+                    Finding::LogonFailed {
+                        user: user.clone(),
+                        status: *status,
+                    },
+                    evidence(request_frame),
+                )),
+                SessionEvent::LoggedOn { flags, .. } if flags.contains(SessionFlags::IsGuest) => {
+                    conn.guest = Some(frame.number);
+                }
+                SessionEvent::TreeConnectFailed { path, status } => {
+                    let finding = match *status {
+                        NtStatus::BAD_NETWORK_NAME => {
+                            Finding::BadNetworkName { path: path.clone() }
+                        }
+                        NtStatus::ACCESS_DENIED => Finding::TreeAccessDenied {
+                            path: path.clone(),
+                            user: user(sessions, frame.conn, header.uid),
+                        },
+                        _ => continue,
+                    };
+                    found.push((finding, evidence(request_frame)));
+                    if let Some(guest) = conn.guest.take() {
+                        found.push((Finding::GuestBlocked, vec![guest, frame.number]));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for (finding, frames) in found {
+            self.push(frame.conn, finding, frames);
+        }
+    }
+
+    /// Every TCP segment, SMB or not, for the resets and teardowns.
+    pub fn feed_tcp(&mut self, frame: &Frame, flags: &FlagCollection) {
+        let reset = flags.is_set(Flag::RST);
+        if !reset && !flags.is_set(Flag::FIN) {
+            return;
+        }
+        let Some(conn) = self.conns.get_mut(&frame.conn) else {
+            return;
+        };
+        if conn.closed.is_some() {
+            return;
+        }
+        conn.closed = Some(frame.time);
+        let mut found = vec![];
+
+        if reset
+            && frame.direction == Direction::RESPONSE
+            && conn.last_opcode == Some(Opcodes::NegotiateProtocol)
+        {
+            if let Some((request, answer)) = conn.negotiate {
+                found.push((
+                    Finding::ResetAfterNegotiate {
+                        answered: answer.is_some(),
+                    },
+                    [Some(request), answer, Some(frame.number)]
+                        .into_iter()
+                        .flatten()
+                        .collect(),
+                ));
+            }
+        }
+        let mut validate: Vec<_> = conn.validate.drain().map(|(_, f)| f).collect();
+        validate.sort();
+        for request in validate {
+            found.push((
+                Finding::ValidateNegotiateFailed { status: None },
+                vec![request, frame.number],
+            ));
+        }
+        // a client giving up on a guest session just hangs up
+        if let Some(guest) = conn.guest.take() {
+            found.push((Finding::GuestBlocked, vec![guest, frame.number]));
+        }
+
+        for (finding, frames) in found {
+            self.push(frame.conn, finding, frames);
+        }
+    }
+
+    /// An SMB1 message, which isn't decoded any further.
+    pub fn feed_smb1(&mut self, frame: &Frame) {
+        let conn = self.conns.entry(frame.conn).or_default();
+        if frame.direction == Direction::RESPONSE && !conn.smb1_from_server {
+            conn.smb1_from_server = true;
+            if !conn.smb2_from_server {
+                let mut frames: Vec<_> = conn.negotiate.map(|(f, _)| f).into_iter().collect();
+                frames.push(frame.number);
+                self.push(frame.conn, Finding::Smb1OnlyServer, frames);
+            }
+        }
+    }
+
+    /// Findings that need the whole capture, `end` being the time of the last packet.
+    pub fn finish(&mut self, matcher: &Matcher, end: NaiveTime) {
+        let mut conns: Vec<_> = self.conns.iter_mut().collect();
+        conns.sort_by_key(|(key, _)| **key);
+        let mut found = vec![];
+        for (key, conn) in conns {
+            if !conn.unsigned.is_empty() {
+                found.push((
+                    *key,
+                    Finding::UnsignedRequests {
+                        count: conn.unsigned.len(),
+                    },
+                    conn.unsigned.iter().take(5).copied().collect(),
+                ));
+            }
+        }
+
+        let mut unmatched: Vec<_> = matcher.unmatched().collect();
+        unmatched.sort_by_key(|(_, request)| request.frame);
+        for (key, request) in unmatched {
+            // watches and blocking locks are meant to stay unanswered
+            if request.interim.is_some() || request.opcode == Opcodes::Notify {
+                continue;
+            }
+            // already explained by another finding
+            if self
+                .diagnoses
+                .iter()
+                .any(|d| d.conn == *key && d.frames.contains(&request.frame))
+            {
+                continue;
+            }
+            let closed = self.conns.get(key).and_then(|c| c.closed);
+            let waited = elapsed(request.time, closed.unwrap_or(end));
+            if closed.is_some() || waited >= RESPONSE_TIMEOUT {
+                found.push((
+                    *key,
+                    Finding::NoResponse {
+                        opcode: request.opcode,
+                        msg_id: request.msg_id,
+                        waited,
+                    },
+                    vec![request.frame],
+                ));
+            }
+        }
+
+        for (conn, finding, frames) in found {
+            self.push(conn, finding, frames);
+        }
+    }
+
+    fn push(&mut self, conn: ConnKey, finding: Finding, frames: Vec<usize>) {
+        self.diagnoses.push(Diagnosis {
+            finding,
+            conn,
+            frames,
+        });
+    }
+
+    pub fn diagnoses(&self) -> &[Diagnosis] {
+        &self.diagnoses
+    }
+}
+
+fn user(sessions: &SessionTracker, conn: ConnKey, session_id: u64) -> Option<User> {
+    sessions.session(conn, session_id)?.user.clone()
+}
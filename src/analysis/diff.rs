@@ -0,0 +1,259 @@
+//! Side by side comparison of two captures of the same scenario ("works on client A, fails on
+//! client B"): operations are aligned by command and the attributes that differ are reported.
+use std::collections::HashMap;
+
+use super::{ConnKey, Frame};
+use crate::{
+    ntlmssp::{Message, Token},
+    smb::{
+        body::{
+            ioctl::ctl_code_name,
+            negotiate::{cipher_name, signing_algorithm_name, NegotiateContext},
+            Body,
+        },
+        opcodes::Opcodes,
+        status::NtStatus,
+        SMBHeader,
+    },
+};
+
+/// How far ahead to look for the next operation of the same command when the two sides don't
+/// line up, enough for a few retries or an extra compound without going quadratic.
+const LOOKAHEAD: usize = 64;
+
+/// One request and its response, reduced to what should be the same in both captures (no
+/// MessageIds, FileIds, GUIDs or times).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Step {
+    /// Frame of the request.
+    pub frame: usize,
+    pub opcode: Opcodes,
+    /// Request then response attributes, in the order they are printed.
+    pub fields: Vec<(&'static str, String)>,
+}
+
+impl Step {
+    fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Divergence {
+    /// Same command on both sides, with different attributes.
+    Differs {
+        a: Step,
+        b: Step,
+        /// Name and values on each side, `None` when the side doesn't have the attribute.
+        fields: Vec<(&'static str, Option<String>, Option<String>)>,
+    },
+    OnlyInA(Step),
+    OnlyInB(Step),
+}
+
+fn contexts(contexts: &[NegotiateContext]) -> String {
+    let names: Vec<String> = contexts.iter().map(|c| format!("{c:?}")).collect();
+    names.join(", ")
+}
+
+fn auth(blob: &[u8]) -> String {
+    match Token::find(blob) {
+        Token::Ntlmssp(Message::Negotiate) => "NTLMSSP_NEGOTIATE".to_owned(),
+        Token::Ntlmssp(Message::Challenge { .. }) => "NTLMSSP_CHALLENGE".to_owned(),
+        Token::Ntlmssp(Message::Authenticate {
+            anonymous: true, ..
+        }) => "NTLMSSP_AUTH anonymous".to_owned(),
+        Token::Ntlmssp(Message::Authenticate { .. }) => "NTLMSSP_AUTH".to_owned(),
+        Token::Ntlmssp(Message::Unknown(kind)) => format!("NTLMSSP {kind}"),
+        Token::Kerberos => "Kerberos".to_owned(),
+        Token::Empty => "none".to_owned(),
+        Token::Other => "other".to_owned(),
+    }
+}
+
+fn request_fields(body: &Body) -> Vec<(&'static str, String)> {
+    match body {
+        Body::NegotiateRequest(req) => vec![
+            ("dialects", format!("{:?}", req.dialects)),
+            ("client security mode", format!("{:?}", req.security_mode)),
+            ("client capabilities", format!("{:?}", req.capabilities)),
+            ("client contexts", contexts(&req.contexts)),
+        ],
+        Body::SessionSetupRequest(req) => vec![
+            ("auth", auth(&req.security_buffer)),
+            ("binding", req.binding().to_string()),
+        ],
+        Body::TreeConnectRequest(req) => vec![("path", req.path.clone())],
+        Body::CreateRequest(req) => {
+            let names: Vec<&str> = req.contexts.iter().map(|c| c.name.as_str()).collect();
+            vec![
+                ("name", req.name.clone()),
+                ("desired access", format!("0x{:08x}", req.desired_access)),
+                ("share access", format!("0x{:x}", req.share_access)),
+                ("disposition", req.disposition.to_string()),
+                ("options", format!("0x{:08x}", req.options)),
+                ("oplock", format!("0x{:02x}", req.oplock_level)),
+                ("create contexts", names.join(", ")),
+            ]
+        }
+        Body::IoctlRequest(req) => vec![(
+            "ctl code",
+            ctl_code_name(req.ctl_code)
+                .map(str::to_owned)
+                .unwrap_or_else(|| format!("0x{:08x}", req.ctl_code)),
+        )],
+        Body::SetInfoRequest(req) => vec![
+            ("info type", req.info_type.to_string()),
+            ("info class", req.file_info_class.to_string()),
+        ],
+        _ => vec![],
+    }
+}
+
+fn response_fields(body: &Body) -> Vec<(&'static str, String)> {
+    match body {
+        Body::NegotiateResponse(res) => vec![
+            ("dialect", res.dialect.to_string()),
+            ("server security mode", format!("{:?}", res.security_mode)),
+            ("server capabilities", format!("{:?}", res.capabilities)),
+            (
+                "cipher",
+                res.cipher().map(cipher_name).unwrap_or("none").to_owned(),
+            ),
+            (
+                "signing algorithm",
+                res.signing_algorithm()
+                    .map(signing_algorithm_name)
+                    .unwrap_or("none")
+                    .to_owned(),
+            ),
+        ],
+        Body::SessionSetupResponse(res) => {
+            vec![("session flags", format!("{:?}", res.session_flags))]
+        }
+        Body::TreeConnectResponse(res) => vec![
+            ("share type", res.share_type.to_string()),
+            ("share flags", format!("0x{:08x}", res.share_flags)),
+            ("share capabilities", format!("0x{:x}", res.capabilities)),
+            ("maximal access", format!("0x{:08x}", res.maximal_access)),
+        ],
+        Body::CreateResponse(res) => vec![
+            ("create action", res.action.to_string()),
+            ("granted oplock", format!("0x{:02x}", res.oplock_level)),
+        ],
+        _ => vec![],
+    }
+}
+
+/// Every operation of a capture in the order they were sent.
+#[derive(Default)]
+pub struct Transcript {
+    pending: HashMap<(ConnKey, u64), usize>,
+    steps: Vec<Step>,
+}
+
+impl Transcript {
+    pub fn feed(&mut self, frame: &Frame, header: &SMBHeader, body: &Body) {
+        let key = (frame.conn, header.cmd_seq);
+        if !header.is_response() {
+            self.pending.insert(key, self.steps.len());
+            self.steps.push(Step {
+                frame: frame.number,
+                opcode: header.opcode,
+                fields: request_fields(body),
+            });
+            return;
+        }
+        if header.status() == NtStatus::PENDING {
+            return;
+        }
+        if let Some(step) = self
+            .pending
+            .remove(&key)
+            .and_then(|i| self.steps.get_mut(i))
+        {
+            step.fields.extend(response_fields(body));
+            step.fields
+                .push(("status", format!("{:?}", header.status())));
+        }
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+}
+
+/// Where two transcripts diverge. Operations are aligned on the command, when the commands
+/// differ the closest resynchronization point within `LOOKAHEAD` operations wins, the operations
+/// skipped over being reported as only on one side.
+pub fn diff(a: &[Step], b: &[Step]) -> Vec<Divergence> {
+    let mut out = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i].opcode == b[j].opcode {
+            let mut names: Vec<&'static str> = a[i].fields.iter().map(|(n, _)| *n).collect();
+            for (name, _) in &b[j].fields {
+                if !names.contains(name) {
+                    names.push(name);
+                }
+            }
+            let fields: Vec<_> = names
+                .into_iter()
+                .map(|name| {
+                    (
+                        name,
+                        a[i].field(name).map(str::to_owned),
+                        b[j].field(name).map(str::to_owned),
+                    )
+                })
+                .filter(|(_, a, b)| a != b)
+                .collect();
+            if !fields.is_empty() {
+                out.push(Divergence::Differs {
+                    a: a[i].clone(),
+                    b: b[j].clone(),
+                    fields,
+                });
+            }
+            i += 1;
+            j += 1;
+            continue;
+        }
+
+        // skip the side whose next matching command is the closest
+        let in_b = b[j..]
+            .iter()
+            .take(LOOKAHEAD)
+            .position(|s| s.opcode == a[i].opcode);
+        let in_a = a[i..]
+            .iter()
+            .take(LOOKAHEAD)
+            .position(|s| s.opcode == b[j].opcode);
+        match (in_a, in_b) {
+            (Some(skip_a), Some(skip_b)) if skip_a <= skip_b => {
+                out.extend(a[i..i + skip_a].iter().cloned().map(Divergence::OnlyInA));
+                i += skip_a;
+            }
+            (_, Some(skip_b)) => {
+                out.extend(b[j..j + skip_b].iter().cloned().map(Divergence::OnlyInB));
+                j += skip_b;
+            }
+            (Some(skip_a), None) => {
+                out.extend(a[i..i + skip_a].iter().cloned().map(Divergence::OnlyInA));
+                i += skip_a;
+            }
+            (None, None) => {
+                out.push(Divergence::OnlyInA(a[i].clone()));
+                out.push(Divergence::OnlyInB(b[j].clone()));
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    out.extend(a[i..].iter().cloned().map(Divergence::OnlyInA));
+    out.extend(b[j..].iter().cloned().map(Divergence::OnlyInB));
+    out
+}
@@ -0,0 +1,327 @@
+//! Oplock and lease grants and breaks, with how long clients take to acknowledge the breaks (a
+//! slow ack stalls the CREATE of whoever triggered the break).
+use chrono::{NaiveTime, TimeDelta};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    net::{IpAddr, SocketAddr},
+};
+
+use super::{opens::OpenTracker, ConnKey, Frame};
+use crate::smb::{
+    body::{
+        oplock::{oplock_level_name, LeaseContext, LeaseState},
+        Body,
+    },
+    status::NtStatus,
+    types::{FileId, Guid},
+    SMBHeader,
+};
+
+const SMB2_OPLOCK_LEVEL_LEASE: u8 = 0xFF;
+
+/// What is being broken, a lease (by LeaseKey) or an oplock (by FileId).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Holder {
+    Lease(Guid),
+    Oplock(FileId),
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum Caching {
+    Lease(LeaseState),
+    Oplock(u8),
+}
+
+impl Debug for Caching {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Lease(state) => write!(f, "{state:?}"),
+            Self::Oplock(level) => write!(f, "{}", oplock_level_name(*level)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum LeaseEvent {
+    Granted {
+        holder: Holder,
+        path: String,
+        client: SocketAddr,
+        requested: Caching,
+        granted: Caching,
+        epoch: Option<u16>,
+    },
+    Break {
+        holder: Holder,
+        path: String,
+        client: SocketAddr,
+        from: Caching,
+        to: Caching,
+        ack_required: bool,
+        /// Clients with a CREATE on the same path still waiting for a response, the likely cause
+        /// of the break and who is stalled until it is acknowledged.
+        waiting: Vec<SocketAddr>,
+    },
+    Acked {
+        holder: Holder,
+        path: String,
+        client: SocketAddr,
+        state: Caching,
+        delay: Option<TimeDelta>,
+    },
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct StateChange {
+    pub frame: usize,
+    pub time: NaiveTime,
+    pub state: Caching,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BreakRecord {
+    pub frame: usize,
+    pub time: NaiveTime,
+    pub from: Caching,
+    pub to: Caching,
+    /// Frame and delay of the acknowledgment, `None` if it never came.
+    pub ack: Option<(usize, TimeDelta)>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Held {
+    pub path: String,
+    pub client: SocketAddr,
+    pub history: Vec<StateChange>,
+    pub breaks: Vec<BreakRecord>,
+}
+
+struct PendingCreate {
+    path: String,
+    requested: Caching,
+}
+
+/// Leases are per client (ClientGuid really, but the address is close enough) and LeaseKey,
+/// oplocks per open.
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+enum Key {
+    Lease(IpAddr, Guid),
+    Oplock(ConnKey, FileId),
+}
+
+impl Key {
+    fn new(conn: ConnKey, holder: Holder) -> Self {
+        match holder {
+            Holder::Lease(key) => Self::Lease(conn.client.ip(), key),
+            Holder::Oplock(file_id) => Self::Oplock(conn, file_id),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct LeaseTracker {
+    pending: HashMap<(ConnKey, u64), PendingCreate>,
+    held: HashMap<Key, Held>,
+}
+
+impl LeaseTracker {
+    pub fn feed(
+        &mut self,
+        frame: &Frame,
+        header: &SMBHeader,
+        body: &Body,
+        opens: &OpenTracker,
+    ) -> Vec<LeaseEvent> {
+        let conn = frame.conn;
+        let key = (conn, header.cmd_seq);
+        match body {
+            Body::CreateRequest(req) => {
+                let requested = match LeaseContext::find(&req.contexts) {
+                    Some(lease) if req.oplock_level == SMB2_OPLOCK_LEVEL_LEASE => {
+                        Caching::Lease(lease.state)
+                    }
+                    _ => Caching::Oplock(req.oplock_level),
+                };
+                self.pending.insert(
+                    key,
+                    PendingCreate {
+                        path: req.name.clone(),
+                        requested,
+                    },
+                );
+                vec![]
+            }
+            Body::CreateResponse(res) => {
+                let Some(create) = self.pending.remove(&key) else {
+                    return vec![];
+                };
+                let (holder, granted, epoch) = match LeaseContext::find(&res.contexts) {
+                    Some(lease) if res.oplock_level == SMB2_OPLOCK_LEVEL_LEASE => (
+                        Holder::Lease(lease.lease_key),
+                        Caching::Lease(lease.state),
+                        lease.epoch,
+                    ),
+                    _ if res.oplock_level != 0 => (
+                        Holder::Oplock(res.file_id),
+                        Caching::Oplock(res.oplock_level),
+                        None,
+                    ),
+                    _ => return vec![],
+                };
+
+                let held = self
+                    .held
+                    .entry(Key::new(conn, holder))
+                    .or_insert_with(|| Held {
+                        path: create.path.clone(),
+                        client: conn.client,
+                        history: vec![],
+                        breaks: vec![],
+                    });
+                held.history.push(StateChange {
+                    frame: frame.number,
+                    time: frame.time,
+                    state: granted,
+                });
+                vec![LeaseEvent::Granted {
+                    holder,
+                    path: create.path,
+                    client: conn.client,
+                    requested: create.requested,
+                    granted,
+                    epoch,
+                }]
+            }
+            Body::LeaseBreakNotification(notif) => {
+                let holder = Holder::Lease(notif.lease_key);
+                let from = Caching::Lease(notif.current_state);
+                let to = Caching::Lease(notif.new_state);
+                self.on_break(frame, holder, from, to, notif.flags & 0x01 != 0, opens)
+            }
+            Body::OplockBreak(brk) if header.is_response() && header.is_unsolicited() => {
+                let holder = Holder::Oplock(brk.file_id);
+                let from = self
+                    .held
+                    .get(&Key::new(conn, holder))
+                    .and_then(|h| h.history.last())
+                    .map(|c| c.state)
+                    .unwrap_or(Caching::Oplock(0));
+                let to = Caching::Oplock(brk.oplock_level);
+                self.on_break(frame, holder, from, to, true, opens)
+            }
+            Body::LeaseBreakAck(ack) if !header.is_response() => self.on_ack(
+                frame,
+                Holder::Lease(ack.lease_key),
+                Caching::Lease(ack.state),
+            ),
+            Body::OplockBreak(ack) if !header.is_response() => self.on_ack(
+                frame,
+                Holder::Oplock(ack.file_id),
+                Caching::Oplock(ack.oplock_level),
+            ),
+            _ => {
+                if header.is_response() && header.status() != NtStatus::PENDING {
+                    self.pending.remove(&key);
+                }
+                vec![]
+            }
+        }
+    }
+
+    fn get_mut(&mut self, conn: ConnKey, holder: Holder) -> Option<&mut Held> {
+        self.held.get_mut(&Key::new(conn, holder))
+    }
+
+    fn on_break(
+        &mut self,
+        frame: &Frame,
+        holder: Holder,
+        from: Caching,
+        to: Caching,
+        ack_required: bool,
+        opens: &OpenTracker,
+    ) -> Vec<LeaseEvent> {
+        let conn = frame.conn;
+        let fallback_path = match holder {
+            Holder::Oplock(file_id) => opens.path(conn, file_id),
+            Holder::Lease(key) => format!("<lease {key}>"),
+        };
+        let held = self.get_mut(conn, holder);
+        let (path, client) = match held {
+            Some(held) => {
+                held.breaks.push(BreakRecord {
+                    frame: frame.number,
+                    time: frame.time,
+                    from,
+                    to,
+                    ack: None,
+                });
+                if !ack_required {
+                    held.history.push(StateChange {
+                        frame: frame.number,
+                        time: frame.time,
+                        state: to,
+                    });
+                }
+                (held.path.clone(), held.client)
+            }
+            None => (fallback_path, conn.client),
+        };
+
+        let waiting = self
+            .pending
+            .iter()
+            .filter(|((c, _), create)| c.client != client && create.path == path)
+            .map(|((c, _), _)| c.client)
+            .collect();
+
+        vec![LeaseEvent::Break {
+            holder,
+            path,
+            client,
+            from,
+            to,
+            ack_required,
+            waiting,
+        }]
+    }
+
+    fn on_ack(&mut self, frame: &Frame, holder: Holder, state: Caching) -> Vec<LeaseEvent> {
+        let conn = frame.conn;
+        let Some(held) = self.get_mut(conn, holder) else {
+            return vec![LeaseEvent::Acked {
+                holder,
+                path: String::new(),
+                client: conn.client,
+                state,
+                delay: None,
+            }];
+        };
+
+        let mut delay = None;
+        if let Some(brk) = held.breaks.iter_mut().rev().find(|b| b.ack.is_none()) {
+            let d = frame.since(brk.time);
+            brk.ack = Some((frame.number, d));
+            delay = Some(d);
+        }
+        held.history.push(StateChange {
+            frame: frame.number,
+            time: frame.time,
+            state,
+        });
+
+        vec![LeaseEvent::Acked {
+            holder,
+            path: held.path.clone(),
+            client: held.client,
+            state,
+            delay,
+        }]
+    }
+
+    /// Every lease and oplock seen, with its state over time and breaks.
+    pub fn held(&self) -> impl Iterator<Item = &Held> {
+        self.held.values()
+    }
+}
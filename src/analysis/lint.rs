@@ -0,0 +1,261 @@
+//! Conformance checks of every message against the MUST/SHOULD rules of [MS-SMB2], to point
+//! implementations at the exact rule they break.
+use std::collections::HashMap;
+
+use super::{sessions::SessionTracker, ConnKey, Frame, Severity};
+use crate::{
+    prettify::conn::Direction,
+    smb::{
+        body::{self, negotiate::Dialect, Body},
+        flags::Flags,
+        opcodes::Opcodes,
+        SMBHeader,
+    },
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Rule {
+    pub id: &'static str,
+    pub severity: Severity,
+    /// [MS-SMB2] section the rule comes from.
+    pub section: &'static str,
+    pub text: &'static str,
+}
+
+pub const HDR_STRUCTURE_SIZE: Rule = Rule {
+    id: "HDR-001",
+    severity: Severity::Error,
+    section: "2.2.1",
+    text: "StructureSize MUST be 64",
+};
+pub const HDR_NEGOTIATE_SESSION_ID: Rule = Rule {
+    id: "HDR-002",
+    severity: Severity::Error,
+    section: "2.2.1.2",
+    text: "SessionId MUST be 0 for NEGOTIATE requests and responses",
+};
+pub const HDR_TREE_CONNECT_TREE_ID: Rule = Rule {
+    id: "HDR-003",
+    severity: Severity::Error,
+    section: "2.2.1.2",
+    text: "TreeId MUST be 0 for TREE_CONNECT requests",
+};
+pub const HDR_TREE_ID_SHOULD_BE_ZERO: Rule = Rule {
+    id: "HDR-004",
+    severity: Severity::Warning,
+    section: "2.2.1.2",
+    text: "TreeId SHOULD be 0 for NEGOTIATE, SESSION_SETUP, LOGOFF, ECHO and CANCEL",
+};
+pub const HDR_UNSIGNED_SIGNATURE: Rule = Rule {
+    id: "HDR-005",
+    severity: Severity::Error,
+    section: "2.2.1.2",
+    text: "Signature MUST be 0 if the message is not signed",
+};
+pub const HDR_RESPONSE_FLAG: Rule = Rule {
+    id: "HDR-006",
+    severity: Severity::Error,
+    section: "2.2.1.2",
+    text: "SMB2_FLAGS_SERVER_TO_REDIR MUST be set on responses and MUST NOT be set on requests",
+};
+pub const HDR_NEXT_COMMAND_ALIGNMENT: Rule = Rule {
+    id: "HDR-007",
+    severity: Severity::Error,
+    section: "2.2.1.2",
+    text: "NextCommand MUST be the offset of an 8-byte aligned SMB2 header",
+};
+pub const HDR_CREDIT_CHARGE_202: Rule = Rule {
+    id: "HDR-008",
+    severity: Severity::Error,
+    section: "2.2.1.2",
+    text: "CreditCharge MUST be 0 in the SMB 2.0.2 dialect",
+};
+pub const HDR_PRIORITY_311: Rule = Rule {
+    id: "HDR-009",
+    severity: Severity::Warning,
+    section: "2.2.1.2",
+    text: "SMB2_FLAGS_PRIORITY_MASK is only valid for the SMB 3.1.1 dialect",
+};
+pub const HDR_ASYNC_REQUEST: Rule = Rule {
+    id: "HDR-010",
+    severity: Severity::Warning,
+    section: "3.2.4.24",
+    text: "only CANCEL requests use the ASYNC header",
+};
+pub const NEG_DIALECT_NOT_OFFERED: Rule = Rule {
+    id: "NEG-001",
+    severity: Severity::Error,
+    section: "3.3.5.4",
+    text: "the server MUST select a dialect from the client's Dialects array",
+};
+pub const BODY_MALFORMED: Rule = Rule {
+    id: "BODY-001",
+    severity: Severity::Error,
+    section: "2.2",
+    text: "the message body MUST have the StructureSize and buffer offsets of its command",
+};
+
+pub const RULES: &[&Rule] = &[
+    &HDR_STRUCTURE_SIZE,
+    &HDR_NEGOTIATE_SESSION_ID,
+    &HDR_TREE_CONNECT_TREE_ID,
+    &HDR_TREE_ID_SHOULD_BE_ZERO,
+    &HDR_UNSIGNED_SIGNATURE,
+    &HDR_RESPONSE_FLAG,
+    &HDR_NEXT_COMMAND_ALIGNMENT,
+    &HDR_CREDIT_CHARGE_202,
+    &HDR_PRIORITY_311,
+    &HDR_ASYNC_REQUEST,
+    &NEG_DIALECT_NOT_OFFERED,
+    &BODY_MALFORMED,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub rule: &'static Rule,
+    pub frame: usize,
+    pub conn: ConnKey,
+    /// What was found instead.
+    pub detail: String,
+}
+
+#[derive(Default)]
+pub struct Linter {
+    offered: HashMap<ConnKey, Vec<Dialect>>,
+    violations: Vec<Violation>,
+}
+
+impl Linter {
+    pub fn feed(
+        &mut self,
+        frame: &Frame,
+        header: &SMBHeader,
+        body: &Body,
+        sessions: &SessionTracker,
+    ) -> Vec<Violation> {
+        let mut found = vec![];
+        let mut violate = |rule: &'static Rule, detail: String| {
+            found.push(Violation {
+                rule,
+                frame: frame.number,
+                conn: frame.conn,
+                detail,
+            })
+        };
+        let response = header.is_response();
+        let dialect = sessions.conn(frame.conn).and_then(|c| c.dialect);
+
+        if header.hlen != 64 {
+            violate(
+                &HDR_STRUCTURE_SIZE,
+                format!("StructureSize {}", header.hlen),
+            );
+        }
+        if header.opcode == Opcodes::NegotiateProtocol && header.uid != 0 {
+            violate(
+                &HDR_NEGOTIATE_SESSION_ID,
+                format!("SessionId 0x{:x}", header.uid),
+            );
+        }
+        if let Some(tree_id) = header.tree_id().filter(|tid| *tid != 0) {
+            match header.opcode {
+                Opcodes::TreeConnect if !response => {
+                    violate(&HDR_TREE_CONNECT_TREE_ID, format!("TreeId 0x{tree_id:x}"))
+                }
+                Opcodes::NegotiateProtocol
+                | Opcodes::SessionSetup
+                | Opcodes::SessionLogoff
+                | Opcodes::KeepAlive
+                | Opcodes::Cancel => violate(
+                    &HDR_TREE_ID_SHOULD_BE_ZERO,
+                    format!("TreeId 0x{tree_id:x} on {:?}", header.opcode),
+                ),
+                _ => {}
+            }
+        }
+        if !header.flags.contains(Flags::FlagsSigned) && header.signature != 0 {
+            violate(
+                &HDR_UNSIGNED_SIGNATURE,
+                format!("Signature {:032x}", header.signature),
+            );
+        }
+        match frame.direction {
+            Direction::RESPONSE if !response => {
+                violate(&HDR_RESPONSE_FLAG, "request sent by the server".to_owned())
+            }
+            Direction::REQUEST if response => {
+                violate(&HDR_RESPONSE_FLAG, "response sent by the client".to_owned())
+            }
+            _ => {}
+        }
+        if !header.chain_offset.is_multiple_of(8) {
+            violate(
+                &HDR_NEXT_COMMAND_ALIGNMENT,
+                format!("NextCommand {}", header.chain_offset),
+            );
+        }
+        if dialect == Some(Dialect::SMB202) && header.cred_charge != 0 {
+            violate(
+                &HDR_CREDIT_CHARGE_202,
+                format!("CreditCharge {}", header.cred_charge),
+            );
+        }
+        if dialect.is_some_and(|d| d != Dialect::SMB311)
+            && header.flags.intersects(Flags::FlagsPriorityMask)
+        {
+            violate(
+                &HDR_PRIORITY_311,
+                format!(
+                    "priority {} on {}",
+                    (header.flags.bits() >> 4) & 7,
+                    dialect.unwrap_or_default()
+                ),
+            );
+        }
+        if !response && header.is_async() && header.opcode != Opcodes::Cancel {
+            violate(&HDR_ASYNC_REQUEST, format!("{:?} request", header.opcode));
+        }
+
+        match body {
+            Body::NegotiateRequest(req) => {
+                self.offered.insert(frame.conn, req.dialects.clone());
+            }
+            Body::NegotiateResponse(res) => {
+                let offered = self.offered.get(&frame.conn);
+                if let Some(offered) = offered.filter(|offered| {
+                    res.dialect != Dialect::WILDCARD && !offered.contains(&res.dialect)
+                }) {
+                    violate(
+                        &NEG_DIALECT_NOT_OFFERED,
+                        format!("{} selected, {offered:?} offered", res.dialect),
+                    );
+                }
+            }
+            _ => {}
+        }
+
+        self.violations.extend(found.iter().cloned());
+        found
+    }
+
+    /// A message whose body didn't decode.
+    pub fn feed_malformed(
+        &mut self,
+        frame: &Frame,
+        header: &SMBHeader,
+        err: &body::Error,
+    ) -> Violation {
+        let violation = Violation {
+            rule: &BODY_MALFORMED,
+            frame: frame.number,
+            conn: frame.conn,
+            detail: format!("{:?} {err:?}", header.opcode),
+        };
+        self.violations.push(violation.clone());
+        violation
+    }
+
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+}
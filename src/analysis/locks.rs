@@ -0,0 +1,179 @@
+//! Byte-range lock table, so lock conflicts can be blamed on whoever holds the range.
+use std::{collections::HashMap, net::SocketAddr};
+
+use super::{opens::OpenTracker, sessions::SessionTracker, ConnKey, FileKey, Frame};
+use crate::smb::{
+    body::{lock::LockElement, lock::LockFlags, Body},
+    status::NtStatus,
+    types::FileId,
+    SMBHeader,
+};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct HeldLock {
+    pub client: SocketAddr,
+    pub file_id: FileId,
+    pub offset: u64,
+    pub length: u64,
+    pub exclusive: bool,
+    /// Frame of the LOCK response that granted it.
+    pub frame: usize,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum LockEvent {
+    /// A LOCK request (or a READ/WRITE, for STATUS_FILE_LOCK_CONFLICT) was refused, `holders`
+    /// are the locks from other opens overlapping the requested ranges.
+    Conflict {
+        /// `\\server\share` of the tree, if its TREE_CONNECT was captured.
+        share: Option<String>,
+        path: String,
+        client: SocketAddr,
+        status: NtStatus,
+        ranges: Vec<(u64, u64)>,
+        holders: Vec<HeldLock>,
+    },
+}
+
+enum Pending {
+    Lock(FileId, Vec<LockElement>),
+    Io(FileId, u64, u64),
+}
+
+#[derive(Default)]
+pub struct LockTracker {
+    /// Requests waiting for their response, with the share they were sent to: async responses
+    /// carry no TreeId.
+    pending: HashMap<(ConnKey, u64), (Option<String>, Pending)>,
+    table: HashMap<FileKey, Vec<HeldLock>>,
+}
+
+impl LockTracker {
+    pub fn feed(
+        &mut self,
+        frame: &Frame,
+        header: &SMBHeader,
+        body: &Body,
+        opens: &OpenTracker,
+        sessions: &SessionTracker,
+    ) -> Vec<LockEvent> {
+        let conn = frame.conn;
+        let key = (conn, header.cmd_seq);
+        let share = || sessions.share(conn, header);
+        match body {
+            Body::LockRequest(req) => {
+                self.pending.insert(
+                    key,
+                    (share(), Pending::Lock(req.file_id, req.locks.clone())),
+                );
+                return vec![];
+            }
+            Body::ReadRequest(req) => {
+                self.pending.insert(
+                    key,
+                    (
+                        share(),
+                        Pending::Io(req.file_id, req.offset, req.length.into()),
+                    ),
+                );
+                return vec![];
+            }
+            Body::WriteRequest(req) => {
+                self.pending.insert(
+                    key,
+                    (
+                        share(),
+                        Pending::Io(req.file_id, req.offset, req.data.len() as u64),
+                    ),
+                );
+                return vec![];
+            }
+            Body::CloseRequest(req) => {
+                let path = opens.path(conn, req.file_id);
+                if let Some(locks) = self.table.get_mut(&(conn.server.ip(), share(), path)) {
+                    locks.retain(|l| !(l.client == conn.client && l.file_id == req.file_id));
+                }
+                return vec![];
+            }
+            _ => {}
+        }
+
+        if !header.is_response() || header.status() == NtStatus::PENDING {
+            return vec![];
+        }
+        let Some((share, pending)) = self.pending.remove(&key) else {
+            return vec![];
+        };
+
+        let (file_id, ranges) = match &pending {
+            Pending::Lock(file_id, locks) => (
+                *file_id,
+                locks.iter().map(|l| (l.offset, l.length)).collect(),
+            ),
+            Pending::Io(file_id, offset, length) => (*file_id, vec![(*offset, *length)]),
+        };
+        let path = opens.path(conn, file_id);
+        let locks = self
+            .table
+            .entry((conn.server.ip(), share.clone(), path.clone()))
+            .or_default();
+
+        match (header.status(), pending) {
+            (NtStatus::SUCCESS, Pending::Lock(file_id, elements)) => {
+                for el in elements {
+                    if el.flags.contains(LockFlags::Unlock) {
+                        if let Some(i) = locks.iter().position(|l| {
+                            l.client == conn.client
+                                && l.file_id == file_id
+                                && l.offset == el.offset
+                                && l.length == el.length
+                        }) {
+                            locks.remove(i);
+                        }
+                    } else {
+                        locks.push(HeldLock {
+                            client: conn.client,
+                            file_id,
+                            offset: el.offset,
+                            length: el.length,
+                            exclusive: el.flags.contains(LockFlags::ExclusiveLock),
+                            frame: frame.number,
+                        });
+                    }
+                }
+                vec![]
+            }
+            (NtStatus::LOCK_NOT_GRANTED | NtStatus::FILE_LOCK_CONFLICT, _) => {
+                let holders = locks
+                    .iter()
+                    .filter(|l| !(l.client == conn.client && l.file_id == file_id))
+                    .filter(|l| {
+                        ranges.iter().any(|(off, len)| {
+                            LockElement {
+                                offset: l.offset,
+                                length: l.length,
+                                flags: LockFlags::empty(),
+                            }
+                            .overlaps(*off, *len)
+                        })
+                    })
+                    .cloned()
+                    .collect();
+                vec![LockEvent::Conflict {
+                    share,
+                    path,
+                    client: conn.client,
+                    status: header.status(),
+                    ranges,
+                    holders,
+                }]
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Locks still held, per server, share and path.
+    pub fn held(&self) -> impl Iterator<Item = (&FileKey, &Vec<HeldLock>)> {
+        self.table.iter().filter(|(_, locks)| !locks.is_empty())
+    }
+}
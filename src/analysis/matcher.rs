@@ -0,0 +1,123 @@
+//! Pairs responses with their request by MessageId, per connection.
+use chrono::{NaiveTime, TimeDelta};
+use std::collections::{BTreeMap, HashMap};
+
+use super::{ConnKey, Frame};
+use crate::smb::{opcodes::Opcodes, status::NtStatus, SMBHeader};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Outstanding {
+    pub frame: usize,
+    pub time: NaiveTime,
+    pub opcode: Opcodes,
+    pub msg_id: u64,
+    /// SessionId and TreeId of the request, async responses don't carry the TreeId.
+    pub session_id: u64,
+    pub tree_id: u32,
+    /// Frame of the interim STATUS_PENDING response and the AsyncId it assigned, if any.
+    pub interim: Option<(usize, u64)>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Match {
+    Request,
+    /// A CANCEL, or a notification not sent in response to anything.
+    Unsolicited,
+    /// Interim response of an async operation, the final response is still to come.
+    Interim {
+        request: Outstanding,
+    },
+    Response {
+        request: Outstanding,
+        elapsed: TimeDelta,
+    },
+    /// A response for a MessageId that no captured request used, usually because the capture
+    /// started after the request was sent.
+    Orphan,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Orphan {
+    pub frame: usize,
+    pub conn: ConnKey,
+    pub opcode: Opcodes,
+    pub msg_id: u64,
+}
+
+#[derive(Default)]
+pub struct Matcher {
+    outstanding: HashMap<ConnKey, BTreeMap<u64, Outstanding>>,
+    orphans: Vec<Orphan>,
+}
+
+impl Matcher {
+    pub fn feed(&mut self, frame: &Frame, header: &SMBHeader) -> Match {
+        let pending = self.outstanding.entry(frame.conn).or_default();
+
+        if !header.is_response() {
+            if header.opcode == Opcodes::Cancel {
+                return Match::Unsolicited;
+            }
+            pending.insert(
+                header.cmd_seq,
+                Outstanding {
+                    frame: frame.number,
+                    time: frame.time,
+                    opcode: header.opcode,
+                    msg_id: header.cmd_seq,
+                    session_id: header.uid,
+                    tree_id: header.tid,
+                    interim: None,
+                },
+            );
+            return Match::Request;
+        }
+
+        if header.is_unsolicited() {
+            return Match::Unsolicited;
+        }
+
+        if header.status() == NtStatus::PENDING && header.is_async() {
+            return match pending.get_mut(&header.cmd_seq) {
+                Some(request) => {
+                    // AsyncId takes the place of Reserved + TreeId on async headers
+                    let async_id = u64::from(header.pid) | u64::from(header.tid) << 32;
+                    request.interim = Some((frame.number, async_id));
+                    Match::Interim {
+                        request: request.clone(),
+                    }
+                }
+                None => self.orphan(frame, header),
+            };
+        }
+
+        match pending.remove(&header.cmd_seq) {
+            Some(request) => Match::Response {
+                elapsed: frame.since(request.time),
+                request,
+            },
+            None => self.orphan(frame, header),
+        }
+    }
+
+    fn orphan(&mut self, frame: &Frame, header: &SMBHeader) -> Match {
+        self.orphans.push(Orphan {
+            frame: frame.number,
+            conn: frame.conn,
+            opcode: header.opcode,
+            msg_id: header.cmd_seq,
+        });
+        Match::Orphan
+    }
+
+    /// Requests that never got a (final) response.
+    pub fn unmatched(&self) -> impl Iterator<Item = (&ConnKey, &Outstanding)> {
+        self.outstanding
+            .iter()
+            .flat_map(|(conn, pending)| pending.values().map(move |o| (conn, o)))
+    }
+
+    pub fn orphans(&self) -> &[Orphan] {
+        &self.orphans
+    }
+}
@@ -0,0 +1,252 @@
+//! Stateful trackers fed with the decoded messages of a capture, each one keeps its own state per
+//! TCP connection.
+pub mod audit;
+pub mod credits;
+pub mod diagnosis;
+pub mod diff;
+pub mod leases;
+pub mod lint;
+pub mod locks;
+pub mod matcher;
+pub mod notify;
+pub mod opens;
+pub mod ops;
+pub mod pipes;
+pub mod sessions;
+pub mod srt;
+pub mod summary;
+
+use chrono::{NaiveTime, TimeDelta};
+use std::net::{IpAddr, SocketAddr};
+
+use crate::{
+    prettify::conn::Direction,
+    smb::{
+        body::{self, Body},
+        SMBHeader,
+    },
+    tcp::flags::FlagCollection,
+};
+
+/// Well known SMB server ports, direct hosted SMB and NetBIOS session service.
+pub const SMB_PORTS: [u16; 2] = [445, 139];
+
+/// A file by server, share and path, as FileIds are per open and the same path can be on
+/// several shares. The share is `None` when its TREE_CONNECT isn't in the capture.
+pub type FileKey = (IpAddr, Option<String>, String);
+
+/// Identifies a TCP connection regardless of the direction of the packet.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct ConnKey {
+    pub client: SocketAddr,
+    pub server: SocketAddr,
+}
+
+impl ConnKey {
+    /// Figures out which side is the server (the one on an SMB port, or the lower port if none
+    /// is) and the direction of the packet.
+    pub fn from_addrs(src: SocketAddr, dst: SocketAddr) -> (Self, Direction) {
+        let src_is_server = if SMB_PORTS.contains(&dst.port()) {
+            false
+        } else if SMB_PORTS.contains(&src.port()) {
+            true
+        } else {
+            src.port() < dst.port()
+        };
+
+        if src_is_server {
+            (
+                Self {
+                    client: dst,
+                    server: src,
+                },
+                Direction::RESPONSE,
+            )
+        } else {
+            (
+                Self {
+                    client: src,
+                    server: dst,
+                },
+                Direction::REQUEST,
+            )
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// Where and when a message was seen, handed to every tracker along with the message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Frame {
+    /// Index of the packet in the input.
+    pub number: usize,
+    pub time: NaiveTime,
+    pub conn: ConnKey,
+    pub direction: Direction,
+}
+
+/// Time from `from` to `to`. Captures only give the time of day, so `to` more than half a day
+/// before `from` is taken as the capture going past midnight, while a smaller negative
+/// difference is left as it is (timestamps slightly out of order).
+pub fn elapsed(from: NaiveTime, to: NaiveTime) -> TimeDelta {
+    let elapsed = to - from;
+    if elapsed < -TimeDelta::hours(12) {
+        elapsed + TimeDelta::days(1)
+    } else {
+        elapsed
+    }
+}
+
+impl Frame {
+    pub fn new(number: usize, time: NaiveTime, src: SocketAddr, dst: SocketAddr) -> Self {
+        let (conn, direction) = ConnKey::from_addrs(src, dst);
+        Self {
+            number,
+            time,
+            conn,
+            direction,
+        }
+    }
+
+    /// Time from `time` to this frame, see [`elapsed`].
+    pub fn since(&self, time: NaiveTime) -> TimeDelta {
+        elapsed(time, self.time)
+    }
+}
+
+/// Anything a tracker had to say about a message.
+#[derive(Debug, Clone)]
+pub enum Event {
+    Match(matcher::Match),
+    Credit(credits::CreditEvent),
+    Pipe(pipes::PipeEvent),
+    Lock(locks::LockEvent),
+    Lease(leases::LeaseEvent),
+    Notify(notify::NotifyEvent),
+    Session(sessions::SessionEvent),
+    Lint(lint::Violation),
+    /// The message completed an operation (or is one on its own).
+    Operation(ops::Operation),
+}
+
+/// Every tracker, fed in the order they depend on each other.
+#[derive(Default)]
+pub struct Analyzer {
+    pub matcher: matcher::Matcher,
+    pub credits: credits::CreditTracker,
+    pub sessions: sessions::SessionTracker,
+    pub srt: srt::SrtTracker,
+    pub opens: opens::OpenTracker,
+    pub ops: ops::OperationTracker,
+    pub pipes: pipes::PipeTracker,
+    pub locks: locks::LockTracker,
+    pub leases: leases::LeaseTracker,
+    pub notify: notify::NotifyTracker,
+    pub audit: audit::AuditTracker,
+    pub summary: summary::SummaryTracker,
+    pub diagnosis: diagnosis::DiagnosisEngine,
+    pub linter: lint::Linter,
+    /// Time of the last packet.
+    pub end: Option<NaiveTime>,
+}
+
+impl Analyzer {
+    /// Feeds every TCP segment, before its SMB messages if it has any.
+    pub fn feed_tcp(&mut self, frame: &Frame, flags: &FlagCollection) {
+        self.end = Some(frame.time);
+        self.diagnosis.feed_tcp(frame, flags);
+    }
+
+    /// Feeds an SMB1 message, those aren't decoded.
+    pub fn feed_smb1(&mut self, frame: &Frame) {
+        self.diagnosis.feed_smb1(frame);
+    }
+
+    /// Feeds one message, `body` being `Body::Other` if it couldn't be decoded.
+    pub fn feed(&mut self, frame: &Frame, header: &SMBHeader, body: &Body) -> Vec<Event> {
+        let matched = self.matcher.feed(frame, header);
+        if let matcher::Match::Response { request, elapsed } = &matched {
+            self.srt
+                .feed(frame, request, *elapsed, header.status(), &self.sessions);
+        }
+        let mut events = vec![Event::Match(matched)];
+        events.extend(
+            self.credits
+                .feed(frame, header)
+                .into_iter()
+                .map(Event::Credit),
+        );
+        events.extend(
+            self.pipes
+                .feed(frame.conn, header, body)
+                .into_iter()
+                .map(Event::Pipe),
+        );
+        events.extend(
+            self.locks
+                .feed(frame, header, body, &self.opens, &self.sessions)
+                .into_iter()
+                .map(Event::Lock),
+        );
+        events.extend(
+            self.leases
+                .feed(frame, header, body, &self.opens)
+                .into_iter()
+                .map(Event::Lease),
+        );
+        events.extend(
+            self.notify
+                .feed(frame, header, body, &self.opens, &self.sessions)
+                .into_iter()
+                .map(Event::Notify),
+        );
+        self.audit
+            .feed(frame, header, body, &self.opens, &self.sessions);
+        events.extend(
+            self.ops
+                .feed(frame, header, body, &self.opens, &self.sessions)
+                .map(Event::Operation),
+        );
+        // last, so the others can still see the path of an open being closed
+        self.opens.feed(frame, header, body);
+        events.extend(
+            self.sessions
+                .feed(frame, header, body)
+                .into_iter()
+                .map(Event::Session),
+        );
+        self.summary.feed(frame, header, body, &self.sessions);
+        events.extend(
+            self.linter
+                .feed(frame, header, body, &self.sessions)
+                .into_iter()
+                .map(Event::Lint),
+        );
+        self.diagnosis
+            .feed(frame, header, body, &events, &self.sessions);
+        events
+    }
+
+    /// A message whose body didn't decode, fed as `Body::Other` afterwards.
+    pub fn feed_malformed(
+        &mut self,
+        frame: &Frame,
+        header: &SMBHeader,
+        err: &body::Error,
+    ) -> Event {
+        Event::Lint(self.linter.feed_malformed(frame, header, err))
+    }
+
+    /// Wraps up the trackers that need the whole capture.
+    pub fn finish(&mut self) {
+        if let Some(end) = self.end {
+            self.diagnosis.finish(&self.matcher, end);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! CHANGE_NOTIFY watches, which directories are being watched and how often the watches fire.
+use chrono::{NaiveTime, TimeDelta};
+use std::{collections::HashMap, net::SocketAddr};
+
+use super::{elapsed, opens::OpenTracker, sessions::SessionTracker, ConnKey, FileKey, Frame};
+use crate::smb::{
+    body::{
+        notify::{CompletionFilter, FileNotifyInformation},
+        Body,
+    },
+    status::NtStatus,
+    SMBHeader,
+};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Watch {
+    /// `\\server\share` of the directory, if known.
+    pub share: Option<String>,
+    pub path: String,
+    pub client: SocketAddr,
+    pub filter: CompletionFilter,
+    pub tree: bool,
+    pub frame: usize,
+    pub time: NaiveTime,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum NotifyEvent {
+    /// A watch completed, with the changes (empty for STATUS_NOTIFY_ENUM_DIR, which tells the
+    /// client to enumerate the whole directory again) or an error/cancellation.
+    Fired {
+        watch: Watch,
+        status: NtStatus,
+        entries: Vec<FileNotifyInformation>,
+        /// Time the watch was outstanding.
+        waited: TimeDelta,
+    },
+    /// STATUS_NOTIFY_CLEANUP, the directory handle was closed and the watch is over.
+    Ended { watch: Watch, waited: TimeDelta },
+}
+
+/// Per directory and client counters.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DirStats {
+    pub share: Option<String>,
+    pub path: String,
+    pub client: SocketAddr,
+    pub watches: usize,
+    pub fires: usize,
+    pub entries: usize,
+    pub enum_dir: usize,
+    pub first_fire: Option<NaiveTime>,
+    pub last_fire: Option<NaiveTime>,
+}
+
+impl DirStats {
+    /// Fires per second between the first and the last fire.
+    pub fn rate(&self) -> Option<f64> {
+        let span = elapsed(self.first_fire?, self.last_fire?).num_milliseconds();
+        (span > 0).then(|| self.fires as f64 * 1000.0 / span as f64)
+    }
+}
+
+#[derive(Default)]
+pub struct NotifyTracker {
+    outstanding: HashMap<(ConnKey, u64), Watch>,
+    /// By directory then client, a path alone would merge the same directory of two shares.
+    stats: HashMap<(FileKey, SocketAddr), DirStats>,
+}
+
+impl NotifyTracker {
+    pub fn feed(
+        &mut self,
+        frame: &Frame,
+        header: &SMBHeader,
+        body: &Body,
+        opens: &OpenTracker,
+        sessions: &SessionTracker,
+    ) -> Vec<NotifyEvent> {
+        let key = (frame.conn, header.cmd_seq);
+        if let Body::NotifyRequest(req) = body {
+            let watch = Watch {
+                share: sessions.share(frame.conn, header),
+                path: opens.path(frame.conn, req.file_id),
+                client: frame.conn.client,
+                filter: req.completion_filter,
+                tree: req.watch_tree(),
+                frame: frame.number,
+                time: frame.time,
+            };
+            self.stats_mut(frame, &watch).watches += 1;
+            self.outstanding.insert(key, watch);
+            return vec![];
+        }
+
+        if !header.is_response() || header.status() == NtStatus::PENDING {
+            return vec![];
+        }
+        let Some(watch) = self.outstanding.remove(&key) else {
+            return vec![];
+        };
+
+        let waited = frame.since(watch.time);
+        if header.status() == NtStatus::NOTIFY_CLEANUP {
+            return vec![NotifyEvent::Ended { watch, waited }];
+        }
+
+        let entries = match body {
+            Body::NotifyResponse(res) => res.entries.clone(),
+            _ => vec![],
+        };
+        let stats = self.stats_mut(frame, &watch);
+        if matches!(
+            header.status(),
+            NtStatus::SUCCESS | NtStatus::NOTIFY_ENUM_DIR
+        ) {
+            stats.fires += 1;
+            stats.entries += entries.len();
+            if header.status() == NtStatus::NOTIFY_ENUM_DIR {
+                stats.enum_dir += 1;
+            }
+            stats.first_fire.get_or_insert(frame.time);
+            stats.last_fire = Some(frame.time);
+        }
+
+        vec![NotifyEvent::Fired {
+            waited,
+            watch,
+            status: header.status(),
+            entries,
+        }]
+    }
+
+    fn stats_mut(&mut self, frame: &Frame, watch: &Watch) -> &mut DirStats {
+        let file = (
+            frame.conn.server.ip(),
+            watch.share.clone(),
+            watch.path.clone(),
+        );
+        self.stats
+            .entry((file, watch.client))
+            .or_insert_with(|| DirStats {
+                share: watch.share.clone(),
+                path: watch.path.clone(),
+                client: watch.client,
+                watches: 0,
+                fires: 0,
+                entries: 0,
+                enum_dir: 0,
+                first_fire: None,
+                last_fire: None,
+            })
+    }
+
+    /// Watches still waiting for a change at the end of the capture.
+    pub fn outstanding(&self) -> impl Iterator<Item = &Watch> {
+        self.outstanding.values()
+    }
+
+    pub fn stats(&self) -> impl Iterator<Item = &DirStats> {
+        self.stats.values()
+    }
+}
@@ -0,0 +1,110 @@
+//! Open (FileId) to path bookkeeping, shared by the trackers that report on files.
+use std::{collections::HashMap, net::SocketAddr};
+
+use super::{ConnKey, Frame};
+use crate::smb::{
+    body::Body, flags::Flags, opcodes::Opcodes, status::NtStatus, types::FileId, SMBHeader,
+};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Open {
+    /// CREATE name, relative to the share root.
+    pub path: String,
+    pub client: SocketAddr,
+    /// Frame of the CREATE response that returned the FileId.
+    pub frame: usize,
+}
+
+#[derive(Default)]
+pub struct OpenTracker {
+    pending_creates: HashMap<(ConnKey, u64), String>,
+    pending_closes: HashMap<(ConnKey, u64), FileId>,
+    opens: HashMap<(ConnKey, FileId), Open>,
+    /// Name of the last CREATE sent on the connection, what compounded related operations using
+    /// `FileId::RELATED` refer to.
+    last_create: HashMap<ConnKey, String>,
+    /// SessionId and TreeId of the last request of the connection, what related compounded
+    /// requests inherit.
+    last_ids: HashMap<ConnKey, (u64, u32)>,
+}
+
+impl OpenTracker {
+    pub fn feed(&mut self, frame: &Frame, header: &SMBHeader, body: &Body) {
+        let conn = frame.conn;
+        let key = (conn, header.cmd_seq);
+        if !header.is_response() {
+            self.last_ids.insert(conn, self.ids(conn, header));
+        }
+        match body {
+            Body::CreateRequest(req) => {
+                self.last_create.insert(conn, req.name.clone());
+                self.pending_creates.insert(key, req.name.clone());
+            }
+            Body::CreateResponse(res) => {
+                if let Some(path) = self.pending_creates.remove(&key) {
+                    self.opens.insert(
+                        (conn, res.file_id),
+                        Open {
+                            path,
+                            client: conn.client,
+                            frame: frame.number,
+                        },
+                    );
+                }
+            }
+            Body::CloseRequest(req) => {
+                self.pending_closes.insert(key, req.file_id);
+            }
+            Body::CloseResponse(_) => {
+                if let Some(file_id) = self.pending_closes.remove(&key) {
+                    self.opens.remove(&(conn, file_id));
+                }
+            }
+            _ => {
+                if header.is_response() && header.status() != NtStatus::PENDING {
+                    self.pending_creates.remove(&key);
+                    self.pending_closes.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// SessionId and TreeId of a message, those of the request before it for related compounded
+    /// requests, with TreeId 0 for async messages, which don't carry it.
+    pub fn ids(&self, conn: ConnKey, header: &SMBHeader) -> (u64, u32) {
+        match header.tree_id() {
+            Some(_) if header.flags.contains(Flags::FlagsRelatedOps) => {
+                self.last_ids.get(&conn).copied().unwrap_or((header.uid, 0))
+            }
+            Some(tree_id) => (header.uid, tree_id),
+            None => (header.uid, 0),
+        }
+    }
+
+    /// Name of the CREATE request a CREATE response answers, as long as the response hasn't been
+    /// fed.
+    pub fn create_name(&self, conn: ConnKey, header: &SMBHeader) -> Option<&str> {
+        if !header.is_response() || header.opcode != Opcodes::Create {
+            return None;
+        }
+        self.pending_creates
+            .get(&(conn, header.cmd_seq))
+            .map(String::as_str)
+    }
+
+    pub fn get(&self, conn: ConnKey, file_id: FileId) -> Option<&Open> {
+        self.opens.get(&(conn, file_id))
+    }
+
+    /// Path of the open, or the FileId itself if the CREATE wasn't captured.
+    pub fn path(&self, conn: ConnKey, file_id: FileId) -> String {
+        if file_id == FileId::RELATED {
+            if let Some(name) = self.last_create.get(&conn) {
+                return name.clone();
+            }
+        }
+        self.get(conn, file_id)
+            .map(|o| o.path.clone())
+            .unwrap_or_else(|| format!("<{file_id:?}>"))
+    }
+}
@@ -0,0 +1,283 @@
+//! Operations: a request and its final response fused into one record, with what the request
+//! was about (the path, the share, the byte range) spelled out, for the one line per operation
+//! output.
+use chrono::{NaiveTime, TimeDelta};
+use std::collections::HashMap;
+
+use super::{
+    opens::OpenTracker,
+    sessions::{SessionTracker, User},
+    ConnKey, Frame,
+};
+use crate::{
+    prettify::conn::Direction,
+    smb::{
+        body::{ioctl::ctl_code_name, lock::LockFlags, setinfo::SetInfo, Body},
+        opcodes::Opcodes,
+        status::NtStatus,
+        types::FileId,
+        SMBHeader,
+    },
+};
+
+/// CreateDisposition values, by value.
+const DISPOSITIONS: [&str; 6] = [
+    "supersede",
+    "open",
+    "create",
+    "open_if",
+    "overwrite",
+    "overwrite_if",
+];
+/// CreateAction values, by value.
+const ACTIONS: [&str; 4] = ["superseded", "opened", "created", "overwritten"];
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Operation {
+    /// Time and frame of the request, or of the response if the request wasn't captured.
+    pub time: NaiveTime,
+    pub frame: usize,
+    pub conn: ConnKey,
+    /// Who started it, `RESPONSE` for the server's break notifications.
+    pub direction: Direction,
+    pub opcode: Opcodes,
+    pub msg_id: u64,
+    /// Path, share or user the operation is about, empty if nothing is.
+    pub target: String,
+    /// How the request asked, `RW`, `open_if`, `4096@0`...
+    pub details: Vec<String>,
+    /// `None` for messages that get no response (CANCEL, break notifications) and for requests
+    /// still unanswered at the end of the capture.
+    pub status: Option<NtStatus>,
+    /// What the response said beyond the status, `created`, `smb3.1.1`...
+    pub outcome: Vec<String>,
+    pub elapsed: Option<TimeDelta>,
+}
+
+#[derive(Default)]
+pub struct OperationTracker {
+    pending: HashMap<(ConnKey, u64), Operation>,
+}
+
+/// `R`, `W` and `D` for the data read, write and delete rights of an access mask.
+fn access(mask: u32) -> String {
+    const GENERIC_ALL: u32 = 0x10000000;
+    const MAXIMUM_ALLOWED: u32 = 0x02000000;
+    let any = |bits: u32| mask & (bits | GENERIC_ALL | MAXIMUM_ALLOWED) != 0;
+    let rights: String = [
+        // FILE_READ_DATA, GENERIC_READ
+        (any(0x00000001 | 0x80000000), 'R'),
+        // FILE_WRITE_DATA, FILE_APPEND_DATA, GENERIC_WRITE
+        (any(0x00000002 | 0x00000004 | 0x40000000), 'W'),
+        // DELETE
+        (any(0x00010000), 'D'),
+    ]
+    .iter()
+    .filter(|(set, _)| *set)
+    .map(|(_, c)| c)
+    .collect();
+    if rights.is_empty() {
+        // attributes, security descriptor, synchronize...
+        format!("0x{mask:x}")
+    } else {
+        rights
+    }
+}
+
+fn name(names: &[&str], value: u32) -> String {
+    names
+        .get(value as usize)
+        .map_or_else(|| value.to_string(), |name| name.to_string())
+}
+
+impl OperationTracker {
+    /// Fed before the `OpenTracker`, so that the path of an open being closed is still known.
+    /// Returns the operation the message completes, if any.
+    pub fn feed(
+        &mut self,
+        frame: &Frame,
+        header: &SMBHeader,
+        body: &Body,
+        opens: &OpenTracker,
+        sessions: &SessionTracker,
+    ) -> Option<Operation> {
+        let conn = frame.conn;
+        let key = (conn, header.cmd_seq);
+
+        if header.is_response() {
+            if !header.is_unsolicited() {
+                if header.status() == NtStatus::PENDING && header.is_async() {
+                    return None;
+                }
+                if let Some(mut op) = self.pending.remove(&key) {
+                    op.status = Some(header.status());
+                    op.outcome = Self::outcome(body);
+                    op.elapsed = Some(frame.since(op.time));
+                    return Some(op);
+                }
+            }
+            // a break notification, or a response to a request that wasn't captured
+            let mut op = self.describe(frame, header, body, opens, sessions);
+            if !header.is_unsolicited() {
+                op.direction = Direction::REQUEST;
+                op.status = Some(header.status());
+                op.outcome = Self::outcome(body);
+                op.details.push("request not captured".to_owned());
+            }
+            return Some(op);
+        }
+
+        let op = self.describe(frame, header, body, opens, sessions);
+        if header.opcode == Opcodes::Cancel {
+            return Some(op);
+        }
+        self.pending.insert(key, op);
+        None
+    }
+
+    /// The operation a request started, until its final response.
+    pub fn pending(&self, conn: ConnKey, msg_id: u64) -> Option<&Operation> {
+        self.pending.get(&(conn, msg_id))
+    }
+
+    /// Requests that never got a final response, by frame.
+    pub fn unanswered(&self) -> Vec<&Operation> {
+        let mut ops: Vec<_> = self.pending.values().collect();
+        ops.sort_by_key(|op| (op.frame, op.msg_id));
+        ops
+    }
+
+    fn describe(
+        &mut self,
+        frame: &Frame,
+        header: &SMBHeader,
+        body: &Body,
+        opens: &OpenTracker,
+        sessions: &SessionTracker,
+    ) -> Operation {
+        let conn = frame.conn;
+        let ids = opens.ids(conn, header);
+        let share = sessions.tree(conn, ids.0, ids.1).map(|t| t.path.as_str());
+        let full = |path: &str| match share {
+            Some(share) if path.is_empty() => share.to_owned(),
+            Some(share) => format!("{share}\\{path}"),
+            None => path.to_owned(),
+        };
+        let file = |file_id: FileId| full(&opens.path(conn, file_id));
+
+        let (target, details) = match body {
+            Body::SessionSetupRequest(req) => {
+                let user = User::from_token(&req.security_buffer);
+                (user.map(|u| u.to_string()).unwrap_or_default(), vec![])
+            }
+            Body::TreeConnectRequest(req) => (req.path.clone(), vec![]),
+            Body::CreateRequest(req) => (
+                full(&req.name),
+                vec![
+                    access(req.desired_access),
+                    name(&DISPOSITIONS, req.disposition),
+                ],
+            ),
+            Body::CloseRequest(req) => (file(req.file_id), vec![]),
+            Body::ReadRequest(req) => (
+                file(req.file_id),
+                vec![format!("{}@{}", req.length, req.offset)],
+            ),
+            Body::WriteRequest(req) => (
+                file(req.file_id),
+                vec![format!("{}@{}", req.data.len(), req.offset)],
+            ),
+            Body::LockRequest(req) => (
+                file(req.file_id),
+                req.locks
+                    .iter()
+                    .map(|lock| {
+                        let kind = if lock.flags.contains(LockFlags::Unlock) {
+                            "unlock"
+                        } else if lock.flags.contains(LockFlags::ExclusiveLock) {
+                            "exclusive"
+                        } else {
+                            "shared"
+                        };
+                        format!("{kind} {}@{}", lock.length, lock.offset)
+                    })
+                    .collect(),
+            ),
+            Body::IoctlRequest(req) => {
+                let code = ctl_code_name(req.ctl_code)
+                    .map_or_else(|| format!("0x{:08x}", req.ctl_code), str::to_owned);
+                // FSCTLs on no file in particular use an all ones FileId
+                let target = opens
+                    .get(conn, req.file_id)
+                    .map(|open| full(&open.path))
+                    .unwrap_or_default();
+                (target, vec![code])
+            }
+            Body::NotifyRequest(req) => (
+                file(req.file_id),
+                vec![req
+                    .completion_filter
+                    .iter_names()
+                    .map(|(name, _)| name)
+                    .collect::<Vec<_>>()
+                    .join("|")],
+            ),
+            Body::SetInfoRequest(req) => {
+                let detail = match &req.info {
+                    SetInfo::Rename { name, .. } => format!("rename to \\{name}"),
+                    SetInfo::Link { name, .. } => format!("link to \\{name}"),
+                    SetInfo::Disposition { delete: true } => "delete".to_owned(),
+                    SetInfo::Disposition { delete: false } => "undelete".to_owned(),
+                    SetInfo::Basic { .. } => "basic".to_owned(),
+                    SetInfo::EndOfFile(size) => format!("end of file {size}"),
+                    SetInfo::Allocation(size) => format!("allocation {size}"),
+                    SetInfo::Security { .. } => "security".to_owned(),
+                    SetInfo::Other(_) => format!("class {}", req.file_info_class),
+                };
+                (file(req.file_id), vec![detail])
+            }
+            Body::OplockBreak(brk) => (
+                file(brk.file_id),
+                vec![format!("level {}", brk.oplock_level)],
+            ),
+            Body::LeaseBreakNotification(brk) => (
+                String::new(),
+                vec![format!(
+                    "lease {:?} to {:?}",
+                    brk.current_state, brk.new_state
+                )],
+            ),
+            Body::LeaseBreakAck(ack) => (String::new(), vec![format!("lease {:?}", ack.state)]),
+            _ => match header.opcode {
+                Opcodes::TreeDisconnect => (share.unwrap_or_default().to_owned(), vec![]),
+                Opcodes::Cancel => (String::new(), vec![format!("MessageId {}", header.cmd_seq)]),
+                _ => (String::new(), vec![]),
+            },
+        };
+
+        Operation {
+            time: frame.time,
+            frame: frame.number,
+            conn,
+            direction: frame.direction,
+            opcode: header.opcode,
+            msg_id: header.cmd_seq,
+            target,
+            details,
+            status: None,
+            outcome: vec![],
+            elapsed: None,
+        }
+    }
+
+    fn outcome(body: &Body) -> Vec<String> {
+        match body {
+            Body::NegotiateResponse(res) => vec![res.dialect.to_string()],
+            Body::CreateResponse(res) => vec![name(&ACTIONS, res.action)],
+            Body::ReadResponse(res) => vec![format!("{} bytes", res.data.len())],
+            Body::WriteResponse(res) => vec![format!("{} bytes", res.count)],
+            Body::NotifyResponse(res) => vec![format!("{} changes", res.entries.len())],
+            _ => vec![],
+        }
+    }
+}
@@ -0,0 +1,260 @@
+//! Named pipe tracking, reassembles the DCE/RPC byte stream carried by WRITE/READ and
+//! FSCTL_PIPE_TRANSCEIVE on pipe FileIds and decodes the PDUs.
+use std::collections::HashMap;
+
+use super::ConnKey;
+use crate::{
+    dcerpc::{self, interfaces, ndr, Pdu, PduBody, PduHeader, PfcFlags},
+    prettify::conn::Direction,
+    smb::{
+        body::{ioctl::FSCTL_PIPE_TRANSCEIVE, Body},
+        status::NtStatus,
+        types::{FileId, Guid},
+        SMBHeader,
+    },
+};
+
+/// Pipe names served over `IPC$` that are known to speak DCE/RPC.
+const RPC_PIPES: &[&str] = &[
+    "srvsvc", "wkssvc", "lsarpc", "samr", "netlogon", "winreg", "svcctl", "spoolss", "lsass",
+];
+
+#[derive(Debug, Clone)]
+pub struct PipeEvent {
+    pub pipe: String,
+    pub direction: Direction,
+    pub pdu: Result<Pdu, dcerpc::Error>,
+    /// Interface and opnum the PDU belongs to, if known from the bind and request.
+    pub call: Option<(Guid, u16)>,
+    /// Decoded stub, only on the last fragment of a call.
+    pub stub: Option<Result<dcerpc::Stub, ndr::Error>>,
+}
+
+#[derive(Default)]
+pub struct PipeTracker {
+    conns: HashMap<ConnKey, ConnPipes>,
+}
+
+#[derive(Default)]
+struct ConnPipes {
+    /// MessageId of outstanding requests whose response needs its request to be understood.
+    pending: HashMap<u64, Pending>,
+    /// CREATE names of opens that are not (yet) known to be RPC pipes.
+    names: HashMap<FileId, String>,
+    pipes: HashMap<FileId, Pipe>,
+}
+
+enum Pending {
+    Create(String),
+    Read(FileId),
+}
+
+#[derive(Default)]
+struct Pipe {
+    name: String,
+    /// Bytes received that don't make a whole fragment yet, per direction.
+    to_server: Vec<u8>,
+    to_client: Vec<u8>,
+    /// Presentation context id to abstract syntax, from bind/alter_context.
+    contexts: HashMap<u16, Guid>,
+    /// Call id to (interface, opnum), from the request.
+    calls: HashMap<u32, (Guid, u16)>,
+    /// Stub of fragmented calls collected so far, per direction and call id.
+    stubs: HashMap<(Direction, u32), Vec<u8>>,
+}
+
+impl PipeTracker {
+    /// Feeds one decoded message and returns the DCE/RPC PDUs it completed.
+    pub fn feed(&mut self, conn: ConnKey, header: &SMBHeader, body: &Body) -> Vec<PipeEvent> {
+        let state = self.conns.entry(conn).or_default();
+        let mut events = vec![];
+
+        match body {
+            Body::CreateRequest(req) => {
+                state
+                    .pending
+                    .insert(header.cmd_seq, Pending::Create(req.name.clone()));
+            }
+            Body::CreateResponse(res) => {
+                if let Some(Pending::Create(name)) = state.pending.remove(&header.cmd_seq) {
+                    if is_rpc_pipe(&name) {
+                        state.pipes.insert(
+                            res.file_id,
+                            Pipe {
+                                name,
+                                ..Default::default()
+                            },
+                        );
+                    } else {
+                        state.names.insert(res.file_id, name);
+                    }
+                }
+            }
+            Body::WriteRequest(req) => {
+                state.feed(req.file_id, Direction::REQUEST, &req.data, &mut events);
+            }
+            Body::ReadRequest(req) => {
+                state
+                    .pending
+                    .insert(header.cmd_seq, Pending::Read(req.file_id));
+            }
+            Body::ReadResponse(res) => {
+                if let Some(Pending::Read(file_id)) = state.pending.remove(&header.cmd_seq) {
+                    state.feed(file_id, Direction::RESPONSE, &res.data, &mut events);
+                }
+            }
+            Body::IoctlRequest(req) if req.ctl_code == FSCTL_PIPE_TRANSCEIVE => {
+                state.feed(req.file_id, Direction::REQUEST, &req.input, &mut events);
+            }
+            Body::IoctlResponse(res) if res.ctl_code == FSCTL_PIPE_TRANSCEIVE => {
+                state.feed(res.file_id, Direction::RESPONSE, &res.output, &mut events);
+            }
+            _ => {
+                // errors, interim responses... don't consume the pending request
+                if header.is_response() && header.status() != NtStatus::PENDING {
+                    state.pending.remove(&header.cmd_seq);
+                }
+            }
+        }
+
+        events
+    }
+}
+
+impl ConnPipes {
+    fn feed(&mut self, file_id: FileId, dir: Direction, data: &[u8], out: &mut Vec<PipeEvent>) {
+        if !self.pipes.contains_key(&file_id) {
+            // opens we didn't see the CREATE of, or with names we don't know, are only considered
+            // pipes if they start with something that looks like a PDU
+            if PduHeader::parse(data).is_err() {
+                return;
+            }
+            let name = self
+                .names
+                .remove(&file_id)
+                .unwrap_or_else(|| format!("{file_id:?}"));
+            self.pipes.insert(
+                file_id,
+                Pipe {
+                    name,
+                    ..Default::default()
+                },
+            );
+        }
+        let Some(pipe) = self.pipes.get_mut(&file_id) else {
+            return;
+        };
+
+        let buf = match dir {
+            Direction::RESPONSE => &mut pipe.to_client,
+            _ => &mut pipe.to_server,
+        };
+        buf.extend_from_slice(data);
+
+        let mut frags = vec![];
+        while buf.len() >= dcerpc::COMMON_HEADER_LEN {
+            let len = match PduHeader::parse(buf) {
+                Ok(header) => header.frag_length as usize,
+                Err(_) => {
+                    // lost sync with the stream, nothing sensible to do but start over
+                    buf.clear();
+                    break;
+                }
+            };
+            if buf.len() < len {
+                break;
+            }
+            frags.push(buf.drain(..len).collect::<Vec<u8>>());
+        }
+
+        for frag in frags {
+            out.push(pipe.on_pdu(dir, Pdu::parse(&frag)));
+        }
+    }
+}
+
+impl Pipe {
+    fn on_pdu(&mut self, direction: Direction, pdu: Result<Pdu, dcerpc::Error>) -> PipeEvent {
+        let mut event = PipeEvent {
+            pipe: self.name.clone(),
+            direction,
+            pdu,
+            call: None,
+            stub: None,
+        };
+        let Ok(pdu) = &event.pdu else {
+            return event;
+        };
+
+        let call_id = pdu.header.call_id;
+        let stub = match &pdu.body {
+            PduBody::Bind { contexts, .. } => {
+                for ctx in contexts {
+                    self.contexts
+                        .insert(ctx.context_id, ctx.abstract_syntax.uuid);
+                }
+                return event;
+            }
+            PduBody::Request {
+                context_id,
+                opnum,
+                stub,
+                ..
+            } => {
+                if let Some(iface) = self.contexts.get(context_id) {
+                    self.calls.insert(call_id, (*iface, *opnum));
+                }
+                stub
+            }
+            PduBody::Response { stub, .. } => stub,
+            PduBody::Fault { .. } => {
+                event.call = self.calls.remove(&call_id);
+                return event;
+            }
+            _ => return event,
+        };
+
+        event.call = self.calls.get(&call_id).copied();
+        let flags = pdu.header.flags;
+        let key = (direction, call_id);
+        let whole = if flags.contains(PfcFlags::FirstFrag | PfcFlags::LastFrag) {
+            Some(stub.clone())
+        } else {
+            let collected = self.stubs.entry(key).or_default();
+            if flags.contains(PfcFlags::FirstFrag) {
+                collected.clear();
+            }
+            collected.extend_from_slice(stub);
+            flags
+                .contains(PfcFlags::LastFrag)
+                .then(|| self.stubs.remove(&key).unwrap_or_default())
+        };
+
+        if let (Some(stub), Some((iface, opnum))) = (whole, event.call) {
+            let response = direction == Direction::RESPONSE;
+            if response {
+                self.calls.remove(&call_id);
+            }
+            event.stub = dcerpc::decode_stub(&iface, opnum, response, &stub).transpose();
+        }
+        event
+    }
+}
+
+fn is_rpc_pipe(name: &str) -> bool {
+    let name = name.trim_start_matches('\\').to_ascii_lowercase();
+    let name = name.strip_prefix("pipe\\").unwrap_or(&name);
+    RPC_PIPES.contains(&name)
+}
+
+impl PipeEvent {
+    /// Human name of the call, like `srvsvc.NetrShareEnum`.
+    pub fn call_name(&self) -> Option<String> {
+        let (iface, opnum) = self.call?;
+        let iface_name = interfaces::lookup(&iface).map(|i| i.name).unwrap_or("?");
+        Some(match interfaces::opnum_name(&iface, opnum) {
+            Some(op) => format!("{iface_name}.{op}"),
+            None => format!("{iface_name}.opnum{opnum}"),
+        })
+    }
+}
@@ -0,0 +1,312 @@
+//! Connection and session state: negotiated dialect and security, who is logged on with which
+//! SessionId and which shares each session has connected.
+use std::{collections::HashMap, fmt::Display};
+
+use super::{ConnKey, Frame};
+use crate::{
+    ntlmssp::{Message, Token},
+    smb::{
+        body::{
+            negotiate::{Capabilities, Dialect, SecurityMode},
+            session::SessionFlags,
+            Body,
+        },
+        opcodes::Opcodes,
+        status::NtStatus,
+        SMBHeader,
+    },
+};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum User {
+    Named {
+        domain: String,
+        user: String,
+    },
+    Anonymous,
+    /// Kerberos doesn't show the client name in clear.
+    Kerberos,
+}
+
+impl User {
+    /// Who a SESSION_SETUP security buffer authenticates, if it says.
+    pub fn from_token(security_buffer: &[u8]) -> Option<Self> {
+        match Token::find(security_buffer) {
+            Token::Ntlmssp(Message::Authenticate {
+                anonymous: true, ..
+            }) => Some(Self::Anonymous),
+            Token::Ntlmssp(Message::Authenticate { domain, user, .. }) => {
+                Some(Self::Named { domain, user })
+            }
+            Token::Kerberos => Some(Self::Kerberos),
+            _ => None,
+        }
+    }
+}
+
+impl Display for User {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Named { domain, user } if domain.is_empty() => write!(f, "{user}"),
+            Self::Named { domain, user } => write!(f, "{user}@{domain}"),
+            Self::Anonymous => write!(f, "anonymous"),
+            Self::Kerberos => write!(f, "(kerberos)"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Tree {
+    /// `\\server\share`
+    pub path: String,
+    pub share_type: u8,
+    pub encrypted: bool,
+    pub frame: usize,
+}
+
+impl Tree {
+    pub fn share(&self) -> &str {
+        self.path.rsplit('\\').next().unwrap_or(&self.path)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct Session {
+    pub user: Option<User>,
+    pub flags: SessionFlags,
+    /// SESSION_SETUP completed, sessions stay half set up while the authentication goes on.
+    pub established: bool,
+    pub trees: HashMap<u32, Tree>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct ConnState {
+    pub offered: Vec<Dialect>,
+    pub dialect: Option<Dialect>,
+    pub client_security: SecurityMode,
+    pub server_security: SecurityMode,
+    pub capabilities: Capabilities,
+    pub cipher: Option<u16>,
+    pub signing_algorithm: Option<u16>,
+    pub sessions: HashMap<u64, Session>,
+}
+
+impl ConnState {
+    pub fn signing_required(&self) -> bool {
+        (self.client_security | self.server_security).contains(SecurityMode::SigningRequired)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SessionEvent {
+    Negotiated {
+        dialect: Dialect,
+        offered: Vec<Dialect>,
+        signing_required: bool,
+    },
+    LoggedOn {
+        session_id: u64,
+        user: Option<User>,
+        flags: SessionFlags,
+    },
+    AuthFailed {
+        session_id: u64,
+        user: Option<User>,
+        status: NtStatus,
+    },
+    LoggedOff {
+        session_id: u64,
+        user: Option<User>,
+    },
+    TreeConnected {
+        session_id: u64,
+        tree_id: u32,
+        path: String,
+    },
+    TreeConnectFailed {
+        path: String,
+        status: NtStatus,
+    },
+    TreeDisconnected {
+        tree_id: u32,
+        path: String,
+    },
+}
+
+enum Pending {
+    Auth(Option<User>),
+    Tree(String),
+}
+
+#[derive(Default)]
+pub struct SessionTracker {
+    conns: HashMap<ConnKey, ConnState>,
+    pending: HashMap<(ConnKey, u64), Pending>,
+}
+
+impl SessionTracker {
+    pub fn feed(&mut self, frame: &Frame, header: &SMBHeader, body: &Body) -> Vec<SessionEvent> {
+        let key = (frame.conn, header.cmd_seq);
+        let conn = self.conns.entry(frame.conn).or_default();
+        let mut events = vec![];
+
+        match body {
+            Body::NegotiateRequest(req) => {
+                conn.offered = req.dialects.clone();
+                conn.client_security = req.security_mode;
+            }
+            Body::NegotiateResponse(res) => {
+                conn.dialect = Some(res.dialect);
+                conn.server_security = res.security_mode;
+                conn.capabilities = res.capabilities;
+                conn.cipher = res.cipher();
+                conn.signing_algorithm = res.signing_algorithm();
+                events.push(SessionEvent::Negotiated {
+                    dialect: res.dialect,
+                    offered: conn.offered.clone(),
+                    signing_required: conn.signing_required(),
+                });
+            }
+            Body::SessionSetupRequest(req) => {
+                let user = User::from_token(&req.security_buffer);
+                self.pending.insert(key, Pending::Auth(user));
+            }
+            Body::SessionSetupResponse(res) => {
+                let user = match self.pending.remove(&key) {
+                    Some(Pending::Auth(user)) => user,
+                    _ => None,
+                };
+                let session = conn.sessions.entry(header.uid).or_default();
+                if user.is_some() {
+                    session.user = user;
+                }
+                if header.nt_status == 0 {
+                    session.established = true;
+                    session.flags = res.session_flags;
+                    if session.flags.contains(SessionFlags::IsNull) {
+                        session.user = Some(User::Anonymous);
+                    }
+                    events.push(SessionEvent::LoggedOn {
+                        session_id: header.uid,
+                        user: session.user.clone(),
+                        flags: session.flags,
+                    });
+                }
+            }
+            Body::TreeConnectRequest(req) => {
+                self.pending.insert(key, Pending::Tree(req.path.clone()));
+            }
+            Body::TreeConnectResponse(res) => {
+                if let Some(Pending::Tree(path)) = self.pending.remove(&key) {
+                    let session = conn.sessions.entry(header.uid).or_default();
+                    session.trees.insert(
+                        header.tid,
+                        Tree {
+                            path: path.clone(),
+                            share_type: res.share_type,
+                            encrypted: res.encrypt_data(),
+                            frame: frame.number,
+                        },
+                    );
+                    events.push(SessionEvent::TreeConnected {
+                        session_id: header.uid,
+                        tree_id: header.tid,
+                        path,
+                    });
+                }
+            }
+            _ if !header.is_response() => {}
+            _ => match header.opcode {
+                Opcodes::SessionSetup if header.status() != NtStatus::MORE_PROCESSING_REQUIRED => {
+                    let user = match self.pending.remove(&key) {
+                        Some(Pending::Auth(user)) => user,
+                        _ => None,
+                    };
+                    conn.sessions.remove(&header.uid);
+                    events.push(SessionEvent::AuthFailed {
+                        session_id: header.uid,
+                        user,
+                        status: header.status(),
+                    });
+                }
+                Opcodes::TreeConnect => {
+                    if let Some(Pending::Tree(path)) = self.pending.remove(&key) {
+                        events.push(SessionEvent::TreeConnectFailed {
+                            path,
+                            status: header.status(),
+                        });
+                    }
+                }
+                Opcodes::SessionLogoff if header.nt_status == 0 => {
+                    if let Some(session) = conn.sessions.remove(&header.uid) {
+                        events.push(SessionEvent::LoggedOff {
+                            session_id: header.uid,
+                            user: session.user,
+                        });
+                    }
+                }
+                Opcodes::TreeDisconnect if header.nt_status == 0 => {
+                    let tree = conn
+                        .sessions
+                        .get_mut(&header.uid)
+                        .and_then(|s| s.trees.remove(&header.tid));
+                    if let Some(tree) = tree {
+                        events.push(SessionEvent::TreeDisconnected {
+                            tree_id: header.tid,
+                            path: tree.path,
+                        });
+                    }
+                }
+                _ => {}
+            },
+        }
+        events
+    }
+
+    pub fn conn(&self, conn: ConnKey) -> Option<&ConnState> {
+        self.conns.get(&conn)
+    }
+
+    pub fn conns(&self) -> impl Iterator<Item = (&ConnKey, &ConnState)> {
+        self.conns.iter()
+    }
+
+    pub fn session(&self, conn: ConnKey, session_id: u64) -> Option<&Session> {
+        self.conns.get(&conn)?.sessions.get(&session_id)
+    }
+
+    pub fn tree(&self, conn: ConnKey, session_id: u64, tree_id: u32) -> Option<&Tree> {
+        self.session(conn, session_id)?.trees.get(&tree_id)
+    }
+
+    /// `\\server\share` the message was sent to, `None` for async messages, which carry no
+    /// TreeId, and when the TREE_CONNECT isn't in the capture.
+    pub fn share(&self, conn: ConnKey, header: &SMBHeader) -> Option<String> {
+        let tree_id = header.tree_id()?;
+        Some(self.tree(conn, header.uid, tree_id)?.path.clone())
+    }
+
+    /// Who, which dialect and which share a message belongs to, `alice@CORP smb3.1.1
+    /// [\\srv\data]`, empty when nothing is known yet.
+    pub fn context(&self, conn: ConnKey, header: &SMBHeader) -> String {
+        let mut parts = vec![];
+        let Some(state) = self.conns.get(&conn) else {
+            return String::new();
+        };
+        let session = state.sessions.get(&header.uid);
+        if let Some(user) = session.and_then(|s| s.user.as_ref()) {
+            parts.push(user.to_string());
+        }
+        if let Some(dialect) = state.dialect {
+            parts.push(dialect.to_string());
+            if let Some(seq) = header.channel_sequence(dialect).filter(|seq| *seq != 0) {
+                parts.push(format!("ChannelSequence {seq}"));
+            }
+        }
+        let tree = header.tree_id().and_then(|tid| session?.trees.get(&tid));
+        if let Some(tree) = tree {
+            parts.push(format!("[{}]", tree.path));
+        }
+        parts.join(" ")
+    }
+}
@@ -0,0 +1,111 @@
+//! Service response time statistics per command, the way Wireshark's SMB2 SRT table does it.
+use chrono::TimeDelta;
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::IpAddr,
+};
+
+use super::{matcher::Outstanding, sessions::SessionTracker, Frame};
+use crate::smb::{opcodes::Opcodes, status::NtStatus};
+
+/// What the rows get broken down by besides the command.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum Breakdown {
+    #[default]
+    None,
+    Share,
+    Client,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SrtRow {
+    pub opcode: Opcodes,
+    /// Share or client, depending on the breakdown.
+    pub group: Option<String>,
+    pub count: usize,
+    pub min: TimeDelta,
+    pub max: TimeDelta,
+    pub mean: TimeDelta,
+    pub median: TimeDelta,
+    pub p95: TimeDelta,
+    pub p99: TimeDelta,
+    /// Responses with a status other than a success.
+    pub failures: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Samples {
+    times: Vec<TimeDelta>,
+    failures: usize,
+}
+
+#[derive(Default)]
+pub struct SrtTracker {
+    samples: HashMap<(Opcodes, Option<String>, IpAddr), Samples>,
+}
+
+impl SrtTracker {
+    /// Accounts for the final response to `request`.
+    pub fn feed(
+        &mut self,
+        frame: &Frame,
+        request: &Outstanding,
+        elapsed: TimeDelta,
+        status: NtStatus,
+        sessions: &SessionTracker,
+    ) {
+        let share = sessions
+            .tree(frame.conn, request.session_id, request.tree_id)
+            .map(|tree| tree.share().to_owned());
+        let samples = self
+            .samples
+            .entry((request.opcode, share, frame.conn.client.ip()))
+            .or_default();
+        samples.times.push(elapsed);
+        // SESSION_SETUP rounds asking for more are part of a successful logon
+        if !status.is_success() && status != NtStatus::MORE_PROCESSING_REQUIRED {
+            samples.failures += 1;
+        }
+    }
+
+    pub fn rows(&self, by: Breakdown) -> Vec<SrtRow> {
+        let mut groups: BTreeMap<(Opcodes, Option<String>), Samples> = BTreeMap::new();
+        for ((opcode, share, client), samples) in &self.samples {
+            let group = match by {
+                Breakdown::None => None,
+                Breakdown::Share => Some(share.clone().unwrap_or_else(|| "?".to_owned())),
+                Breakdown::Client => Some(client.to_string()),
+            };
+            let merged = groups.entry((*opcode, group)).or_default();
+            merged.times.extend(&samples.times);
+            merged.failures += samples.failures;
+        }
+
+        groups
+            .into_iter()
+            .map(|((opcode, group), mut samples)| {
+                samples.times.sort();
+                let times = &samples.times;
+                let total: TimeDelta = times.iter().sum();
+                SrtRow {
+                    opcode,
+                    group,
+                    count: times.len(),
+                    min: times[0],
+                    max: times[times.len() - 1],
+                    mean: total / times.len() as i32,
+                    median: percentile(times, 50),
+                    p95: percentile(times, 95),
+                    p99: percentile(times, 99),
+                    failures: samples.failures,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Nearest-rank percentile of sorted, non empty samples.
+fn percentile(sorted: &[TimeDelta], p: usize) -> TimeDelta {
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
@@ -0,0 +1,102 @@
+//! Totals per client and per share, for captures mixing too many clients to follow message by
+//! message.
+use chrono::NaiveTime;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    net::IpAddr,
+};
+
+use super::{sessions::SessionTracker, ConnKey, Frame};
+use crate::smb::{
+    body::{negotiate::Dialect, Body},
+    opcodes::Opcodes,
+    status::NtStatus,
+    SMBHeader,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    pub conns: BTreeSet<ConnKey>,
+    /// SessionIds, per connection as they are only unique on one.
+    pub sessions: BTreeSet<(ConnKey, u64)>,
+    pub users: BTreeSet<String>,
+    pub dialects: BTreeSet<Dialect>,
+    /// Requests and responses.
+    pub messages: BTreeMap<Opcodes, usize>,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+    /// Responses with an error status.
+    pub errors: BTreeMap<NtStatus, usize>,
+    pub first_seen: Option<NaiveTime>,
+    pub last_seen: Option<NaiveTime>,
+}
+
+impl Stats {
+    fn add(&mut self, frame: &Frame, header: &SMBHeader, body: &Body, sessions: &SessionTracker) {
+        let conn = frame.conn;
+        self.conns.insert(conn);
+        if header.uid != 0 && sessions.session(conn, header.uid).is_some() {
+            self.sessions.insert((conn, header.uid));
+        }
+        if let Some(user) = sessions
+            .session(conn, header.uid)
+            .and_then(|s| s.user.as_ref())
+        {
+            self.users.insert(user.to_string());
+        }
+        if let Some(dialect) = sessions.conn(conn).and_then(|c| c.dialect) {
+            self.dialects.insert(dialect);
+        }
+        *self.messages.entry(header.opcode).or_default() += 1;
+        match body {
+            Body::ReadResponse(res) => self.bytes_read += res.data.len() as u64,
+            Body::WriteResponse(res) => self.bytes_written += u64::from(res.count),
+            _ => {}
+        }
+        if header.is_response() && header.status().is_error() {
+            *self.errors.entry(header.status()).or_default() += 1;
+        }
+        self.first_seen.get_or_insert(frame.time);
+        self.last_seen = Some(frame.time);
+    }
+}
+
+#[derive(Default)]
+pub struct SummaryTracker {
+    clients: HashMap<IpAddr, Stats>,
+    /// By `\\server\share`.
+    shares: HashMap<String, Stats>,
+}
+
+impl SummaryTracker {
+    /// Fed after the `SessionTracker`, so a SESSION_SETUP response already counts for its user.
+    pub fn feed(
+        &mut self,
+        frame: &Frame,
+        header: &SMBHeader,
+        body: &Body,
+        sessions: &SessionTracker,
+    ) {
+        self.clients
+            .entry(frame.conn.client.ip())
+            .or_default()
+            .add(frame, header, body, sessions);
+        let tree = header
+            .tree_id()
+            .and_then(|tid| sessions.tree(frame.conn, header.uid, tid));
+        if let Some(tree) = tree {
+            self.shares
+                .entry(tree.path.clone())
+                .or_default()
+                .add(frame, header, body, sessions);
+        }
+    }
+
+    pub fn clients(&self) -> impl Iterator<Item = (&IpAddr, &Stats)> {
+        self.clients.iter()
+    }
+
+    pub fn shares(&self) -> impl Iterator<Item = (&String, &Stats)> {
+        self.shares.iter()
+    }
+}
@@ -0,0 +1,1157 @@
+//! Scrubbing of captures before they are shared (`--anonymize`): IP addresses, host, domain,
+//! user and share names, file paths and domain SIDs are replaced by pseudonyms, the same ones in
+//! every packet and layer, and the table of what replaced what goes to its own file.
+//!
+//! Pseudonyms are as long as what they replace, so that no length or offset field changes and
+//! the capture parses the same, and none is one of the names of the capture, which takes a first
+//! pass over it ([`Anonymizer::restart`]). What can't be rewritten field by field is blanked
+//! instead: file data of READ and WRITE and IOCTL buffers (named pipe RPC keeps its PDU headers),
+//! security tokens other than NTLMSSP, CREATE contexts other than the fixed-layout ones, the
+//! buffers of the bodies the parsers don't decode, and the names no pseudonym of their length is
+//! left for, such as IP addresses written out in paths.
+use num_traits::FromPrimitive;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Display,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+use crate::{
+    dcerpc::{self, interfaces::INTERFACES, PduType},
+    ntlmssp,
+    smb::{
+        body::{error::ErrorDetail, setinfo::SetInfo, Body},
+        reader::Span,
+        types::utf16le,
+        SMBMsg,
+    },
+    tcpdump::TcpdumpMsg,
+};
+
+/// Shares whose names say nothing about whose server it is, along with `C$` and the like.
+const WELL_KNOWN_SHARES: [&str; 5] = ["IPC$", "ADMIN$", "PRINT$", "SYSVOL", "NETLOGON"];
+
+/// File extensions up to this long are kept, `report.docx` becomes `f00001.docx`.
+const MAX_EXTENSION: usize = 5;
+
+/// RIDs below are the well-known accounts and groups of every domain (500 Administrator, 512
+/// Domain Admins...), the others are mapped.
+const FIRST_USER_RID: u32 = 1000;
+
+/// Bytes of a message kept when its body can't be rewritten field by field: the NetBIOS length
+/// and the protocol id, so that SMB1 is still told apart.
+const KEPT_HEAD: usize = 8;
+
+/// CREATE contexts whose data are flags, times, GUIDs and FileIds (leases, durable handles,
+/// maximal access, on-disk id, previous versions, allocation size). The others, extended
+/// attributes and security descriptors among them, are blanked.
+const FIXED_CONTEXTS: [&[u8]; 9] = [
+    b"RqLs", b"DHnQ", b"DHnC", b"DH2Q", b"DH2C", b"MxAc", b"QFid", b"TWrp", b"AlSi",
+];
+
+/// How IPv6 addresses are written in UNC paths, `fe80--1.ipv6-literal.net`.
+const IPV6_LITERAL: &str = ".ipv6-literal.net";
+
+/// What the mapping file says of the names that were blanked.
+const BLANKED: &str = "(blanked)";
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub enum Kind {
+    Ip,
+    Host,
+    Domain,
+    User,
+    Share,
+    Path,
+    Sid,
+}
+
+impl Kind {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Ip => "ip",
+            Self::Host => "host",
+            Self::Domain => "domain",
+            Self::User => "user",
+            Self::Share => "share",
+            Self::Path => "path",
+            Self::Sid => "sid",
+        }
+    }
+
+    /// First letter of the pseudonyms of names.
+    fn prefix(&self) -> char {
+        match self {
+            Self::Host => 'h',
+            Self::Domain => 'd',
+            Self::User => 'u',
+            Self::Share => 's',
+            _ => 'f',
+        }
+    }
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// The `index`th of the `len` characters names of a kind: the prefix and the index padded with
+/// zeros while it fits, then letters alone. `None` once there are no more, all are different.
+fn invent(prefix: char, index: usize, len: usize) -> Option<String> {
+    let numbered = 10usize
+        .checked_pow(len.saturating_sub(1) as u32)
+        .unwrap_or(usize::MAX);
+    if index < numbered {
+        return Some(format!("{prefix}{index:0>width$}", width = len - 1));
+    }
+    let mut rest = index - numbered;
+    let mut letters = vec![];
+    for _ in 0..len {
+        letters.push((b'a' + (rest % 26) as u8) as char);
+        rest /= 26;
+    }
+    letters.reverse();
+    (rest == 0).then(|| letters.into_iter().collect())
+}
+
+/// Writes `text` as UTF-16 over `raw` if it has the same length, which pseudonyms do.
+fn put_utf16(raw: &mut [u8], text: &str) {
+    let bytes: Vec<u8> = text.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    if bytes.len() == raw.len() {
+        raw.copy_from_slice(&bytes);
+    }
+}
+
+fn blank(raw: &mut [u8]) {
+    raw.fill(0);
+}
+
+/// Blanks file data and IOCTL buffers. DCE/RPC over a named pipe keeps its PDU headers, and
+/// BIND PDUs whole as they only name interfaces, so that operations and faults still show.
+fn blank_data(raw: &mut [u8]) {
+    let kept = match &*raw {
+        [5, 0, ptype, ..] if raw.len() >= dcerpc::COMMON_HEADER_LEN => {
+            match PduType::from_u8(*ptype) {
+                Some(
+                    PduType::Bind
+                    | PduType::BindAck
+                    | PduType::BindNak
+                    | PduType::AlterContext
+                    | PduType::AlterContextResp,
+                ) => raw.len(),
+                // alloc_hint, context id and opnum, the status of a fault after them
+                Some(PduType::Request | PduType::Response) => dcerpc::COMMON_HEADER_LEN + 8,
+                Some(PduType::Fault) => dcerpc::COMMON_HEADER_LEN + 12,
+                _ => dcerpc::COMMON_HEADER_LEN,
+            }
+        }
+        _ => 0,
+    };
+    blank(raw.get_mut(kept..).unwrap_or_default());
+}
+
+/// Blanks the data of the CREATE contexts of a chain but for `FIXED_CONTEXTS`: Next,
+/// NameOffset, NameLength, Reserved, DataOffset, DataLength, offsets from the context start.
+fn create_contexts(chain: &mut [u8]) {
+    let mut start = 0;
+    while let Some(head) = chain.get(start..start + 16) {
+        let half = |at: usize| usize::from(u16::from_le_bytes([head[at], head[at + 1]]));
+        let word = |at: usize| u32::from_le_bytes(head[at..at + 4].try_into().expect("4 bytes"));
+        let next = word(0) as usize;
+        let name = start + half(4)..start + half(4) + half(6);
+        let data = start + half(10)..start + half(10) + word(12) as usize;
+        let fixed = chain
+            .get(name)
+            .is_some_and(|name| FIXED_CONTEXTS.contains(&name));
+        if !fixed {
+            blank(chain.get_mut(data).unwrap_or_default());
+        }
+        if next == 0 {
+            break;
+        }
+        start += next;
+    }
+}
+
+/// One's complement sum of `bytes` as big endian words, for the IP and TCP checksums.
+fn checksum(bytes: &[u8]) -> u16 {
+    let mut sum: u32 = bytes
+        .chunks(2)
+        .map(|word| u32::from(word[0]) << 8 | u32::from(*word.get(1).unwrap_or(&0)))
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Pseudonyms given so far, kept across packets and inputs so that they stay consistent.
+#[derive(Default)]
+pub struct Anonymizer {
+    ips: HashMap<IpAddr, IpAddr>,
+    /// Last index of the addresses of the pseudonym ranges.
+    ip_count: u32,
+    /// Pseudonym of every name by kind and length, compared case-insensitively like SMB does,
+    /// `None` when it is blanked.
+    names: HashMap<(Kind, String, usize), Option<String>>,
+    /// Where the search for a new pseudonym starts, per kind and length.
+    counts: HashMap<(Kind, usize), usize>,
+    /// Names (lowercase) and addresses of the capture, which no pseudonym may be: those seen so
+    /// far, and all of them after a first pass.
+    originals: HashSet<String>,
+    /// Pseudonyms (lowercase) given, which no other name may get.
+    given: HashSet<String>,
+    /// Pseudonyms of the domain identifiers (the three sub authorities after 21) and of the
+    /// RIDs in them.
+    domains: HashMap<[u32; 3], u32>,
+    rids: HashMap<([u32; 3], u32), u32>,
+    table: BTreeMap<(Kind, String), String>,
+}
+
+impl Anonymizer {
+    /// A new anonymizer that knows the names this one has seen, to go over the capture again
+    /// with pseudonyms that are none of them.
+    pub fn restart(self) -> Self {
+        Self {
+            originals: self.originals,
+            ..Self::default()
+        }
+    }
+
+    /// Rewrites a segment in place: addresses and checksums of the IP/TCP headers, then the
+    /// SMB2 messages it carries.
+    pub fn packet(&mut self, msg: &mut TcpdumpMsg) {
+        let (src, dst) = (self.ip(msg.header.src.ip()), self.ip(msg.header.dst.ip()));
+        msg.header.src.set_ip(src);
+        msg.header.dst.set_ip(dst);
+        let ip = &mut msg.data.ip_header;
+        match (src, dst) {
+            (IpAddr::V4(src), IpAddr::V4(dst)) if ip.len() >= 20 => {
+                ip[12..16].copy_from_slice(&src.octets());
+                ip[16..20].copy_from_slice(&dst.octets());
+            }
+            (IpAddr::V6(src), IpAddr::V6(dst)) if ip.len() >= 40 => {
+                ip[8..24].copy_from_slice(&src.octets());
+                ip[24..40].copy_from_slice(&dst.octets());
+            }
+            _ => {}
+        }
+        self.payload(&mut msg.data.data);
+        Self::checksums(msg);
+    }
+
+    /// Recomputes the checksums the capture has, offloaded ones (zero) are left alone.
+    fn checksums(msg: &mut TcpdumpMsg) {
+        let data = &mut msg.data;
+        let ip = &mut data.ip_header;
+        let tcp_len = data.tcp_header.len() + data.data.len();
+        let pseudo = match ip.first().map(|b| b >> 4) {
+            Some(4) if ip.len() >= 20 => {
+                if ip[10..12] != [0, 0] {
+                    ip[10..12].fill(0);
+                    let sum = checksum(ip);
+                    ip[10..12].copy_from_slice(&sum.to_be_bytes());
+                }
+                [&ip[12..20], &[0, 6], &(tcp_len as u16).to_be_bytes()].concat()
+            }
+            Some(6) if ip.len() >= 40 => [
+                &ip[8..40],
+                &(tcp_len as u32).to_be_bytes()[..],
+                &[0, 0, 0, 6],
+            ]
+            .concat(),
+            _ => return,
+        };
+        let tcp = &mut data.tcp_header;
+        if tcp.len() < 18 || tcp[16..18] == [0, 0] {
+            return;
+        }
+        tcp[16..18].fill(0);
+        let sum = checksum(&[pseudo.as_slice(), tcp, &data.data].concat());
+        tcp[16..18].copy_from_slice(&sum.to_be_bytes());
+    }
+
+    /// Addresses in the ranges set aside for benchmarks (198.18.0.0/15) and documentation
+    /// (2001:db8::/32), in order of appearance. Loopback and unspecified ones stay.
+    fn ip(&mut self, addr: IpAddr) -> IpAddr {
+        if addr.is_loopback() || addr.is_unspecified() {
+            return addr;
+        }
+        if let Some(pseudonym) = self.ips.get(&addr) {
+            return *pseudonym;
+        }
+        self.originals.insert(addr.to_string());
+        let pseudonym = loop {
+            self.ip_count += 1;
+            let pseudonym = match addr {
+                IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from(
+                    u32::from(Ipv4Addr::new(198, 18, 0, 0)) + self.ip_count,
+                )),
+                IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(
+                    u128::from(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0))
+                        + u128::from(self.ip_count),
+                )),
+            };
+            // the capture may have addresses of those ranges too
+            if !self.originals.contains(&pseudonym.to_string()) {
+                break pseudonym;
+            }
+        };
+        self.ips.insert(addr, pseudonym);
+        self.table
+            .insert((Kind::Ip, addr.to_string()), pseudonym.to_string());
+        pseudonym
+    }
+
+    /// Pseudonym of `original` as `len` characters, the length it is encoded with, or as many
+    /// NULs when there is no pseudonym of that length left.
+    fn name(&mut self, kind: Kind, original: &str, len: usize) -> String {
+        if original.is_empty() {
+            return String::new();
+        }
+        let lowercase = original.to_lowercase();
+        let key = (kind, lowercase.clone(), len);
+        let pseudonym = match self.names.get(&key) {
+            Some(pseudonym) => pseudonym.clone(),
+            None => {
+                self.originals.insert(lowercase);
+                let pseudonym = self.invent(kind, len);
+                self.names.insert(key, pseudonym.clone());
+                pseudonym
+            }
+        };
+        self.table
+            .entry((kind, original.to_owned()))
+            .or_insert_with(|| pseudonym.clone().unwrap_or_else(|| BLANKED.to_owned()));
+        pseudonym.unwrap_or_else(|| "\0".repeat(len))
+    }
+
+    /// The next pseudonym of `kind` that is neither taken nor a name of the capture.
+    fn invent(&mut self, kind: Kind, len: usize) -> Option<String> {
+        let count = self.counts.entry((kind, len)).or_default();
+        loop {
+            *count += 1;
+            let pseudonym = invent(kind.prefix(), *count, len)?;
+            let lowercase = pseudonym.to_lowercase();
+            if !self.originals.contains(&lowercase) && self.given.insert(lowercase) {
+                return Some(pseudonym);
+            }
+        }
+    }
+
+    fn utf16_name(&mut self, kind: Kind, original: &str) -> String {
+        self.name(kind, original, original.encode_utf16().count())
+    }
+
+    /// A host name, or an address written out, which gets the pseudonym of the address if it
+    /// has the same length and is blanked otherwise.
+    fn host(&mut self, host: &str) -> String {
+        let len = host.encode_utf16().count();
+        let lowercase = host.to_ascii_lowercase();
+        let pseudonym = match lowercase.strip_suffix(IPV6_LITERAL) {
+            // `-` for `:` and `s` before the zone index
+            Some(literal) => {
+                let addr = literal
+                    .split('s')
+                    .next()
+                    .unwrap_or_default()
+                    .replace('-', ":");
+                match addr.parse::<Ipv6Addr>() {
+                    Ok(addr) => {
+                        let pseudonym = self.ip(IpAddr::V6(addr)).to_string();
+                        pseudonym.replace(':', "-") + IPV6_LITERAL
+                    }
+                    Err(_) => return self.utf16_name(Kind::Host, host),
+                }
+            }
+            None => match host.parse::<IpAddr>() {
+                Ok(addr) => self.ip(addr).to_string(),
+                Err(_) => return self.utf16_name(Kind::Host, host),
+            },
+        };
+        if pseudonym.encode_utf16().count() == len {
+            return pseudonym;
+        }
+        self.table
+            .entry((Kind::Host, host.to_owned()))
+            .or_insert_with(|| BLANKED.to_owned());
+        "\0".repeat(len)
+    }
+
+    fn share(&mut self, share: &str) -> String {
+        let upper = share.to_uppercase();
+        let drive =
+            upper.len() == 2 && upper.ends_with('$') && upper.starts_with(char::is_alphabetic);
+        if drive || WELL_KNOWN_SHARES.contains(&upper.as_str()) {
+            return share.to_owned();
+        }
+        self.utf16_name(Kind::Share, share)
+    }
+
+    /// A path component, keeping its extension and stream name (`:Zone.Identifier:$DATA`).
+    fn component(&mut self, part: &str) -> String {
+        if part.is_empty() || part == "." || part == ".." || part == "*" {
+            return part.to_owned();
+        }
+        let (name, stream) = match part.split_once(':') {
+            Some((name, stream)) => (name, Some(stream)),
+            None => (part, None),
+        };
+        let (stem, extension) = match name.rsplit_once('.') {
+            Some((stem, extension))
+                if !stem.is_empty()
+                    && (1..=MAX_EXTENSION).contains(&extension.len())
+                    && extension.chars().all(|c| c.is_ascii_alphanumeric()) =>
+            {
+                (stem, Some(extension))
+            }
+            _ => (name, None),
+        };
+        let mut out = self.utf16_name(Kind::Path, stem);
+        if let Some(extension) = extension {
+            out += &format!(".{extension}");
+        }
+        if let Some(stream) = stream {
+            out += &format!(":{stream}");
+        }
+        out
+    }
+
+    /// A path relative to a share, or a UNC path (`\\server\share\dir`).
+    fn path(&mut self, path: &str) -> String {
+        let parts: Vec<&str> = path.split('\\').collect();
+        let unc = path.starts_with("\\\\");
+        parts
+            .iter()
+            .enumerate()
+            .map(|(i, part)| match i {
+                2 if unc => self.host(part),
+                3 if unc => self.share(part),
+                _ => self.component(part),
+            })
+            .collect::<Vec<_>>()
+            .join("\\")
+    }
+
+    /// Rewrites the UTF-16 path in `raw`.
+    fn rewrite_path(&mut self, raw: &mut [u8]) {
+        let path = utf16le(raw);
+        let pseudonym = self.path(&path);
+        put_utf16(raw, &pseudonym);
+    }
+
+    fn payload(&mut self, data: &mut [u8]) {
+        if data.is_empty() {
+            return;
+        }
+        let Ok(msgs) = SMBMsg::parse_packet(data) else {
+            // SMB1, or part of a message split across segments
+            blank(data.get_mut(KEPT_HEAD..).unwrap_or_default());
+            return;
+        };
+        // where each message starts, like `prettify::dissect::packet_spans` walks them
+        let mut msgs = msgs.into_iter();
+        let mut pos = 0;
+        while let Some(head) = data.get(pos..pos + 4) {
+            let len = u32::from_be_bytes(head.try_into().expect("4 bytes")) as usize;
+            let mut start = pos + 4;
+            for msg in msgs.by_ref() {
+                let end = start + usize::from(msg.header.hlen) + msg.payload.len();
+                self.message(&msg, &mut data[start..end]);
+                match msg.header.chain_offset {
+                    0 => break,
+                    next => start += next as usize,
+                }
+            }
+            pos += 4 + len;
+        }
+    }
+
+    /// Rewrites a message, `raw` being its header and body: span ranges index it directly.
+    fn message(&mut self, msg: &SMBMsg, raw: &mut [u8]) {
+        let hlen = usize::from(msg.header.hlen);
+        let spans = Body::spans(&msg.header, &msg.payload);
+        let span = |name: &str| {
+            spans
+                .iter()
+                .find(|span| span.name == name)
+                .map(|span| span.range.clone())
+        };
+        match msg.body() {
+            Ok(Body::NegotiateRequest(_)) => {
+                if let Some(range) = span("netname") {
+                    let host = utf16le(&raw[range.clone()]);
+                    let pseudonym = self.host(&host);
+                    put_utf16(&mut raw[range], &pseudonym);
+                }
+            }
+            // SPNEGO hints, which can name the server principal and realm
+            Ok(
+                Body::NegotiateResponse(_)
+                | Body::SessionSetupRequest(_)
+                | Body::SessionSetupResponse(_),
+            ) => {
+                if let Some(range) = span("security_buffer") {
+                    self.token(&mut raw[range]);
+                }
+            }
+            Ok(Body::TreeConnectRequest(_)) => {
+                if let Some(range) = span("path") {
+                    self.rewrite_path(&mut raw[range]);
+                }
+            }
+            Ok(Body::CreateRequest(req)) => {
+                // named pipes are protocol names
+                let pipe = INTERFACES
+                    .iter()
+                    .any(|interface| interface.name.eq_ignore_ascii_case(&req.name));
+                if let Some(range) = span("name").filter(|_| !pipe) {
+                    self.rewrite_path(&mut raw[range]);
+                }
+                if let Some(range) = span("contexts") {
+                    create_contexts(&mut raw[range]);
+                }
+            }
+            Ok(Body::CreateResponse(_)) => {
+                if let Some(range) = span("contexts") {
+                    create_contexts(&mut raw[range]);
+                }
+            }
+            Ok(Body::ReadResponse(_) | Body::WriteRequest(_)) => {
+                if let Some(range) = span("data") {
+                    blank_data(&mut raw[range]);
+                }
+            }
+            Ok(Body::IoctlRequest(_) | Body::IoctlResponse(_)) => {
+                for name in ["input", "output"] {
+                    if let Some(range) = span(name) {
+                        blank_data(&mut raw[range]);
+                    }
+                }
+            }
+            Ok(Body::SetInfoRequest(req)) => {
+                if let Some(range) = span("raw") {
+                    match req.info {
+                        // FileNameLength then FileName, after ReplaceIfExists, Reserved and
+                        // RootDirectory
+                        SetInfo::Rename { .. } | SetInfo::Link { .. } => {
+                            let name = range.start + 20..range.end;
+                            self.rewrite_path(&mut raw[name]);
+                        }
+                        SetInfo::Other(_) => blank(&mut raw[range]),
+                        _ => {}
+                    }
+                }
+            }
+            Ok(Body::NotifyResponse(_)) => {
+                if let Some(range) = span("buf") {
+                    self.notify(&mut raw[range]);
+                }
+            }
+            // the required size is all there is to it
+            Ok(Body::Error(err))
+                if err
+                    .details
+                    .iter()
+                    .all(|detail| matches!(detail, ErrorDetail::BufferTooSmall(_))) => {}
+            Ok(Body::Error(_) | Body::Other) | Err(_) => {
+                // StructureSize counts the first byte of the buffer when it's odd
+                let fixed = raw.get(hlen..hlen + 2).map_or(0, |size| {
+                    usize::from(u16::from_le_bytes([size[0], size[1]]) & !1)
+                });
+                blank(raw.get_mut(hlen + fixed..).unwrap_or_default());
+            }
+            Ok(_) => {}
+        }
+        // security descriptors of CREATE contexts and SET_INFO, among others
+        self.sids(&mut raw[hlen..]);
+    }
+
+    /// A GSS token: the names of NTLMSSP messages are replaced and its challenge responses,
+    /// session key and target info blanked, other mechanisms (Kerberos) are blanked whole.
+    fn token(&mut self, blob: &mut [u8]) {
+        let start = blob.windows(8).position(|w| w == b"NTLMSSP\0");
+        let Some((start, (spans, unicode))) =
+            start.and_then(|start| Some((start, ntlmssp::spans(&blob[start..])?)))
+        else {
+            blank(blob);
+            return;
+        };
+        let message = &mut blob[start..];
+        for Span { name, range } in spans {
+            let kind = match name {
+                "target_name" | "domain_name" => Kind::Domain,
+                "user_name" => Kind::User,
+                "workstation" => Kind::Host,
+                _ => {
+                    blank(&mut message[range]);
+                    continue;
+                }
+            };
+            let raw = &mut message[range];
+            if unicode {
+                let text = utf16le(raw);
+                let pseudonym = match kind {
+                    Kind::Host => self.host(&text),
+                    _ => self.utf16_name(kind, &text),
+                };
+                put_utf16(raw, &pseudonym);
+            } else {
+                let text = String::from_utf8_lossy(raw).into_owned();
+                let pseudonym = match kind {
+                    Kind::Host if text.is_ascii() => self.host(&text),
+                    _ => self.name(kind, &text, raw.len()),
+                };
+                raw.copy_from_slice(pseudonym.as_bytes());
+            }
+        }
+    }
+
+    /// FILE_NOTIFY_INFORMATION entries: NextEntryOffset, Action, FileNameLength, FileName.
+    fn notify(&mut self, buf: &mut [u8]) {
+        let mut start = 0;
+        while let Some(head) = buf.get(start..start + 12) {
+            let word =
+                |at: usize| u32::from_le_bytes(head[at..at + 4].try_into().expect("4 bytes"));
+            let (next, len) = (word(0) as usize, word(8) as usize);
+            if let Some(name) = buf.get_mut(start + 12..start + 12 + len) {
+                self.rewrite_path(name);
+            }
+            if next == 0 {
+                break;
+            }
+            start += next;
+        }
+    }
+
+    /// Domain SIDs (S-1-5-21-...) wherever they are, found by their binary layout: revision 1,
+    /// a count of sub authorities, authority 5 and a first sub authority of 21.
+    fn sids(&mut self, raw: &mut [u8]) {
+        let mut at = 0;
+        while at + 12 <= raw.len() {
+            let count = usize::from(raw[at + 1]);
+            let end = at + 8 + 4 * count;
+            let domain_sid = raw[at] == 1
+                && (4..=15).contains(&count)
+                && raw[at + 2..at + 8] == [0, 0, 0, 0, 0, 5]
+                && raw[at + 8..at + 12] == 21u32.to_le_bytes()
+                && end <= raw.len();
+            if !domain_sid {
+                at += 1;
+                continue;
+            }
+            let mut sub: Vec<u32> = raw[at + 8..end]
+                .chunks(4)
+                .map(|word| u32::from_le_bytes(word.try_into().expect("4 bytes")))
+                .collect();
+            let original = sub.clone();
+            self.sid(&mut sub);
+            for (word, value) in raw[at + 8..end].chunks_mut(4).zip(&sub) {
+                word.copy_from_slice(&value.to_le_bytes());
+            }
+            let text = |sub: &[u32]| {
+                sub.iter()
+                    .fold("S-1-5".to_owned(), |text, value| format!("{text}-{value}"))
+            };
+            self.table
+                .entry((Kind::Sid, text(&original)))
+                .or_insert_with(|| text(&sub));
+            at = end;
+        }
+    }
+
+    /// The sub authorities of a domain SID, 21 then the domain identifier and the RID if any.
+    fn sid(&mut self, sub: &mut [u32]) {
+        let domain = [sub[1], sub[2], sub[3]];
+        let count = self.domains.len() as u32;
+        let index = *self.domains.entry(domain).or_insert(count + 1);
+        sub[1..4].fill(index);
+        if let Some(rid) = sub.get_mut(4).filter(|rid| **rid >= FIRST_USER_RID) {
+            let count = self.rids.len() as u32;
+            *rid = *self
+                .rids
+                .entry((domain, *rid))
+                .or_insert(FIRST_USER_RID + count);
+        }
+    }
+
+    /// What replaced what, one `kind original pseudonym` line each, tab separated.
+    pub fn mapping(&self) -> String {
+        let mut out = String::from("kind\toriginal\tpseudonym\n");
+        for ((kind, original), pseudonym) in &self.table {
+            out += &format!("{kind}\t{original}\t{pseudonym}\n");
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tcp::flags::FlagCollection,
+        tcpdump::{data::Data, header::Header},
+    };
+    use chrono::NaiveTime;
+    use std::net::SocketAddr;
+
+    const CLIENT: &str = "10.0.0.2:50000";
+    /// As long as its pseudonym, so that it can stay in a path.
+    const SERVER: &str = "10.0.10.10:445";
+    const DOMAIN_SID: [u32; 4] = [21, 3_623_811_015, 3_361_044_348, 30_300_820];
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
+    }
+
+    /// Neither as UTF-16 nor as ASCII, whatever the case.
+    fn assert_gone(raw: &[u8], text: &str) {
+        for text in [text.to_owned(), text.to_uppercase(), text.to_lowercase()] {
+            assert!(!contains(raw, &utf16(&text)), "{text:?} is left as UTF-16");
+            assert!(!contains(raw, text.as_bytes()), "{text:?} is left as ASCII");
+        }
+    }
+
+    fn sid(rid: u32) -> Vec<u8> {
+        let mut raw = vec![1, 5, 0, 0, 0, 0, 0, 5];
+        for value in DOMAIN_SID.iter().chain([&rid]) {
+            raw.extend(value.to_le_bytes());
+        }
+        raw
+    }
+
+    fn pad(raw: &mut Vec<u8>, to: usize) {
+        raw.resize(raw.len().next_multiple_of(to), 0);
+    }
+
+    /// SMB2 header then `body`.
+    fn smb(command: u16, response: bool, msg_id: u64, body: &[u8]) -> Vec<u8> {
+        let mut raw = b"\xfeSMB".to_vec();
+        raw.extend(64u16.to_le_bytes());
+        raw.extend(1u16.to_le_bytes());
+        raw.extend(0u32.to_le_bytes());
+        raw.extend(command.to_le_bytes());
+        raw.extend(1u16.to_le_bytes());
+        raw.extend(u32::from(response).to_le_bytes());
+        raw.extend(0u32.to_le_bytes());
+        raw.extend(msg_id.to_le_bytes());
+        raw.extend(0xfeffu32.to_le_bytes());
+        raw.extend(1u32.to_le_bytes());
+        raw.extend(0x11u64.to_le_bytes());
+        raw.extend([0; 16]);
+        raw.extend(body);
+        raw
+    }
+
+    /// A segment carrying `smb` with valid checksums.
+    fn segment(to_server: bool, smb: Vec<u8>) -> TcpdumpMsg {
+        let (client, server): (SocketAddr, SocketAddr) =
+            (CLIENT.parse().unwrap(), SERVER.parse().unwrap());
+        let (src, dst) = if to_server {
+            (client, server)
+        } else {
+            (server, client)
+        };
+        let mut data = (smb.len() as u32).to_be_bytes().to_vec();
+        data.extend(smb);
+        let octets = |addr: SocketAddr| match addr.ip() {
+            IpAddr::V4(ip) => ip.octets(),
+            IpAddr::V6(_) => unreachable!(),
+        };
+        let mut ip = vec![0x45, 0];
+        ip.extend((40 + data.len() as u16).to_be_bytes());
+        ip.extend([0, 0, 0x40, 0, 64, 6, 0, 0]);
+        ip.extend(octets(src));
+        ip.extend(octets(dst));
+        let sum = checksum(&ip);
+        ip[10..12].copy_from_slice(&sum.to_be_bytes());
+        let mut tcp = vec![];
+        tcp.extend(src.port().to_be_bytes());
+        tcp.extend(dst.port().to_be_bytes());
+        tcp.extend(1000u32.to_be_bytes());
+        tcp.extend(5000u32.to_be_bytes());
+        tcp.extend([0x50, 0x18, 0x01, 0xf6, 0, 0, 0, 0]);
+        let pseudo = [
+            &ip[12..20],
+            &[0, 6],
+            &(20 + data.len() as u16).to_be_bytes(),
+        ]
+        .concat();
+        let sum = checksum(&[pseudo.as_slice(), &tcp, &data].concat());
+        tcp[16..18].copy_from_slice(&sum.to_be_bytes());
+        TcpdumpMsg {
+            header: Header {
+                time: NaiveTime::default(),
+                src,
+                dst,
+                seq: Some(1000),
+                ack: Some(5000),
+                win: 502,
+                options: None,
+                length: data.len() as u128,
+                flags: FlagCollection::from_bits(0x18),
+            },
+            data: Data {
+                ip_header: ip,
+                tcp_header: tcp,
+                data,
+            },
+        }
+    }
+
+    /// Rewrites `msg` and checks it is still a valid segment with the same SMB2 structure.
+    fn anonymize(anonymizer: &mut Anonymizer, mut msg: TcpdumpMsg) -> TcpdumpMsg {
+        let before = SMBMsg::parse_packet(&msg.data.data).unwrap();
+        let len = msg.data.data.len();
+        anonymizer.packet(&mut msg);
+
+        assert_eq!(msg.data.data.len(), len);
+        let ip = &msg.data.ip_header;
+        assert_eq!(checksum(ip), 0, "IP checksum");
+        let total = 40 + msg.data.data.len() as u16;
+        assert_eq!(ip[2..4], total.to_be_bytes());
+        let pseudo = [&ip[12..20], &[0, 6], &(total - 20).to_be_bytes()].concat();
+        let tcp = [pseudo.as_slice(), &msg.data.tcp_header, &msg.data.data].concat();
+        assert_eq!(checksum(&tcp), 0, "TCP checksum");
+
+        let after = SMBMsg::parse_packet(&msg.data.data).unwrap();
+        assert_eq!(after.len(), before.len());
+        for (after, before) in after.iter().zip(&before) {
+            assert_eq!(after.header, before.header);
+            assert_eq!(after.payload.len(), before.payload.len());
+            let variant = |msg: &SMBMsg| {
+                format!("{:?}", msg.body())
+                    .split('(')
+                    .nth(1)
+                    .map(str::to_owned)
+            };
+            assert_eq!(variant(after), variant(before));
+        }
+        msg
+    }
+
+    fn tree_connect(path: &str) -> TcpdumpMsg {
+        let path = utf16(path);
+        let mut body = vec![9, 0, 0, 0];
+        body.extend(72u16.to_le_bytes());
+        body.extend((path.len() as u16).to_le_bytes());
+        body.extend(path);
+        segment(true, smb(0x03, false, 4, &body))
+    }
+
+    fn create_context(name: &[u8; 4], data: &[u8], last: bool) -> Vec<u8> {
+        let mut raw = vec![];
+        let len = (24 + data.len()).next_multiple_of(8);
+        raw.extend(if last { 0u32 } else { len as u32 }.to_le_bytes());
+        raw.extend(16u16.to_le_bytes());
+        raw.extend(4u16.to_le_bytes());
+        raw.extend(0u16.to_le_bytes());
+        raw.extend(24u16.to_le_bytes());
+        raw.extend((data.len() as u32).to_le_bytes());
+        raw.extend(name);
+        raw.extend([0; 4]);
+        raw.extend(data);
+        pad(&mut raw, 8);
+        raw
+    }
+
+    fn create(name: &str, contexts: &[u8]) -> TcpdumpMsg {
+        let name = utf16(name);
+        let mut body = 57u16.to_le_bytes().to_vec();
+        body.extend([0, 0]);
+        body.extend(2u32.to_le_bytes());
+        body.extend([0; 16]);
+        body.extend(0x0012_019fu32.to_le_bytes());
+        body.extend(0x80u32.to_le_bytes());
+        body.extend(7u32.to_le_bytes());
+        body.extend(3u32.to_le_bytes());
+        body.extend(0x40u32.to_le_bytes());
+        body.extend(120u16.to_le_bytes());
+        body.extend((name.len() as u16).to_le_bytes());
+        let contexts_offset = (120 + name.len()).next_multiple_of(8);
+        body.extend((contexts_offset as u32).to_le_bytes());
+        body.extend((contexts.len() as u32).to_le_bytes());
+        body.extend(name);
+        body.resize(contexts_offset - 64, 0);
+        body.extend(contexts);
+        segment(true, smb(0x05, false, 5, &body))
+    }
+
+    fn ntlmssp_authenticate(domain: &str, user: &str, workstation: &str) -> Vec<u8> {
+        let payload: [Vec<u8>; 6] = [
+            vec![0x11; 24],
+            vec![0x22; 48],
+            utf16(domain),
+            utf16(user),
+            utf16(workstation),
+            vec![0x33; 16],
+        ];
+        let mut raw = b"NTLMSSP\0".to_vec();
+        raw.extend(3u32.to_le_bytes());
+        let mut offset = 64;
+        for field in &payload {
+            raw.extend((field.len() as u16).to_le_bytes());
+            raw.extend((field.len() as u16).to_le_bytes());
+            raw.extend((offset as u32).to_le_bytes());
+            offset += field.len();
+        }
+        // NEGOTIATE_UNICODE | NEGOTIATE_NTLM | NEGOTIATE_KEY_EXCH
+        raw.extend(0x4000_0201u32.to_le_bytes());
+        raw.extend(payload.concat());
+        raw
+    }
+
+    fn session_setup(token: &[u8]) -> TcpdumpMsg {
+        let mut body = vec![25, 0, 0, 1];
+        body.extend([0; 8]);
+        body.extend(88u16.to_le_bytes());
+        body.extend((token.len() as u16).to_le_bytes());
+        body.extend([0; 8]);
+        body.extend(token);
+        segment(true, smb(0x01, false, 2, &body))
+    }
+
+    fn notify_response(names: &[&str]) -> TcpdumpMsg {
+        let mut entries = vec![];
+        for (i, name) in names.iter().enumerate() {
+            let name = utf16(name);
+            let len = (12 + name.len()).next_multiple_of(4);
+            let next = if i + 1 == names.len() { 0 } else { len };
+            entries.extend((next as u32).to_le_bytes());
+            entries.extend(3u32.to_le_bytes());
+            entries.extend((name.len() as u32).to_le_bytes());
+            entries.extend(name);
+            pad(&mut entries, 4);
+        }
+        let mut body = vec![9, 0];
+        body.extend(72u16.to_le_bytes());
+        body.extend((entries.len() as u32).to_le_bytes());
+        body.extend(entries);
+        segment(false, smb(0x0f, true, 7, &body))
+    }
+
+    /// FSCTL_PIPE_TRANSCEIVE response with an LSA response PDU naming `user` with `sid`.
+    fn lsa_response(user: &str, sid: &[u8]) -> TcpdumpMsg {
+        let mut stub = vec![];
+        stub.extend(utf16(user));
+        pad(&mut stub, 4);
+        stub.extend(sid);
+        stub.extend(0u32.to_le_bytes());
+        let mut pdu = vec![5, 0, 2, 3, 0x10, 0, 0, 0];
+        pdu.extend((24 + stub.len() as u16).to_le_bytes());
+        pdu.extend(0u16.to_le_bytes());
+        pdu.extend(9u32.to_le_bytes());
+        pdu.extend((stub.len() as u32).to_le_bytes());
+        pdu.extend([0, 0, 0, 0]);
+        pdu.extend(stub);
+        let mut body = vec![49, 0, 0, 0];
+        body.extend(0x0011_c017u32.to_le_bytes());
+        body.extend([0x42; 16]);
+        body.extend(112u32.to_le_bytes());
+        body.extend(0u32.to_le_bytes());
+        body.extend(112u32.to_le_bytes());
+        body.extend((pdu.len() as u32).to_le_bytes());
+        body.extend([0; 8]);
+        body.extend(pdu);
+        segment(false, smb(0x0b, true, 9, &body))
+    }
+
+    #[test]
+    fn invented_names_are_all_different() {
+        let names: Vec<String> = (1..).map_while(|i| invent('h', i, 2)).collect();
+        assert_eq!(names.len(), 9 + 26 * 26);
+        assert_eq!(names[0], "h1");
+        assert_eq!(names[9], "aa");
+        let unique: HashSet<&String> = names.iter().collect();
+        assert_eq!(unique.len(), names.len());
+        assert!(names.iter().all(|name| name.len() == 2));
+    }
+
+    #[test]
+    fn pseudonyms_are_none_of_the_originals() {
+        let mut first = Anonymizer::default();
+        first.name(Kind::Host, "ab", 2);
+        first.name(Kind::Host, "H1", 2);
+        // `ab` would get `h1` otherwise, which is the name of another host
+        let mut anonymizer = first.restart();
+        let ab = anonymizer.name(Kind::Host, "ab", 2);
+        let h1 = anonymizer.name(Kind::Host, "H1", 2);
+        assert_ne!(ab.to_lowercase(), "h1");
+        assert_ne!(h1.to_lowercase(), "h1");
+        assert_ne!(ab, h1);
+        assert_eq!(anonymizer.name(Kind::Host, "AB", 2), ab);
+    }
+
+    #[test]
+    fn names_are_blanked_when_pseudonyms_run_out() {
+        let letters: Vec<String> = ('a'..='z').map(String::from).collect();
+        let mut first = Anonymizer::default();
+        for letter in &letters {
+            let pseudonym = first.name(Kind::Path, letter, 1);
+            assert_ne!(&pseudonym, letter);
+        }
+        // every one letter name is a name of the capture
+        let mut anonymizer = first.restart();
+        assert_eq!(anonymizer.name(Kind::Path, "a", 1), "\0");
+        assert!(anonymizer
+            .mapping()
+            .contains(&format!("path\ta\t{BLANKED}\n")));
+        assert_eq!(anonymizer.name(Kind::Path, "ab", 2), "f1");
+    }
+
+    #[test]
+    fn tree_connect_keeps_the_mapping_across_packets() {
+        let mut anonymizer = Anonymizer::default();
+        let first = anonymize(&mut anonymizer, tree_connect("\\\\FILESRV01\\Finance"));
+        let mapping = anonymizer.mapping();
+        let second = anonymize(&mut anonymizer, tree_connect("\\\\filesrv01\\FINANCE"));
+        assert!(anonymizer
+            .mapping()
+            .contains("host\tfilesrv01\th00000001\n"));
+        for msg in [&first, &second] {
+            assert_gone(&msg.data.data, "filesrv01");
+            assert_gone(&msg.data.data, "finance");
+        }
+        let path = |msg: &TcpdumpMsg| match SMBMsg::parse_packet(&msg.data.data).unwrap()[0].body()
+        {
+            Ok(Body::TreeConnectRequest(req)) => req.path,
+            other => panic!("{other:?}"),
+        };
+        assert_eq!(path(&first).to_lowercase(), path(&second).to_lowercase());
+        assert_eq!(path(&first), "\\\\h00000001\\s000001");
+        assert!(mapping.contains("host\tFILESRV01\th00000001\n"));
+        assert!(mapping.contains("share\tFinance\ts000001\n"));
+        // the addresses of the segment are in the table too
+        assert!(mapping.contains("ip\t10.0.10.10\t198.18.0.2\n"));
+        assert_eq!(first.header.dst.ip().to_string(), "198.18.0.2");
+    }
+
+    #[test]
+    fn addresses_in_paths_are_mapped_like_the_addresses() {
+        let mut anonymizer = Anonymizer::default();
+        let msg = anonymize(&mut anonymizer, tree_connect("\\\\10.0.10.10\\IPC$"));
+        let raw = &msg.data.data;
+        assert!(contains(raw, &utf16("\\\\198.18.0.2\\IPC$")));
+        assert_gone(raw, "10.0.10.10");
+
+        // too short to hold its pseudonym
+        let msg = anonymize(&mut anonymizer, tree_connect("\\\\10.9.8.7\\IPC$"));
+        assert_gone(&msg.data.data, "10.9.8.7");
+        assert!(contains(
+            &msg.data.data,
+            &utf16("\\\\\0\0\0\0\0\0\0\0\\IPC$")
+        ));
+        let mapping = anonymizer.mapping();
+        assert!(mapping.contains("ip\t10.9.8.7\t198.18.0.3\n"));
+        assert!(mapping.contains(&format!("host\t10.9.8.7\t{BLANKED}\n")));
+
+        let literal = "fe80--1s12.ipv6-literal.net";
+        let msg = anonymize(
+            &mut anonymizer,
+            tree_connect(&format!("\\\\{literal}\\IPC$")),
+        );
+        assert_gone(&msg.data.data, "fe80--1");
+        assert!(anonymizer.mapping().contains("ip\tfe80::1\t2001:db8::4\n"));
+    }
+
+    #[test]
+    fn create_names_and_contexts() {
+        let mut contexts = create_context(b"RqLs", &[0xab; 32], false);
+        let ea = [b"\0\0\0\0\0\x08\x05\0SECRETEA\0".as_slice(), b"hello"].concat();
+        contexts.extend(create_context(b"ExtA", &ea, false));
+        contexts.extend(create_context(b"SecD", &sid(1105), true));
+        let mut anonymizer = Anonymizer::default();
+        let msg = anonymize(
+            &mut anonymizer,
+            create("Payroll\\2024\\salaries.xlsx", &contexts),
+        );
+        let raw = &msg.data.data;
+        for text in ["Payroll", "salaries", "SECRETEA", "hello"] {
+            assert_gone(raw, text);
+        }
+        assert!(!contains(raw, &sid(1105)));
+        // the lease is kept, and the extension
+        assert!(contains(raw, &[0xab; 32]));
+        assert!(contains(raw, &utf16(".xlsx")));
+        let msgs = SMBMsg::parse_packet(raw).unwrap();
+        match msgs[0].body() {
+            Ok(Body::CreateRequest(req)) => {
+                assert_eq!(req.name, "f000001\\f001\\f0000001.xlsx");
+                let names: Vec<&str> = req.contexts.iter().map(|c| c.name.as_str()).collect();
+                assert_eq!(names, ["RqLs", "ExtA", "SecD"]);
+            }
+            other => panic!("{other:?}"),
+        }
+
+        // the same directory in another packet
+        let msg = anonymize(&mut anonymizer, create("payroll\\2023", &[]));
+        assert!(contains(&msg.data.data, &utf16("f000001\\")));
+    }
+
+    #[test]
+    fn ntlmssp_authenticate_names() {
+        let token = ntlmssp_authenticate("CONTOSO", "jsmith", "LAPTOP-7");
+        let mut anonymizer = Anonymizer::default();
+        let msg = anonymize(&mut anonymizer, session_setup(&token));
+        let raw = &msg.data.data;
+        for text in ["CONTOSO", "jsmith", "LAPTOP-7"] {
+            assert_gone(raw, text);
+        }
+        for secret in [[0x11; 8], [0x22; 8], [0x33; 8]] {
+            assert!(!contains(raw, &secret));
+        }
+        let user = match SMBMsg::parse_packet(raw).unwrap()[0].body() {
+            Ok(Body::SessionSetupRequest(req)) => ntlmssp::Token::find(&req.security_buffer),
+            other => panic!("{other:?}"),
+        };
+        assert!(format!("{user:?}").contains("u00001"), "{user:?}");
+        let mapping = anonymizer.mapping();
+        assert!(mapping.contains("domain\tCONTOSO\td000001\n"));
+        assert!(mapping.contains("user\tjsmith\tu00001\n"));
+        assert!(mapping.contains("host\tLAPTOP-7\th0000001\n"));
+
+        // the same user in another message gets the same pseudonym
+        let token = ntlmssp_authenticate("contoso", "JSMITH", "LAPTOP-7");
+        let again = anonymize(&mut anonymizer, session_setup(&token));
+        assert_eq!(again.data.data, msg.data.data);
+    }
+
+    #[test]
+    fn change_notify_names() {
+        let mut anonymizer = Anonymizer::default();
+        anonymize(&mut anonymizer, create("Payroll", &[]));
+        let msg = anonymize(
+            &mut anonymizer,
+            notify_response(&["Payroll\\bonus.docx", "~$bonus.docx"]),
+        );
+        let raw = &msg.data.data;
+        assert_gone(raw, "Payroll");
+        assert_gone(raw, "bonus");
+        match SMBMsg::parse_packet(raw).unwrap()[0].body() {
+            Ok(Body::NotifyResponse(res)) => {
+                let names: Vec<&str> = res.entries.iter().map(|e| e.name.as_str()).collect();
+                assert_eq!(names, ["f000001\\f0001.docx", "f000002.docx"]);
+            }
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn lsa_response_names_and_sids() {
+        let mut anonymizer = Anonymizer::default();
+        let msg = anonymize(&mut anonymizer, lsa_response("jsmith", &sid(1105)));
+        let raw = &msg.data.data;
+        assert_gone(raw, "jsmith");
+        for value in &DOMAIN_SID[1..] {
+            assert!(!contains(raw, &value.to_le_bytes()));
+        }
+        // the PDU header says what it was
+        assert!(contains(raw, &[5, 0, 2, 3]));
+    }
+}
@@ -0,0 +1,81 @@
+//! Input selection: a file or stdin, in tcpdump text (`tcpdump -x`) or pcap format, both read as
+//! the same TCP segments.
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{self, BufRead, BufReader},
+};
+
+use crate::{
+    pcap::{self, PcapIter},
+    tcpdump::{self, TcpdumpIter, TcpdumpMsg},
+};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum Format {
+    /// Told apart by the first bytes, pcap files start with a magic number.
+    #[default]
+    Auto,
+    Tcpdump,
+    Pcap,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Open(String, io::Error),
+    Tcpdump(tcpdump::Error),
+    Pcap(pcap::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Open(path, err) => write!(f, "can't open {path}: {err}"),
+            Self::Tcpdump(err) => write!(f, "tcpdump text {err:?}"),
+            Self::Pcap(err) => write!(f, "pcap {err:?}"),
+        }
+    }
+}
+
+pub type Segments = Box<dyn Iterator<Item = Result<TcpdumpMsg, Error>>>;
+
+/// Opens `path`, `-` being stdin.
+pub fn open(path: &str, format: Format) -> Result<Segments, Error> {
+    let mut stream: Box<dyn BufRead> = if path == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        let file = File::open(path).map_err(|err| Error::Open(path.to_owned(), err))?;
+        Box::new(BufReader::new(file))
+    };
+
+    let format = match format {
+        Format::Auto => {
+            let head = stream
+                .fill_buf()
+                .map_err(|err| Error::Open(path.to_owned(), err))?;
+            let magic = head
+                .get(..4)
+                .map(|m| u32::from_le_bytes(m.try_into().unwrap()));
+            let is_pcap = magic.is_some_and(|m| {
+                [pcap::MAGIC_MICROS, pcap::MAGIC_NANOS, pcap::MAGIC_PCAPNG]
+                    .iter()
+                    .any(|magic| m == *magic || m.swap_bytes() == *magic)
+            });
+            if is_pcap {
+                Format::Pcap
+            } else {
+                Format::Tcpdump
+            }
+        }
+        format => format,
+    };
+
+    Ok(match format {
+        Format::Pcap => Box::new(
+            PcapIter::new(stream)
+                .map_err(Error::Pcap)?
+                .map(|msg| msg.map_err(Error::Pcap)),
+        ),
+        _ => Box::new(TcpdumpIter::new(stream).map(|msg| msg.map_err(Error::Tcpdump))),
+    })
+}
@@ -0,0 +1,392 @@
+//! Command line arguments, parsed by hand as there are only a few of them.
+use std::{
+    fmt::Display,
+    net::{IpAddr, SocketAddr},
+};
+
+use crate::{
+    analysis::srt::Breakdown,
+    capture::Format,
+    filter::{self, fields::Message, Expr},
+    prettify::{
+        byte::Dump,
+        color::{Theme, When},
+        report,
+        sequence::Style,
+    },
+    smb::{opcodes::Opcodes, status::NtStatus},
+};
+
+pub const USAGE: &str = "\
+usage: smbdump [options] [file...]
+
+Reads tcpdump -x text output or pcap files (stdin if no file or `-`) and prints the SMB2
+conversation with an analysis at the end.
+
+input:
+  -f, --format auto|tcpdump|pcap   input format, detected from the first bytes by default
+      --diff                       compare the operations of the two input files
+      --tui                        browse the messages in the terminal, with live filtering
+      --anonymize FILE             write the capture to FILE with addresses, names, paths and
+                                   SIDs replaced by pseudonyms and file data blanked, as pcap
+                                   if FILE ends in .pcap and tcpdump -x text otherwise
+      --mapping FILE               where --anonymize keeps what replaced what (FILE.mapping)
+
+output:
+  -o, --output text|report|json|oneline|mermaid|plantuml|ladder|html
+                                   message by message then the report, only the report, JSON
+                                   lines, one object per message (schema version 3), one line
+                                   per request and response pair, a sequence diagram of the
+                                   exchange, as Mermaid or PlantUML source or an ASCII ladder,
+                                   or one HTML file with no external assets to attach to tickets
+  -v, --verbose                    more detail (repeatable), up to the raw IP/TCP headers
+  -q, --quiet                      less detail (repeatable), down to one line per packet
+      --dump escaped|hex|utf16|fields
+                                   bytes as escaped ASCII, or as a hexdump with offsets (from
+                                   the SMB2 header for bodies), with a UTF-16LE column for utf16,
+                                   or of whole packets with each decoded field colored and listed
+      --color auto|always|never    ANSI colors, auto when stdout is a terminal and NO_COLOR
+                                   isn't set (CLICOLOR_FORCE forces them)
+      --theme ROLE=SGR:...         colors of the roles, replaces $SMBDUMP_COLORS, e.g.
+                                   'error=1;91:dim=90', see --theme-roles
+      --theme-roles                list the roles of a theme with their default colors
+      --srt-by share|client        break response times down further
+      --audit                      print the file operations timeline
+      --audit-csv FILE             write the file operations timeline as CSV
+
+filters (select what is printed, the analysis always sees the whole capture; repeat an
+option to accept several values):
+      --host IP                    packets from or to this address
+      --port PORT                  packets from or to this port
+      --conn IP:PORT               packets of the connection with this endpoint
+      --opcode NAME                messages of this command (Create, QUERY_DIRECTORY, 0x0e...)
+      --status NAME                responses with this status (ACCESS_DENIED, 0xC0000022...)
+      --frames FIRST-LAST          frame range, either bound can be omitted
+  -Y, --filter EXPR                messages matching a display filter expression, all of
+                                   them when repeated, e.g. for the failed opens of .docx files
+                                   'smb2.cmd == create && smb2.status != STATUS_SUCCESS
+                                   && smb2.path ~ \"\\.docx$\"'
+      --filter-fields              list the fields a filter expression can test
+
+  -h, --help                       this help";
+
+/// Most detailed output, with the IP and TCP headers of every packet.
+pub const MAX_VERBOSITY: u8 = 3;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Error {
+    UnknownArgument(String),
+    MissingValue(&'static str),
+    InvalidValue(&'static str, String),
+    /// `--flag=value` for a flag that takes none.
+    UnexpectedValue(String),
+    /// `--diff` needs exactly two inputs.
+    DiffInputs(usize),
+    /// Theme and what's wrong with it.
+    InvalidTheme(String, String),
+    /// Filter expression and what's wrong with it.
+    Filter(String, filter::Error),
+    Help,
+    /// Like `Help`, asks for the list of filter fields instead of a run.
+    FilterFields,
+    /// Same for the roles of a theme.
+    ThemeRoles,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownArgument(arg) => write!(f, "unknown argument {arg}"),
+            Self::MissingValue(arg) => write!(f, "{arg} takes a value"),
+            Self::InvalidValue(arg, value) => write!(f, "invalid value {value:?} for {arg}"),
+            Self::UnexpectedValue(arg) => write!(f, "{arg} doesn't take a value"),
+            Self::DiffInputs(count) => write!(f, "--diff takes two input files, got {count}"),
+            Self::InvalidTheme(theme, err) => write!(f, "invalid theme {theme:?}: {err}"),
+            Self::Filter(expr, err) => write!(f, "invalid filter: {err}\n{}", err.pointer(expr)),
+            Self::Help => write!(f, "{USAGE}"),
+            Self::FilterFields => write!(f, "--filter-fields"),
+            Self::ThemeRoles => write!(f, "--theme-roles"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum Output {
+    /// Every message, then the end of capture report.
+    #[default]
+    Text,
+    /// Only the end of capture report.
+    Report,
+    /// One JSON object per message, see `prettify::json` for the schema.
+    Json,
+    /// One line per operation, see `prettify::ops`.
+    Oneline,
+    /// A sequence diagram of the whole capture, printed at the end.
+    Sequence(Style),
+    /// A self-contained HTML page with the messages and the report, see `prettify::html`.
+    Html,
+}
+
+/// What gets printed. Values of one option are alternatives, different options must all match.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    pub hosts: Vec<IpAddr>,
+    pub ports: Vec<u16>,
+    pub conns: Vec<SocketAddr>,
+    pub opcodes: Vec<Opcodes>,
+    pub statuses: Vec<NtStatus>,
+    pub first_frame: Option<usize>,
+    pub last_frame: Option<usize>,
+    pub expr: Option<Expr>,
+}
+
+impl Filter {
+    pub fn packet(&self, frame: usize, src: SocketAddr, dst: SocketAddr) -> bool {
+        self.first_frame.is_none_or(|first| frame >= first)
+            && self.last_frame.is_none_or(|last| frame <= last)
+            && (self.hosts.is_empty()
+                || self
+                    .hosts
+                    .iter()
+                    .any(|ip| *ip == src.ip() || *ip == dst.ip()))
+            && (self.ports.is_empty()
+                || self
+                    .ports
+                    .iter()
+                    .any(|p| *p == src.port() || *p == dst.port()))
+            && (self.conns.is_empty() || self.conns.iter().any(|c| *c == src || *c == dst))
+    }
+
+    /// Only the messages of a packet are filtered by command, status and expression.
+    pub fn selects_messages(&self) -> bool {
+        !self.opcodes.is_empty() || !self.statuses.is_empty() || self.expr.is_some()
+    }
+
+    pub fn message(&self, msg: &Message) -> bool {
+        let header = msg.header;
+        (self.opcodes.is_empty() || self.opcodes.contains(&header.opcode))
+            && (self.statuses.is_empty()
+                || header.is_response() && self.statuses.contains(&header.status()))
+            && self.expr.as_ref().is_none_or(|expr| expr.matches(msg))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Args {
+    /// `-` is stdin.
+    pub inputs: Vec<String>,
+    pub format: Format,
+    pub output: Output,
+    /// 0 packet lines only, 1 decoded messages, 2 (default) payload dumps, 3 IP/TCP headers.
+    pub verbosity: u8,
+    pub dump: Dump,
+    pub color: When,
+    /// `--theme`, checked already.
+    pub theme: Option<String>,
+    pub filter: Filter,
+    pub report: report::Options,
+    pub audit_csv: Option<String>,
+    pub diff: bool,
+    pub tui: bool,
+    /// `--anonymize` output and mapping table.
+    pub anonymize: Option<String>,
+    pub mapping: Option<String>,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            inputs: vec![],
+            format: Format::Auto,
+            output: Output::Text,
+            verbosity: 2,
+            dump: Dump::Escaped,
+            color: When::Auto,
+            theme: None,
+            filter: Filter::default(),
+            report: report::Options::default(),
+            audit_csv: None,
+            diff: false,
+            tui: false,
+            anonymize: None,
+            mapping: None,
+        }
+    }
+}
+
+fn parse_frames(value: &str) -> Option<(Option<usize>, Option<usize>)> {
+    let bound = |s: &str| -> Option<Option<usize>> {
+        if s.is_empty() {
+            Some(None)
+        } else {
+            s.parse().ok().map(Some)
+        }
+    };
+    match value.split_once('-') {
+        Some((first, last)) => Some((bound(first)?, bound(last)?)),
+        None => {
+            let frame = value.parse().ok()?;
+            Some((Some(frame), Some(frame)))
+        }
+    }
+}
+
+impl Args {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, Error> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            // `--name=value` is the same as `--name value`
+            let (arg, mut inline) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => {
+                    (name.to_owned(), Some(value.to_owned()))
+                }
+                _ => (arg, None),
+            };
+            let mut value = |name: &'static str| {
+                inline
+                    .take()
+                    .or_else(|| args.next())
+                    .ok_or(Error::MissingValue(name))
+            };
+            let invalid =
+                |name: &'static str, value: &str| Error::InvalidValue(name, value.to_owned());
+
+            match arg.as_str() {
+                "-h" | "--help" => return Err(Error::Help),
+                "--filter-fields" => return Err(Error::FilterFields),
+                "--theme-roles" => return Err(Error::ThemeRoles),
+                "-f" | "--format" => {
+                    let v = value("--format")?;
+                    parsed.format = match v.as_str() {
+                        "auto" => Format::Auto,
+                        "tcpdump" => Format::Tcpdump,
+                        "pcap" => Format::Pcap,
+                        _ => return Err(invalid("--format", &v)),
+                    }
+                }
+                "--diff" => parsed.diff = true,
+                "--tui" => parsed.tui = true,
+                "--anonymize" => parsed.anonymize = Some(value("--anonymize")?),
+                "--mapping" => parsed.mapping = Some(value("--mapping")?),
+                "-o" | "--output" => {
+                    let v = value("--output")?;
+                    parsed.output = match v.as_str() {
+                        "text" => Output::Text,
+                        "report" => Output::Report,
+                        "json" => Output::Json,
+                        "oneline" => Output::Oneline,
+                        "mermaid" => Output::Sequence(Style::Mermaid),
+                        "plantuml" => Output::Sequence(Style::PlantUml),
+                        "ladder" => Output::Sequence(Style::Ladder),
+                        "html" => Output::Html,
+                        _ => return Err(invalid("--output", &v)),
+                    }
+                }
+                "-v" | "--verbose" => parsed.verbosity = (parsed.verbosity + 1).min(MAX_VERBOSITY),
+                "-q" | "--quiet" => parsed.verbosity = parsed.verbosity.saturating_sub(1),
+                "-vv" => parsed.verbosity = MAX_VERBOSITY,
+                "-qq" => parsed.verbosity = 0,
+                "--dump" => {
+                    let v = value("--dump")?;
+                    parsed.dump = match v.as_str() {
+                        "escaped" => Dump::Escaped,
+                        "hex" => Dump::Hex,
+                        "utf16" => Dump::HexUtf16,
+                        "fields" => Dump::Fields,
+                        _ => return Err(invalid("--dump", &v)),
+                    }
+                }
+                "--color" => {
+                    let v = value("--color")?;
+                    parsed.color = match v.as_str() {
+                        "auto" => When::Auto,
+                        "always" => When::Always,
+                        "never" => When::Never,
+                        _ => return Err(invalid("--color", &v)),
+                    }
+                }
+                "--theme" => {
+                    let v = value("--theme")?;
+                    Theme::default()
+                        .apply(&v)
+                        .map_err(|err| Error::InvalidTheme(v.clone(), err))?;
+                    parsed.theme = Some(v);
+                }
+                "--srt-by" => {
+                    let v = value("--srt-by")?;
+                    parsed.report.srt_by = match v.as_str() {
+                        "share" => Breakdown::Share,
+                        "client" => Breakdown::Client,
+                        _ => return Err(invalid("--srt-by", &v)),
+                    }
+                }
+                "--audit" => parsed.report.audit = true,
+                "--audit-csv" => parsed.audit_csv = Some(value("--audit-csv")?),
+                "--host" => {
+                    let v = value("--host")?;
+                    parsed
+                        .filter
+                        .hosts
+                        .push(v.parse().map_err(|_| invalid("--host", &v))?);
+                }
+                "--port" => {
+                    let v = value("--port")?;
+                    parsed
+                        .filter
+                        .ports
+                        .push(v.parse().map_err(|_| invalid("--port", &v))?);
+                }
+                "--conn" => {
+                    let v = value("--conn")?;
+                    parsed
+                        .filter
+                        .conns
+                        .push(v.parse().map_err(|_| invalid("--conn", &v))?);
+                }
+                "--opcode" => {
+                    let v = value("--opcode")?;
+                    parsed
+                        .filter
+                        .opcodes
+                        .push(Opcodes::from_name(&v).ok_or_else(|| invalid("--opcode", &v))?);
+                }
+                "--status" => {
+                    let v = value("--status")?;
+                    parsed
+                        .filter
+                        .statuses
+                        .push(NtStatus::from_name(&v).ok_or_else(|| invalid("--status", &v))?);
+                }
+                "--frames" => {
+                    let v = value("--frames")?;
+                    let (first, last) = parse_frames(&v).ok_or_else(|| invalid("--frames", &v))?;
+                    parsed.filter.first_frame = first;
+                    parsed.filter.last_frame = last;
+                }
+                "-Y" | "--filter" => {
+                    let v = value("--filter")?;
+                    let expr = Expr::parse(&v).map_err(|err| Error::Filter(v, err))?;
+                    // unlike the values of the other filters, expressions all have to match
+                    parsed.filter.expr = Some(match parsed.filter.expr.take() {
+                        Some(previous) => Expr::And(Box::new(previous), Box::new(expr)),
+                        None => expr,
+                    });
+                }
+                _ if arg.starts_with('-') && arg != "-" => return Err(Error::UnknownArgument(arg)),
+                _ => parsed.inputs.push(arg.clone()),
+            }
+            if inline.is_some() {
+                return Err(Error::UnexpectedValue(arg));
+            }
+        }
+
+        if parsed.diff && parsed.inputs.len() != 2 {
+            return Err(Error::DiffInputs(parsed.inputs.len()));
+        }
+        if parsed.inputs.is_empty() {
+            parsed.inputs.push("-".to_owned());
+        }
+        Ok(parsed)
+    }
+}
@@ -0,0 +1,268 @@
+use crate::smb::types::Guid;
+
+pub struct Interface {
+    pub name: &'static str,
+    pub uuid: Guid,
+    pub version: u16,
+    /// Operation names indexed by opnum, empty names are unused/unknown opnums.
+    pub opnums: &'static [&'static str],
+}
+
+pub const SRVSVC: Guid = Guid::from_fields(
+    0x4b324fc8,
+    0x1670,
+    0x01d3,
+    [0x12, 0x78, 0x5a, 0x47, 0xbf, 0x6e, 0xe1, 0x88],
+);
+pub const WKSSVC: Guid = Guid::from_fields(
+    0x6bffd098,
+    0xa112,
+    0x3610,
+    [0x98, 0x33, 0x46, 0xc3, 0xf8, 0x7e, 0x34, 0x5a],
+);
+pub const LSARPC: Guid = Guid::from_fields(
+    0x12345778,
+    0x1234,
+    0xabcd,
+    [0xef, 0x00, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab],
+);
+pub const SAMR: Guid = Guid::from_fields(
+    0x12345778,
+    0x1234,
+    0xabcd,
+    [0xef, 0x00, 0x01, 0x23, 0x45, 0x67, 0x89, 0xac],
+);
+pub const NETLOGON: Guid = Guid::from_fields(
+    0x12345678,
+    0x1234,
+    0xabcd,
+    [0xef, 0x00, 0x01, 0x23, 0x45, 0x67, 0xcf, 0xfb],
+);
+pub const WINREG: Guid = Guid::from_fields(
+    0x338cd001,
+    0x2244,
+    0x31f1,
+    [0xaa, 0xaa, 0x90, 0x00, 0x38, 0x00, 0x10, 0x03],
+);
+pub const SVCCTL: Guid = Guid::from_fields(
+    0x367abb81,
+    0x9844,
+    0x35f1,
+    [0xad, 0x32, 0x98, 0xf0, 0x38, 0x00, 0x10, 0x03],
+);
+pub const SPOOLSS: Guid = Guid::from_fields(
+    0x12345678,
+    0x1234,
+    0xabcd,
+    [0xef, 0x00, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab],
+);
+
+pub const NDR: Guid = Guid::from_fields(
+    0x8a885d04,
+    0x1ceb,
+    0x11c9,
+    [0x9f, 0xe8, 0x08, 0x00, 0x2b, 0x10, 0x48, 0x60],
+);
+pub const NDR64: Guid = Guid::from_fields(
+    0x71710533,
+    0xbeba,
+    0x4937,
+    [0x83, 0x19, 0xb5, 0xdb, 0xef, 0x9c, 0xcc, 0x36],
+);
+
+const SRVSVC_OPNUMS: &[&str] = &[
+    "NetrCharDevEnum",
+    "NetrCharDevGetInfo",
+    "NetrCharDevControl",
+    "NetrCharDevQEnum",
+    "NetrCharDevQGetInfo",
+    "NetrCharDevQSetInfo",
+    "NetrCharDevQPurge",
+    "NetrCharDevQPurgeSelf",
+    "NetrConnectionEnum",
+    "NetrFileEnum",
+    "NetrFileGetInfo",
+    "NetrFileClose",
+    "NetrSessionEnum",
+    "NetrSessionDel",
+    "NetrShareAdd",
+    "NetrShareEnum",
+    "NetrShareGetInfo",
+    "NetrShareSetInfo",
+    "NetrShareDel",
+    "NetrShareDelSticky",
+    "NetrShareCheck",
+    "NetrServerGetInfo",
+    "NetrServerSetInfo",
+    "NetrServerDiskEnum",
+    "NetrServerStatisticsGet",
+    "NetrServerTransportAdd",
+    "NetrServerTransportEnum",
+    "NetrServerTransportDel",
+    "NetrRemoteTOD",
+];
+
+const WKSSVC_OPNUMS: &[&str] = &[
+    "NetrWkstaGetInfo",
+    "NetrWkstaSetInfo",
+    "NetrWkstaUserEnum",
+    "",
+    "",
+    "NetrWkstaTransportEnum",
+];
+
+const LSARPC_OPNUMS: &[&str] = &[
+    "LsarClose",
+    "",
+    "LsarEnumeratePrivileges",
+    "LsarQuerySecurityObject",
+    "LsarSetSecurityObject",
+    "",
+    "LsarOpenPolicy",
+    "LsarQueryInformationPolicy",
+    "LsarSetInformationPolicy",
+    "",
+    "LsarCreateAccount",
+    "LsarEnumerateAccounts",
+    "LsarCreateTrustedDomain",
+    "LsarEnumerateTrustedDomains",
+    "LsarLookupNames",
+    "LsarLookupSids",
+    "LsarCreateSecret",
+    "LsarOpenAccount",
+    "LsarEnumeratePrivilegesAccount",
+    "LsarAddPrivilegesToAccount",
+    "LsarRemovePrivilegesFromAccount",
+    "",
+    "",
+    "LsarGetSystemAccessAccount",
+    "LsarSetSystemAccessAccount",
+    "LsarOpenTrustedDomain",
+    "LsarQueryInfoTrustedDomain",
+    "LsarSetInformationTrustedDomain",
+    "LsarOpenSecret",
+    "LsarSetSecret",
+    "LsarQuerySecret",
+    "LsarLookupPrivilegeValue",
+    "LsarLookupPrivilegeName",
+    "LsarLookupPrivilegeDisplayName",
+    "LsarDeleteObject",
+    "LsarEnumerateAccountsWithUserRight",
+    "LsarEnumerateAccountRights",
+    "LsarAddAccountRights",
+    "LsarRemoveAccountRights",
+    "LsarQueryTrustedDomainInfo",
+    "LsarSetTrustedDomainInfo",
+    "LsarDeleteTrustedDomain",
+    "LsarStorePrivateData",
+    "LsarRetrievePrivateData",
+    "LsarOpenPolicy2",
+    "LsarGetUserName",
+    "LsarQueryInformationPolicy2",
+    "LsarSetInformationPolicy2",
+    "LsarQueryTrustedDomainInfoByName",
+    "LsarSetTrustedDomainInfoByName",
+    "LsarEnumerateTrustedDomainsEx",
+    "LsarCreateTrustedDomainEx",
+    "",
+    "LsarQueryDomainInformationPolicy",
+    "LsarSetDomainInformationPolicy",
+    "LsarOpenTrustedDomainByName",
+    "",
+    "LsarLookupSids2",
+    "LsarLookupNames2",
+    "LsarCreateTrustedDomainEx2",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "LsarLookupNames3",
+    "",
+    "",
+    "",
+    "",
+    "LsarQueryForestTrustInformation",
+    "LsarSetForestTrustInformation",
+    "",
+    "LsarLookupSids3",
+    "LsarLookupNames4",
+];
+
+pub const INTERFACES: &[Interface] = &[
+    Interface {
+        name: "srvsvc",
+        uuid: SRVSVC,
+        version: 3,
+        opnums: SRVSVC_OPNUMS,
+    },
+    Interface {
+        name: "wkssvc",
+        uuid: WKSSVC,
+        version: 1,
+        opnums: WKSSVC_OPNUMS,
+    },
+    Interface {
+        name: "lsarpc",
+        uuid: LSARPC,
+        version: 0,
+        opnums: LSARPC_OPNUMS,
+    },
+    Interface {
+        name: "samr",
+        uuid: SAMR,
+        version: 1,
+        opnums: &[],
+    },
+    Interface {
+        name: "netlogon",
+        uuid: NETLOGON,
+        version: 1,
+        opnums: &[],
+    },
+    Interface {
+        name: "winreg",
+        uuid: WINREG,
+        version: 1,
+        opnums: &[],
+    },
+    Interface {
+        name: "svcctl",
+        uuid: SVCCTL,
+        version: 2,
+        opnums: &[],
+    },
+    Interface {
+        name: "spoolss",
+        uuid: SPOOLSS,
+        version: 1,
+        opnums: &[],
+    },
+    Interface {
+        name: "NDR",
+        uuid: NDR,
+        version: 2,
+        opnums: &[],
+    },
+    Interface {
+        name: "NDR64",
+        uuid: NDR64,
+        version: 1,
+        opnums: &[],
+    },
+];
+
+pub fn lookup(uuid: &Guid) -> Option<&'static Interface> {
+    INTERFACES.iter().find(|i| &i.uuid == uuid)
+}
+
+pub fn opnum_name(uuid: &Guid, opnum: u16) -> Option<&'static str> {
+    lookup(uuid)?
+        .opnums
+        .get(opnum as usize)
+        .copied()
+        .filter(|n| !n.is_empty())
+}
@@ -0,0 +1,219 @@
+//! [MS-LSAT] Local Security Authority (Translation Methods) Remote Protocol
+use super::ndr::{Error, Ndr, Sid, __};
+use crate::smb::status::NtStatus;
+
+pub const LSAR_LOOKUP_NAMES: u16 = 14;
+pub const LSAR_LOOKUP_SIDS: u16 = 15;
+pub const LSAR_LOOKUP_SIDS2: u16 = 57;
+pub const LSAR_LOOKUP_NAMES2: u16 = 58;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Request {
+    LsarLookupNames { names: Vec<String> },
+    LsarLookupSids { sids: Vec<Sid> },
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Response {
+    LsarLookupNames {
+        domains: Vec<Domain>,
+        sids: Vec<TranslatedSid>,
+        mapped_count: u32,
+        status: NtStatus,
+    },
+    LsarLookupSids {
+        domains: Vec<Domain>,
+        names: Vec<TranslatedName>,
+        mapped_count: u32,
+        status: NtStatus,
+    },
+}
+
+/// LSAPR_TRUST_INFORMATION
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Domain {
+    pub name: Option<String>,
+    pub sid: Option<Sid>,
+}
+
+/// LSAPR_TRANSLATED_NAME(_EX)
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TranslatedName {
+    pub use_: SidNameUse,
+    pub name: Option<String>,
+    pub domain_index: i32,
+}
+
+/// LSA_TRANSLATED_SID(_EX), `sid` is the domain SID with the RelativeId appended
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TranslatedSid {
+    pub use_: SidNameUse,
+    pub rid: u32,
+    pub domain_index: i32,
+    pub sid: Option<Sid>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub struct SidNameUse(pub u16);
+
+impl std::fmt::Debug for SidNameUse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self.0 {
+            1 => "User",
+            2 => "Group",
+            3 => "Domain",
+            4 => "Alias",
+            5 => "WellKnownGroup",
+            6 => "DeletedAccount",
+            7 => "Invalid",
+            8 => "Unknown",
+            9 => "Computer",
+            10 => "Label",
+            n => return write!(f, "SidNameUse({n})"),
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// LSAPR_HANDLE, an opaque 20 byte context handle
+fn policy_handle(ndr: &mut Ndr) -> Result<(), Error> {
+    __!(ndr.0.align(4));
+    __!(ndr.0.skip(20));
+    Ok(())
+}
+
+pub fn decode_request(opnum: u16, stub: &[u8]) -> Result<Option<Request>, Error> {
+    let mut ndr = Ndr::new(stub);
+    Ok(Some(match opnum {
+        LSAR_LOOKUP_NAMES | LSAR_LOOKUP_NAMES2 => {
+            policy_handle(&mut ndr)?;
+            let _count = ndr.u32()?;
+            let count = ndr.count()?;
+            let present: Vec<bool> = (0..count).map(|_| ndr.unicode_string()).try_collect()?;
+            let names = present
+                .into_iter()
+                .map(|p| Ok(ndr.unicode_string_buffer(p)?.unwrap_or_default()))
+                .try_collect()?;
+            Request::LsarLookupNames { names }
+        }
+        LSAR_LOOKUP_SIDS | LSAR_LOOKUP_SIDS2 => {
+            policy_handle(&mut ndr)?;
+            let _entries = ndr.u32()?;
+            let mut sids = vec![];
+            if ndr.ptr()? {
+                let count = ndr.count()?;
+                let present: Vec<bool> = (0..count).map(|_| ndr.ptr()).try_collect()?;
+                for _ in present.into_iter().filter(|p| *p) {
+                    sids.push(ndr.sid()?);
+                }
+            }
+            Request::LsarLookupSids { sids }
+        }
+        _ => return Ok(None),
+    }))
+}
+
+pub fn decode_response(opnum: u16, stub: &[u8]) -> Result<Option<Response>, Error> {
+    let mut ndr = Ndr::new(stub);
+    let ex = matches!(opnum, LSAR_LOOKUP_SIDS2 | LSAR_LOOKUP_NAMES2);
+    Ok(Some(match opnum {
+        LSAR_LOOKUP_NAMES | LSAR_LOOKUP_NAMES2 => {
+            let domains = referenced_domains(&mut ndr)?;
+            let _entries = ndr.u32()?;
+            let mut sids = vec![];
+            if ndr.ptr()? {
+                let count = ndr.count()?;
+                for _ in 0..count {
+                    let use_ = SidNameUse(ndr.u16()?);
+                    let rid = ndr.u32()?;
+                    let domain_index = ndr.i32()?;
+                    if ex {
+                        let _flags = ndr.u32()?;
+                    }
+                    let sid = usize::try_from(domain_index)
+                        .ok()
+                        .and_then(|i| domains.get(i))
+                        .and_then(|d| d.sid.as_ref())
+                        .map(|d| {
+                            if use_.0 == 3 {
+                                d.clone()
+                            } else {
+                                d.with_rid(rid)
+                            }
+                        });
+                    sids.push(TranslatedSid {
+                        use_,
+                        rid,
+                        domain_index,
+                        sid,
+                    });
+                }
+            }
+            Response::LsarLookupNames {
+                domains,
+                sids,
+                mapped_count: ndr.u32()?,
+                status: NtStatus(ndr.u32()?),
+            }
+        }
+        LSAR_LOOKUP_SIDS | LSAR_LOOKUP_SIDS2 => {
+            let domains = referenced_domains(&mut ndr)?;
+            let _entries = ndr.u32()?;
+            let mut names = vec![];
+            if ndr.ptr()? {
+                let count = ndr.count()?;
+                let mut present = vec![];
+                for _ in 0..count {
+                    let use_ = SidNameUse(ndr.u16()?);
+                    present.push(ndr.unicode_string()?);
+                    let domain_index = ndr.i32()?;
+                    if ex {
+                        let _flags = ndr.u32()?;
+                    }
+                    names.push(TranslatedName {
+                        use_,
+                        name: None,
+                        domain_index,
+                    });
+                }
+                for (name, p) in names.iter_mut().zip(present) {
+                    name.name = ndr.unicode_string_buffer(p)?;
+                }
+            }
+            Response::LsarLookupSids {
+                domains,
+                names,
+                mapped_count: ndr.u32()?,
+                status: NtStatus(ndr.u32()?),
+            }
+        }
+        _ => return Ok(None),
+    }))
+}
+
+/// `[out] PLSAPR_REFERENCED_DOMAIN_LIST* ReferencedDomains`
+fn referenced_domains(ndr: &mut Ndr) -> Result<Vec<Domain>, Error> {
+    let mut domains = vec![];
+    if !ndr.ptr()? {
+        return Ok(domains);
+    }
+    let _entries = ndr.u32()?;
+    let array = ndr.ptr()?;
+    let _max_entries = ndr.u32()?;
+    if !array {
+        return Ok(domains);
+    }
+
+    let count = ndr.count()?;
+    let mut present = vec![];
+    for _ in 0..count {
+        present.push((ndr.unicode_string()?, ndr.ptr()?));
+    }
+    for (name, sid) in present {
+        domains.push(Domain {
+            name: ndr.unicode_string_buffer(name)?,
+            sid: sid.then(|| ndr.sid()).transpose()?,
+        });
+    }
+    Ok(domains)
+}
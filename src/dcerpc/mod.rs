@@ -0,0 +1,474 @@
+//! Connection oriented DCE/RPC (C706 chapter 12 + [MS-RPCE] 2.2.2) as carried over SMB2 named
+//! pipes on the `IPC$` share.
+pub mod interfaces;
+pub mod lsarpc;
+pub mod ndr;
+pub mod srvsvc;
+pub mod wkssvc;
+
+use bitflags::bitflags;
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+use std::fmt::Debug;
+
+use crate::smb::{reader::Reader, types::Guid};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Error {
+    ExpectedByte,
+    UnsupportedVersion(u8, u8),
+    UnsupportedDataRepresentation,
+    UnknownPduType(u8),
+    InvalidFragLength,
+}
+
+macro_rules! __ {
+    ($exp:expr) => {
+        match $exp {
+            Some(val) => val,
+            None => return Err(Error::ExpectedByte),
+        }
+    };
+}
+
+pub const COMMON_HEADER_LEN: usize = 16;
+
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, FromPrimitive)]
+pub enum PduType {
+    Request = 0,
+    Ping = 1,
+    Response = 2,
+    Fault = 3,
+    Working = 4,
+    Nocall = 5,
+    Reject = 6,
+    Ack = 7,
+    ClCancel = 8,
+    Fack = 9,
+    CancelAck = 10,
+    Bind = 11,
+    BindAck = 12,
+    BindNak = 13,
+    AlterContext = 14,
+    AlterContextResp = 15,
+    Auth3 = 16,
+    Shutdown = 17,
+    CoCancel = 18,
+    Orphaned = 19,
+}
+
+bitflags! {
+    #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+    pub struct PfcFlags: u8 {
+        const FirstFrag = 0x01;
+        const LastFrag = 0x02;
+        const PendingCancel = 0x04;
+        const ConcMpx = 0x10;
+        const DidNotExecute = 0x20;
+        const Maybe = 0x40;
+        const ObjectUuid = 0x80;
+    }
+}
+
+// 12.6.3.1 The Common Fields
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PduHeader {
+    pub ptype: PduType,
+    pub flags: PfcFlags,
+    pub frag_length: u16,
+    pub auth_length: u16,
+    pub call_id: u32,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SyntaxId {
+    pub uuid: Guid,
+    pub version: u16,
+    pub version_minor: u16,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ContextElem {
+    pub context_id: u16,
+    pub abstract_syntax: SyntaxId,
+    pub transfer_syntaxes: Vec<SyntaxId>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ContextResult {
+    ///  0 acceptance, 1 user rejection, 2 provider rejection, 3 negotiate ack ([MS-RPCE] bind time
+    /// feature negotiation).
+    pub result: u16,
+    pub reason: u16,
+    pub transfer_syntax: SyntaxId,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum PduBody {
+    /// Bind and AlterContext
+    Bind {
+        max_xmit_frag: u16,
+        max_recv_frag: u16,
+        assoc_group_id: u32,
+        contexts: Vec<ContextElem>,
+    },
+    /// BindAck and AlterContextResp
+    BindAck {
+        max_xmit_frag: u16,
+        max_recv_frag: u16,
+        assoc_group_id: u32,
+        secondary_addr: String,
+        results: Vec<ContextResult>,
+    },
+    BindNak {
+        reject_reason: u16,
+    },
+    Request {
+        alloc_hint: u32,
+        context_id: u16,
+        opnum: u16,
+        object: Option<Guid>,
+        stub: Vec<u8>,
+    },
+    Response {
+        alloc_hint: u32,
+        context_id: u16,
+        stub: Vec<u8>,
+    },
+    Fault {
+        context_id: u16,
+        status: FaultStatus,
+    },
+    Other,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Pdu {
+    pub header: PduHeader,
+    pub body: PduBody,
+}
+
+impl PduHeader {
+    pub fn parse(raw: &[u8]) -> Result<Self, Error> {
+        let mut r = Reader::new(raw, 0);
+        let vers = __!(r.u8());
+        let vers_minor = __!(r.u8());
+        if vers != 5 || vers_minor > 1 {
+            return Err(Error::UnsupportedVersion(vers, vers_minor));
+        }
+        let ptype = __!(r.u8());
+        let ptype = PduType::from_u8(ptype).ok_or(Error::UnknownPduType(ptype))?;
+        let flags = PfcFlags::from_bits_retain(__!(r.u8()));
+        let drep: [u8; 4] = __!(r.array());
+        // big endian and non ASCII/IEEE peers are a thing of the past, and not from SMB pipes
+        if drep[0] & 0xF0 != 0x10 || drep[1] != 0 {
+            return Err(Error::UnsupportedDataRepresentation);
+        }
+        let frag_length = __!(r.u16());
+        let auth_length = __!(r.u16());
+        let call_id = __!(r.u32());
+        if (frag_length as usize) < COMMON_HEADER_LEN {
+            return Err(Error::InvalidFragLength);
+        }
+
+        Ok(Self {
+            ptype,
+            flags,
+            frag_length,
+            auth_length,
+            call_id,
+        })
+    }
+}
+
+impl Pdu {
+    /// Parses a whole fragment, `raw` has to be exactly `frag_length` bytes long.
+    pub fn parse(raw: &[u8]) -> Result<Self, Error> {
+        let header = PduHeader::parse(raw)?;
+        if raw.len() != header.frag_length as usize {
+            return Err(Error::InvalidFragLength);
+        }
+
+        // auth verifier is at the end, preceded by an 8 byte sec_trailer
+        let body_end = match header.auth_length {
+            0 => raw.len(),
+            len => raw
+                .len()
+                .checked_sub(len as usize + 8)
+                .ok_or(Error::InvalidFragLength)?,
+        };
+        let body = raw
+            .get(COMMON_HEADER_LEN..body_end)
+            .ok_or(Error::InvalidFragLength)?;
+        let mut r = Reader::new(body, COMMON_HEADER_LEN);
+
+        let body = match header.ptype {
+            PduType::Bind | PduType::AlterContext => parse_bind(&mut r)?,
+            PduType::BindAck | PduType::AlterContextResp => parse_bind_ack(&mut r)?,
+            PduType::BindNak => PduBody::BindNak {
+                reject_reason: __!(r.u16()),
+            },
+            PduType::Request => {
+                let alloc_hint = __!(r.u32());
+                let context_id = __!(r.u16());
+                let opnum = __!(r.u16());
+                let object = if header.flags.contains(PfcFlags::ObjectUuid) {
+                    Some(__!(r.guid()))
+                } else {
+                    None
+                };
+                PduBody::Request {
+                    alloc_hint,
+                    context_id,
+                    opnum,
+                    object,
+                    stub: strip_auth_pad(r.rest(), header.auth_length, raw).to_vec(),
+                }
+            }
+            PduType::Response => {
+                let alloc_hint = __!(r.u32());
+                let context_id = __!(r.u16());
+                let _cancel_count = __!(r.u8());
+                let _reserved = __!(r.u8());
+                PduBody::Response {
+                    alloc_hint,
+                    context_id,
+                    stub: strip_auth_pad(r.rest(), header.auth_length, raw).to_vec(),
+                }
+            }
+            PduType::Fault => {
+                let _alloc_hint = __!(r.u32());
+                let context_id = __!(r.u16());
+                let _cancel_count = __!(r.u8());
+                let _reserved = __!(r.u8());
+                PduBody::Fault {
+                    context_id,
+                    status: FaultStatus(__!(r.u32())),
+                }
+            }
+            _ => PduBody::Other,
+        };
+
+        Ok(Self { header, body })
+    }
+}
+
+/// The sec_trailer announces how many padding bytes were appended to the stub to align it.
+fn strip_auth_pad<'a>(stub: &'a [u8], auth_length: u16, raw: &[u8]) -> &'a [u8] {
+    if auth_length == 0 {
+        return stub;
+    }
+    let pad = raw
+        .len()
+        .checked_sub(auth_length as usize + 6)
+        .and_then(|i| raw.get(i))
+        .copied()
+        .unwrap_or(0);
+    &stub[..stub.len().saturating_sub(pad.into())]
+}
+
+fn parse_syntax_id(r: &mut Reader) -> Result<SyntaxId, Error> {
+    Ok(SyntaxId {
+        uuid: __!(r.guid()),
+        version: __!(r.u16()),
+        version_minor: __!(r.u16()),
+    })
+}
+
+fn parse_bind(r: &mut Reader) -> Result<PduBody, Error> {
+    let max_xmit_frag = __!(r.u16());
+    let max_recv_frag = __!(r.u16());
+    let assoc_group_id = __!(r.u32());
+    let n = __!(r.u8());
+    __!(r.skip(3));
+
+    let mut contexts = vec![];
+    for _ in 0..n {
+        let context_id = __!(r.u16());
+        let n_transfer = __!(r.u8());
+        __!(r.skip(1));
+        let abstract_syntax = parse_syntax_id(r)?;
+        let transfer_syntaxes = (0..n_transfer).map(|_| parse_syntax_id(r)).try_collect()?;
+        contexts.push(ContextElem {
+            context_id,
+            abstract_syntax,
+            transfer_syntaxes,
+        });
+    }
+
+    Ok(PduBody::Bind {
+        max_xmit_frag,
+        max_recv_frag,
+        assoc_group_id,
+        contexts,
+    })
+}
+
+fn parse_bind_ack(r: &mut Reader) -> Result<PduBody, Error> {
+    let max_xmit_frag = __!(r.u16());
+    let max_recv_frag = __!(r.u16());
+    let assoc_group_id = __!(r.u32());
+    let addr_len = __!(r.u16());
+    let addr = __!(r.bytes(addr_len.into()));
+    __!(r.align(4));
+    let n = __!(r.u8());
+    __!(r.skip(3));
+
+    let results = (0..n)
+        .map(|_| {
+            Ok(ContextResult {
+                result: __!(r.u16()),
+                reason: __!(r.u16()),
+                transfer_syntax: parse_syntax_id(r)?,
+            })
+        })
+        .try_collect()?;
+
+    Ok(PduBody::BindAck {
+        max_xmit_frag,
+        max_recv_frag,
+        assoc_group_id,
+        secondary_addr: String::from_utf8_lossy(addr)
+            .trim_end_matches('\0')
+            .to_owned(),
+        results,
+    })
+}
+
+/// Decoded stub of a call on one of the interfaces we understand.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Stub {
+    SrvsvcRequest(srvsvc::Request),
+    SrvsvcResponse(srvsvc::Response),
+    WkssvcRequest(wkssvc::Request),
+    WkssvcResponse(wkssvc::Response),
+    LsarpcRequest(lsarpc::Request),
+    LsarpcResponse(lsarpc::Response),
+}
+
+/// Decodes a (reassembled) stub for `iface`, `Ok(None)` if the interface/opnum has no decoder.
+pub fn decode_stub(
+    iface: &Guid,
+    opnum: u16,
+    response: bool,
+    stub: &[u8],
+) -> Result<Option<Stub>, ndr::Error> {
+    Ok(match (*iface, response) {
+        (interfaces::SRVSVC, false) => {
+            srvsvc::decode_request(opnum, stub)?.map(Stub::SrvsvcRequest)
+        }
+        (interfaces::SRVSVC, true) => {
+            srvsvc::decode_response(opnum, stub)?.map(Stub::SrvsvcResponse)
+        }
+        (interfaces::WKSSVC, false) => {
+            wkssvc::decode_request(opnum, stub)?.map(Stub::WkssvcRequest)
+        }
+        (interfaces::WKSSVC, true) => {
+            wkssvc::decode_response(opnum, stub)?.map(Stub::WkssvcResponse)
+        }
+        (interfaces::LSARPC, false) => {
+            lsarpc::decode_request(opnum, stub)?.map(Stub::LsarpcRequest)
+        }
+        (interfaces::LSARPC, true) => {
+            lsarpc::decode_response(opnum, stub)?.map(Stub::LsarpcResponse)
+        }
+        _ => None,
+    })
+}
+
+// C706 Appendix E + [MS-RPCE] 3.1.1.5.5
+const FAULT_NAMES: &[(u32, &str)] = &[
+    (0x00000005, "rpc_s_access_denied"),
+    (0x000006D1, "rpc_s_procnum_out_of_range"),
+    (0x000006F7, "rpc_x_bad_stub_data"),
+    (0x00000721, "rpc_s_sec_pkg_error"),
+    (0x1C000001, "nca_s_fault_int_div_by_zero"),
+    (0x1C000002, "nca_s_fault_addr_error"),
+    (0x1C000003, "nca_s_fault_fp_div_zero"),
+    (0x1C000004, "nca_s_fault_fp_underflow"),
+    (0x1C000005, "nca_s_fault_fp_overflow"),
+    (0x1C000006, "nca_s_fault_invalid_tag"),
+    (0x1C000007, "nca_s_fault_invalid_bound"),
+    (0x1C000008, "nca_s_rpc_version_mismatch"),
+    (0x1C000009, "nca_s_unspec_reject"),
+    (0x1C00000A, "nca_s_bad_actid"),
+    (0x1C00000B, "nca_s_who_are_you_failed"),
+    (0x1C00000C, "nca_s_manager_not_entered"),
+    (0x1C00000D, "nca_s_fault_cancel"),
+    (0x1C00000E, "nca_s_fault_ill_inst"),
+    (0x1C00000F, "nca_s_fault_fp_error"),
+    (0x1C000010, "nca_s_fault_int_overflow"),
+    (0x1C000012, "nca_s_fault_unspec"),
+    (0x1C000013, "nca_s_fault_remote_comm_failure"),
+    (0x1C000014, "nca_s_fault_pipe_empty"),
+    (0x1C000015, "nca_s_fault_pipe_closed"),
+    (0x1C000016, "nca_s_fault_pipe_order"),
+    (0x1C000017, "nca_s_fault_pipe_discipline"),
+    (0x1C000018, "nca_s_fault_pipe_comm_error"),
+    (0x1C000019, "nca_s_fault_pipe_memory"),
+    (0x1C00001A, "nca_s_fault_context_mismatch"),
+    (0x1C00001B, "nca_s_fault_remote_no_memory"),
+    (0x1C00001C, "nca_s_invalid_pres_context_id"),
+    (0x1C00001D, "nca_s_unsupported_authn_level"),
+    (0x1C00001F, "nca_s_invalid_checksum"),
+    (0x1C000020, "nca_s_invalid_crc"),
+    (0x1C010001, "nca_s_comm_failure"),
+    (0x1C010002, "nca_s_op_rng_error"),
+    (0x1C010003, "nca_s_unk_if"),
+    (0x1C010006, "nca_s_wrong_boot_time"),
+    (0x1C010009, "nca_s_you_crashed"),
+    (0x1C01000B, "nca_s_proto_error"),
+    (0x1C010013, "nca_s_out_args_too_big"),
+    (0x1C010014, "nca_s_server_too_busy"),
+    (0x1C010017, "nca_s_unsupported_type"),
+];
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub struct FaultStatus(pub u32);
+
+impl FaultStatus {
+    pub fn name(&self) -> Option<&'static str> {
+        FAULT_NAMES
+            .iter()
+            .find(|(c, _)| *c == self.0)
+            .map(|(_, n)| *n)
+    }
+}
+
+impl Debug for FaultStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{name}"),
+            None => write!(f, "0x{:08X}", self.0),
+        }
+    }
+}
+
+// [MS-ERREF] 2.2 Win32 Error Codes, as returned by srvsvc/wkssvc
+const WERROR_NAMES: &[(u32, &str)] = &[
+    (0, "WERR_OK"),
+    (5, "WERR_ACCESS_DENIED"),
+    (8, "WERR_NOT_ENOUGH_MEMORY"),
+    (50, "WERR_NOT_SUPPORTED"),
+    (53, "WERR_BAD_NETPATH"),
+    (87, "WERR_INVALID_PARAMETER"),
+    (123, "WERR_INVALID_NAME"),
+    (124, "WERR_INVALID_LEVEL"),
+    (234, "WERR_MORE_DATA"),
+    (1722, "WERR_RPC_S_SERVER_UNAVAILABLE"),
+    (2123, "NERR_BufTooSmall"),
+    (2310, "NERR_NetNameNotFound"),
+];
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub struct WError(pub u32);
+
+impl Debug for WError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match WERROR_NAMES.iter().find(|(c, _)| *c == self.0) {
+            Some((_, name)) => write!(f, "{name}"),
+            None => write!(f, "0x{:08X}", self.0),
+        }
+    }
+}
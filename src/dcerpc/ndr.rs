@@ -0,0 +1,160 @@
+//! Just enough NDR (C706 chapter 14) to walk the stubs of the calls we decode. Alignment is
+//! relative to the start of the stub, which is always 8 byte aligned in the PDU.
+use std::fmt::{Debug, Display};
+
+use crate::smb::{reader::Reader, types::utf16le};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Error {
+    ExpectedByte,
+    UnsupportedLevel(u32),
+    TooManyElements(u32),
+}
+
+macro_rules! __ {
+    ($exp:expr) => {
+        match $exp {
+            Some(val) => val,
+            None => return Err($crate::dcerpc::ndr::Error::ExpectedByte),
+        }
+    };
+}
+pub(crate) use __;
+
+/// Upper bound for conformant array sizes, so a garbage count doesn't make us allocate the world.
+const MAX_ELEMENTS: u32 = 0x10000;
+
+pub struct Ndr<'a>(pub Reader<'a>);
+
+impl<'a> Ndr<'a> {
+    pub fn new(stub: &'a [u8]) -> Self {
+        Self(Reader::new(stub, 0))
+    }
+
+    pub fn u8(&mut self) -> Result<u8, Error> {
+        Ok(__!(self.0.u8()))
+    }
+
+    pub fn u16(&mut self) -> Result<u16, Error> {
+        __!(self.0.align(2));
+        Ok(__!(self.0.u16()))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, Error> {
+        __!(self.0.align(4));
+        Ok(__!(self.0.u32()))
+    }
+
+    pub fn i32(&mut self) -> Result<i32, Error> {
+        __!(self.0.align(4));
+        Ok(__!(self.0.i32()))
+    }
+
+    /// Referent id of a unique/full pointer, `false` for NULL.
+    pub fn ptr(&mut self) -> Result<bool, Error> {
+        Ok(self.u32()? != 0)
+    }
+
+    /// Conformance (max count) of a conformant array.
+    pub fn count(&mut self) -> Result<u32, Error> {
+        let count = self.u32()?;
+        if count > MAX_ELEMENTS {
+            return Err(Error::TooManyElements(count));
+        }
+        Ok(count)
+    }
+
+    /// Conformant varying `wchar_t` string (`[string] wchar_t*` pointee).
+    pub fn wstring(&mut self) -> Result<String, Error> {
+        let _max_count = self.u32()?;
+        let _offset = self.u32()?;
+        let actual = self.count()?;
+        let raw = __!(self.0.bytes(actual as usize * 2));
+        Ok(utf16le(raw).trim_end_matches('\0').to_owned())
+    }
+
+    /// Deferred string of a `[string] wchar_t*`, only present if its pointer wasn't NULL.
+    pub fn deferred_wstring(&mut self, present: bool) -> Result<Option<String>, Error> {
+        present.then(|| self.wstring()).transpose()
+    }
+
+    /// Inline part of an RPC_UNICODE_STRING, returns whether the buffer pointer is not NULL.
+    pub fn unicode_string(&mut self) -> Result<bool, Error> {
+        // the struct is 4 byte aligned because of the pointer, not 2 as its first field
+        __!(self.0.align(4));
+        let _length = self.u16()?;
+        let _max_length = self.u16()?;
+        self.ptr()
+    }
+
+    /// Deferred `[size_is(MaximumLength/2), length_is(Length/2)]` buffer of an
+    /// RPC_UNICODE_STRING, which is not NUL terminated.
+    pub fn unicode_string_buffer(&mut self, present: bool) -> Result<Option<String>, Error> {
+        present.then(|| self.wstring()).transpose()
+    }
+
+    /// Conformant RPC_SID, preceded by its SubAuthorityCount conformance.
+    pub fn sid(&mut self) -> Result<Sid, Error> {
+        let _count = self.u32()?;
+        let revision = self.u8()?;
+        let sub_count = self.u8()?;
+        let authority: [u8; 6] = __!(self.0.array());
+        let mut sub_authorities = vec![];
+        for _ in 0..sub_count {
+            sub_authorities.push(__!(self.0.u32()));
+        }
+        Ok(Sid {
+            revision,
+            authority,
+            sub_authorities,
+        })
+    }
+}
+
+/// [MS-DTYP] 2.4.2 SID
+#[derive(Clone, Eq, PartialEq, Hash)]
+pub struct Sid {
+    pub revision: u8,
+    pub authority: [u8; 6],
+    pub sub_authorities: Vec<u32>,
+}
+
+impl Sid {
+    /// Parses the binary SID layout used outside of NDR (security descriptors, NTLMSSP...).
+    pub fn parse(r: &mut Reader) -> Option<Self> {
+        let revision = r.u8()?;
+        let sub_count = r.u8()?;
+        let authority = r.array()?;
+        let sub_authorities = (0..sub_count).map(|_| r.u32()).try_collect()?;
+        Some(Self {
+            revision,
+            authority,
+            sub_authorities,
+        })
+    }
+
+    /// Same SID with `rid` appended, how LSA lookups express account SIDs from a domain one.
+    pub fn with_rid(&self, rid: u32) -> Self {
+        let mut sid = self.clone();
+        sid.sub_authorities.push(rid);
+        sid
+    }
+}
+
+impl Display for Sid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut auth = [0u8; 8];
+        auth[2..].copy_from_slice(&self.authority);
+        write!(f, "S-{}-{}", self.revision, u64::from_be_bytes(auth))?;
+        for sub in &self.sub_authorities {
+            write!(f, "-{sub}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for Sid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+}
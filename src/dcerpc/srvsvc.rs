@@ -0,0 +1,240 @@
+//! [MS-SRVS] Server Service Remote Protocol
+use super::{ndr::Error, ndr::Ndr, WError};
+
+pub const NETR_SHARE_ENUM: u16 = 15;
+pub const NETR_SHARE_GET_INFO: u16 = 16;
+pub const NETR_SERVER_GET_INFO: u16 = 21;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Request {
+    NetrShareEnum {
+        server: Option<String>,
+        level: u32,
+    },
+    NetrShareGetInfo {
+        server: Option<String>,
+        share: String,
+        level: u32,
+    },
+    NetrServerGetInfo {
+        server: Option<String>,
+        level: u32,
+    },
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Response {
+    NetrShareEnum {
+        shares: Vec<ShareInfo>,
+        total_entries: u32,
+        result: WError,
+    },
+    NetrShareGetInfo {
+        share: Option<ShareInfo>,
+        result: WError,
+    },
+    NetrServerGetInfo {
+        server: Option<ServerInfo>,
+        result: WError,
+    },
+}
+
+/// SHARE_INFO_0/1/2/501 flattened, fields missing from the level are `None`.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct ShareInfo {
+    pub name: Option<String>,
+    pub share_type: Option<ShareType>,
+    pub remark: Option<String>,
+    pub path: Option<String>,
+    pub current_uses: Option<u32>,
+    pub max_uses: Option<u32>,
+}
+
+/// SERVER_INFO_100/101
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct ServerInfo {
+    pub platform_id: u32,
+    pub name: Option<String>,
+    pub version: Option<(u32, u32)>,
+    pub server_type: Option<u32>,
+    pub comment: Option<String>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub struct ShareType(pub u32);
+
+impl std::fmt::Debug for ShareType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.0 & 0x0FFFFFFF {
+            0 => "DISKTREE",
+            1 => "PRINTQ",
+            2 => "DEVICE",
+            3 => "IPC",
+            4 => "CLUSTER_FS",
+            _ => "UNKNOWN",
+        };
+        write!(f, "{kind}")?;
+        if self.0 & 0x80000000 != 0 {
+            write!(f, "|SPECIAL")?;
+        }
+        if self.0 & 0x40000000 != 0 {
+            write!(f, "|TEMPORARY")?;
+        }
+        Ok(())
+    }
+}
+
+pub fn decode_request(opnum: u16, stub: &[u8]) -> Result<Option<Request>, Error> {
+    let mut ndr = Ndr::new(stub);
+    Ok(Some(match opnum {
+        NETR_SHARE_ENUM => {
+            let server = server_name(&mut ndr)?;
+            // [in, out] LPSHARE_ENUM_STRUCT InfoStruct, only its level matters here
+            let level = ndr.u32()?;
+            Request::NetrShareEnum { server, level }
+        }
+        NETR_SHARE_GET_INFO => Request::NetrShareGetInfo {
+            server: server_name(&mut ndr)?,
+            share: ndr.wstring()?,
+            level: ndr.u32()?,
+        },
+        NETR_SERVER_GET_INFO => Request::NetrServerGetInfo {
+            server: server_name(&mut ndr)?,
+            level: ndr.u32()?,
+        },
+        _ => return Ok(None),
+    }))
+}
+
+pub fn decode_response(opnum: u16, stub: &[u8]) -> Result<Option<Response>, Error> {
+    let mut ndr = Ndr::new(stub);
+    Ok(Some(match opnum {
+        NETR_SHARE_ENUM => {
+            let level = ndr.u32()?;
+            let _switch = ndr.u32()?;
+            let mut shares = vec![];
+            if ndr.ptr()? {
+                let _entries_read = ndr.u32()?;
+                if ndr.ptr()? {
+                    let count = ndr.count()?;
+                    shares = share_info_array(&mut ndr, level, count)?;
+                }
+            }
+            let total_entries = ndr.u32()?;
+            if ndr.ptr()? {
+                let _resume_handle = ndr.u32()?;
+            }
+            Response::NetrShareEnum {
+                shares,
+                total_entries,
+                result: WError(ndr.u32()?),
+            }
+        }
+        NETR_SHARE_GET_INFO => {
+            let level = ndr.u32()?;
+            let share = if ndr.ptr()? {
+                share_info_array(&mut ndr, level, 1)?.pop()
+            } else {
+                None
+            };
+            Response::NetrShareGetInfo {
+                share,
+                result: WError(ndr.u32()?),
+            }
+        }
+        NETR_SERVER_GET_INFO => {
+            let level = ndr.u32()?;
+            let server = if ndr.ptr()? {
+                Some(server_info(&mut ndr, level)?)
+            } else {
+                None
+            };
+            Response::NetrServerGetInfo {
+                server,
+                result: WError(ndr.u32()?),
+            }
+        }
+        _ => return Ok(None),
+    }))
+}
+
+/// `[in, string, unique] SRVSVC_HANDLE ServerName`
+pub(super) fn server_name(ndr: &mut Ndr) -> Result<Option<String>, Error> {
+    let present = ndr.ptr()?;
+    ndr.deferred_wstring(present)
+}
+
+/// Array of SHARE_INFO_x structs, inline parts first and then every deferred string in order.
+fn share_info_array(ndr: &mut Ndr, level: u32, count: u32) -> Result<Vec<ShareInfo>, Error> {
+    // which fields were non-NULL pointers: name, remark, path, passwd
+    let mut ptrs = vec![];
+    let mut shares = vec![];
+    for _ in 0..count {
+        let mut share = ShareInfo::default();
+        let mut p = [false; 4];
+        match level {
+            0 => p[0] = ndr.ptr()?,
+            1 | 501 => {
+                p[0] = ndr.ptr()?;
+                share.share_type = Some(ShareType(ndr.u32()?));
+                p[1] = ndr.ptr()?;
+                if level == 501 {
+                    let _flags = ndr.u32()?;
+                }
+            }
+            2 => {
+                p[0] = ndr.ptr()?;
+                share.share_type = Some(ShareType(ndr.u32()?));
+                p[1] = ndr.ptr()?;
+                let _permissions = ndr.u32()?;
+                share.max_uses = Some(ndr.u32()?);
+                share.current_uses = Some(ndr.u32()?);
+                p[2] = ndr.ptr()?;
+                p[3] = ndr.ptr()?;
+            }
+            level => return Err(Error::UnsupportedLevel(level)),
+        }
+        ptrs.push(p);
+        shares.push(share);
+    }
+
+    for (share, p) in shares.iter_mut().zip(ptrs) {
+        share.name = ndr.deferred_wstring(p[0])?;
+        share.remark = ndr.deferred_wstring(p[1])?;
+        share.path = ndr.deferred_wstring(p[2])?;
+        let _passwd = ndr.deferred_wstring(p[3])?;
+    }
+    Ok(shares)
+}
+
+fn server_info(ndr: &mut Ndr, level: u32) -> Result<ServerInfo, Error> {
+    let mut info = ServerInfo {
+        platform_id: ndr.u32()?,
+        ..Default::default()
+    };
+    let name = ndr.ptr()?;
+    let mut comment = false;
+    match level {
+        100 => {}
+        101 | 102 => {
+            info.version = Some((ndr.u32()?, ndr.u32()?));
+            info.server_type = Some(ndr.u32()?);
+            comment = ndr.ptr()?;
+            if level == 102 {
+                // users, disc, hidden, announce, anndelta, licenses
+                for _ in 0..6 {
+                    ndr.u32()?;
+                }
+                let userpath = ndr.ptr()?;
+                info.name = ndr.deferred_wstring(name)?;
+                info.comment = ndr.deferred_wstring(comment)?;
+                let _userpath = ndr.deferred_wstring(userpath)?;
+                return Ok(info);
+            }
+        }
+        level => return Err(Error::UnsupportedLevel(level)),
+    }
+    info.name = ndr.deferred_wstring(name)?;
+    info.comment = ndr.deferred_wstring(comment)?;
+    Ok(info)
+}
@@ -0,0 +1,79 @@
+//! [MS-WKST] Workstation Service Remote Protocol
+use super::{ndr::Error, ndr::Ndr, srvsvc::server_name, WError};
+
+pub const NETR_WKSTA_GET_INFO: u16 = 0;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Request {
+    NetrWkstaGetInfo { server: Option<String>, level: u32 },
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Response {
+    NetrWkstaGetInfo {
+        info: Option<WkstaInfo>,
+        result: WError,
+    },
+}
+
+/// WKSTA_INFO_100/101/102
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct WkstaInfo {
+    pub platform_id: u32,
+    pub computer_name: Option<String>,
+    pub lan_group: Option<String>,
+    pub version: (u32, u32),
+    pub lan_root: Option<String>,
+    pub logged_on_users: Option<u32>,
+}
+
+pub fn decode_request(opnum: u16, stub: &[u8]) -> Result<Option<Request>, Error> {
+    let mut ndr = Ndr::new(stub);
+    Ok(Some(match opnum {
+        NETR_WKSTA_GET_INFO => Request::NetrWkstaGetInfo {
+            server: server_name(&mut ndr)?,
+            level: ndr.u32()?,
+        },
+        _ => return Ok(None),
+    }))
+}
+
+pub fn decode_response(opnum: u16, stub: &[u8]) -> Result<Option<Response>, Error> {
+    let mut ndr = Ndr::new(stub);
+    Ok(Some(match opnum {
+        NETR_WKSTA_GET_INFO => {
+            let level = ndr.u32()?;
+            let info = if ndr.ptr()? {
+                Some(wksta_info(&mut ndr, level)?)
+            } else {
+                None
+            };
+            Response::NetrWkstaGetInfo {
+                info,
+                result: WError(ndr.u32()?),
+            }
+        }
+        _ => return Ok(None),
+    }))
+}
+
+fn wksta_info(ndr: &mut Ndr, level: u32) -> Result<WkstaInfo, Error> {
+    if !matches!(level, 100..=102) {
+        return Err(Error::UnsupportedLevel(level));
+    }
+    let mut info = WkstaInfo {
+        platform_id: ndr.u32()?,
+        ..Default::default()
+    };
+    let computer_name = ndr.ptr()?;
+    let lan_group = ndr.ptr()?;
+    info.version = (ndr.u32()?, ndr.u32()?);
+    let lan_root = if level >= 101 { ndr.ptr()? } else { false };
+    if level == 102 {
+        info.logged_on_users = Some(ndr.u32()?);
+    }
+    info.computer_name = ndr.deferred_wstring(computer_name)?;
+    info.lan_group = ndr.deferred_wstring(lan_group)?;
+    info.lan_root = ndr.deferred_wstring(lan_root)?;
+    Ok(info)
+}
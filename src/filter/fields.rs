@@ -0,0 +1,546 @@
+//! Registry of the fields an expression can test, named after Wireshark's where there is one.
+use std::{fmt::Display, net::IpAddr};
+
+use crate::{
+    smb::{body::Body, flags::Flags, opcodes::Opcodes, status::NtStatus, types::FileId, SMBHeader},
+    tcp::flags::{Flag, FlagMask},
+    tcpdump::header::Header,
+};
+
+/// What a filter is evaluated against: one SMB2 message and the TCP segment carrying it.
+pub struct Message<'a> {
+    pub frame: usize,
+    pub tcp: &'a Header,
+    pub header: &'a SMBHeader,
+    pub body: &'a Body,
+    /// Name of the request of a CREATE response, which the response doesn't repeat, from the
+    /// analysis (`None` when the request isn't in the capture).
+    pub create_name: Option<&'a str>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Kind {
+    Number,
+    Text,
+    Address,
+    /// Flags, `tcp.flags.syn` alone tests the flag is set.
+    Bool,
+    Opcode,
+    Status,
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Number => write!(f, "a number"),
+            Self::Text => write!(f, "a string"),
+            Self::Address => write!(f, "an IP address"),
+            Self::Bool => write!(f, "true or false"),
+            Self::Opcode => write!(f, "a command"),
+            Self::Status => write!(f, "a status"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Value {
+    Number(u64),
+    Text(String),
+    Address(IpAddr),
+    Bool(bool),
+    Opcode(Opcodes),
+    Status(NtStatus),
+}
+
+impl Value {
+    /// Reads a value of the expression as `kind`, `None` if it isn't one.
+    pub fn parse(kind: Kind, raw: &str) -> Option<Self> {
+        let number = |raw: &str| match raw.strip_prefix("0x").or(raw.strip_prefix("0X")) {
+            Some(hex) => u64::from_str_radix(hex, 16).ok(),
+            None => raw.parse().ok(),
+        };
+        Some(match kind {
+            Kind::Number => Self::Number(number(raw)?),
+            Kind::Text => Self::Text(raw.to_owned()),
+            Kind::Address => Self::Address(raw.parse().ok()?),
+            Kind::Bool => Self::Bool(match raw {
+                "true" | "1" => true,
+                "false" | "0" => false,
+                _ => return None,
+            }),
+            Kind::Opcode => Self::Opcode(Opcodes::from_name(raw)?),
+            Kind::Status => Self::Status(NtStatus::from_name(raw)?),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Field {
+    pub name: &'static str,
+    pub kind: Kind,
+    pub help: &'static str,
+    /// Values of the field in a message, none when it doesn't have it (`smb2.path` of a READ) and
+    /// several for the fields that stand for either side (`tcp.port`) or for lists.
+    pub values: fn(&Message) -> Vec<Value>,
+}
+
+fn number(value: impl Into<u64>) -> Vec<Value> {
+    vec![Value::Number(value.into())]
+}
+
+fn flag(tcp: &Header, flag: Flag) -> Vec<Value> {
+    vec![Value::Bool(tcp.flags.is_set(flag))]
+}
+
+fn smb_flag(header: &SMBHeader, flag: Flags) -> Vec<Value> {
+    vec![Value::Bool(header.flags.contains(flag))]
+}
+
+fn file_id(body: &Body) -> Option<FileId> {
+    Some(match body {
+        Body::CreateResponse(res) => res.file_id,
+        Body::CloseRequest(req) => req.file_id,
+        Body::ReadRequest(req) => req.file_id,
+        Body::WriteRequest(req) => req.file_id,
+        Body::IoctlRequest(req) => req.file_id,
+        Body::IoctlResponse(res) => res.file_id,
+        Body::LockRequest(req) => req.file_id,
+        Body::OplockBreak(brk) => brk.file_id,
+        Body::NotifyRequest(req) => req.file_id,
+        Body::SetInfoRequest(req) => req.file_id,
+        _ => return None,
+    })
+}
+
+pub const FIELDS: &[Field] = &[
+    Field {
+        name: "frame.number",
+        kind: Kind::Number,
+        help: "frame number, as printed",
+        values: |m| number(m.frame as u64),
+    },
+    Field {
+        name: "ip.src",
+        kind: Kind::Address,
+        help: "source address",
+        values: |m| vec![Value::Address(m.tcp.src.ip())],
+    },
+    Field {
+        name: "ip.dst",
+        kind: Kind::Address,
+        help: "destination address",
+        values: |m| vec![Value::Address(m.tcp.dst.ip())],
+    },
+    Field {
+        name: "ip.addr",
+        kind: Kind::Address,
+        help: "source or destination address",
+        values: |m| {
+            vec![
+                Value::Address(m.tcp.src.ip()),
+                Value::Address(m.tcp.dst.ip()),
+            ]
+        },
+    },
+    Field {
+        name: "tcp.srcport",
+        kind: Kind::Number,
+        help: "source port",
+        values: |m| number(m.tcp.src.port()),
+    },
+    Field {
+        name: "tcp.dstport",
+        kind: Kind::Number,
+        help: "destination port",
+        values: |m| number(m.tcp.dst.port()),
+    },
+    Field {
+        name: "tcp.port",
+        kind: Kind::Number,
+        help: "source or destination port",
+        values: |m| {
+            vec![
+                Value::Number(m.tcp.src.port().into()),
+                Value::Number(m.tcp.dst.port().into()),
+            ]
+        },
+    },
+    Field {
+        name: "tcp.seq",
+        kind: Kind::Number,
+        help: "sequence number",
+        values: |m| m.tcp.seq.map(number).unwrap_or_default(),
+    },
+    Field {
+        name: "tcp.ack",
+        kind: Kind::Number,
+        help: "acknowledgment number",
+        values: |m| m.tcp.ack.map(number).unwrap_or_default(),
+    },
+    Field {
+        name: "tcp.window_size",
+        kind: Kind::Number,
+        help: "window",
+        values: |m| number(m.tcp.win),
+    },
+    Field {
+        name: "tcp.len",
+        kind: Kind::Number,
+        help: "payload length",
+        values: |m| number(m.tcp.length as u64),
+    },
+    Field {
+        name: "tcp.flags",
+        kind: Kind::Number,
+        help: "flags byte",
+        values: |m| number(m.tcp.flags.as_flag_bits()),
+    },
+    Field {
+        name: "tcp.flags.fin",
+        kind: Kind::Bool,
+        help: "FIN flag",
+        values: |m| flag(m.tcp, Flag::FIN),
+    },
+    Field {
+        name: "tcp.flags.syn",
+        kind: Kind::Bool,
+        help: "SYN flag",
+        values: |m| flag(m.tcp, Flag::SYN),
+    },
+    Field {
+        name: "tcp.flags.reset",
+        kind: Kind::Bool,
+        help: "RST flag",
+        values: |m| flag(m.tcp, Flag::RST),
+    },
+    Field {
+        name: "tcp.flags.push",
+        kind: Kind::Bool,
+        help: "PSH flag",
+        values: |m| flag(m.tcp, Flag::PSH),
+    },
+    Field {
+        name: "tcp.flags.ack",
+        kind: Kind::Bool,
+        help: "ACK flag",
+        values: |m| flag(m.tcp, Flag::ACK),
+    },
+    Field {
+        name: "tcp.flags.urg",
+        kind: Kind::Bool,
+        help: "URG flag",
+        values: |m| flag(m.tcp, Flag::URG),
+    },
+    Field {
+        name: "smb2.cmd",
+        kind: Kind::Opcode,
+        help: "command (create, QUERY_DIRECTORY, 0x0e...)",
+        values: |m| vec![Value::Opcode(m.header.opcode)],
+    },
+    Field {
+        name: "smb2.status",
+        kind: Kind::Status,
+        help: "status of a response (STATUS_SUCCESS, ACCESS_DENIED, 0xC0000022...)",
+        values: |m| {
+            if m.header.is_response() {
+                vec![Value::Status(m.header.status())]
+            } else {
+                vec![]
+            }
+        },
+    },
+    Field {
+        name: "smb2.msg_id",
+        kind: Kind::Number,
+        help: "MessageId",
+        values: |m| number(m.header.cmd_seq),
+    },
+    Field {
+        name: "smb2.sesid",
+        kind: Kind::Number,
+        help: "SessionId",
+        values: |m| number(m.header.uid),
+    },
+    Field {
+        name: "smb2.tid",
+        kind: Kind::Number,
+        help: "TreeId, absent from async messages",
+        values: |m| m.header.tree_id().map(number).unwrap_or_default(),
+    },
+    Field {
+        name: "smb2.pid",
+        kind: Kind::Number,
+        help: "ProcessId",
+        values: |m| number(m.header.pid),
+    },
+    Field {
+        name: "smb2.credit.charge",
+        kind: Kind::Number,
+        help: "CreditCharge",
+        values: |m| number(m.header.cred_charge),
+    },
+    Field {
+        name: "smb2.credits",
+        kind: Kind::Number,
+        help: "CreditRequest/CreditResponse",
+        values: |m| number(m.header.cred_req_res),
+    },
+    Field {
+        name: "smb2.flags",
+        kind: Kind::Number,
+        help: "header flags",
+        values: |m| number(m.header.flags.bits()),
+    },
+    Field {
+        name: "smb2.flags.response",
+        kind: Kind::Bool,
+        help: "the message is a response",
+        values: |m| smb_flag(m.header, Flags::FlagsServer2Redir),
+    },
+    Field {
+        name: "smb2.flags.async",
+        kind: Kind::Bool,
+        help: "async header",
+        values: |m| smb_flag(m.header, Flags::FlagsAsyncCommand),
+    },
+    Field {
+        name: "smb2.flags.chained",
+        kind: Kind::Bool,
+        help: "related operation of a compound",
+        values: |m| smb_flag(m.header, Flags::FlagsRelatedOps),
+    },
+    Field {
+        name: "smb2.flags.signature",
+        kind: Kind::Bool,
+        help: "the message is signed",
+        values: |m| smb_flag(m.header, Flags::FlagsSigned),
+    },
+    Field {
+        name: "smb2.next_command",
+        kind: Kind::Number,
+        help: "offset of the next message of a compound",
+        values: |m| number(m.header.chain_offset),
+    },
+    Field {
+        name: "smb2.path",
+        kind: Kind::Text,
+        help: "name of a CREATE request and of its response, or share path of a TREE_CONNECT \
+               request",
+        values: |m| match m.body {
+            Body::CreateRequest(req) => vec![Value::Text(req.name.clone())],
+            Body::TreeConnectRequest(req) => vec![Value::Text(req.path.clone())],
+            _ => m
+                .create_name
+                .map(|name| vec![Value::Text(name.to_owned())])
+                .unwrap_or_default(),
+        },
+    },
+    Field {
+        name: "smb2.fid",
+        kind: Kind::Text,
+        help: "FileId, as printed (persistent:volatile in hex)",
+        values: |m| {
+            file_id(m.body)
+                .map(|id| vec![Value::Text(format!("{id:?}"))])
+                .unwrap_or_default()
+        },
+    },
+    Field {
+        name: "smb2.dialect",
+        kind: Kind::Number,
+        help: "dialects offered by a NEGOTIATE request or chosen by its response",
+        values: |m| match m.body {
+            Body::NegotiateRequest(req) => req
+                .dialects
+                .iter()
+                .map(|d| Value::Number(d.0.into()))
+                .collect(),
+            Body::NegotiateResponse(res) => number(res.dialect.0),
+            _ => vec![],
+        },
+    },
+    Field {
+        name: "smb2.share_type",
+        kind: Kind::Number,
+        help: "share type of a TREE_CONNECT response (1 disk, 2 pipe, 3 print)",
+        values: |m| match m.body {
+            Body::TreeConnectResponse(res) => number(res.share_type),
+            _ => vec![],
+        },
+    },
+    Field {
+        name: "smb2.access_mask",
+        kind: Kind::Number,
+        help: "desired access of a CREATE request",
+        values: |m| match m.body {
+            Body::CreateRequest(req) => number(req.desired_access),
+            _ => vec![],
+        },
+    },
+    Field {
+        name: "smb2.share_access",
+        kind: Kind::Number,
+        help: "share access of a CREATE request",
+        values: |m| match m.body {
+            Body::CreateRequest(req) => number(req.share_access),
+            _ => vec![],
+        },
+    },
+    Field {
+        name: "smb2.create.disposition",
+        kind: Kind::Number,
+        help: "create disposition of a CREATE request",
+        values: |m| match m.body {
+            Body::CreateRequest(req) => number(req.disposition),
+            _ => vec![],
+        },
+    },
+    Field {
+        name: "smb2.create_options",
+        kind: Kind::Number,
+        help: "create options of a CREATE request",
+        values: |m| match m.body {
+            Body::CreateRequest(req) => number(req.options),
+            _ => vec![],
+        },
+    },
+    Field {
+        name: "smb2.create.action",
+        kind: Kind::Number,
+        help: "create action of a CREATE response",
+        values: |m| match m.body {
+            Body::CreateResponse(res) => number(res.action),
+            _ => vec![],
+        },
+    },
+    Field {
+        name: "smb2.oplock",
+        kind: Kind::Number,
+        help: "oplock level of a CREATE or oplock break",
+        values: |m| match m.body {
+            Body::CreateRequest(req) => number(req.oplock_level),
+            Body::CreateResponse(res) => number(res.oplock_level),
+            Body::OplockBreak(brk) => number(brk.oplock_level),
+            _ => vec![],
+        },
+    },
+    Field {
+        name: "smb2.file_attributes",
+        kind: Kind::Number,
+        help: "file attributes of a CREATE or CLOSE",
+        values: |m| match m.body {
+            Body::CreateRequest(req) => number(req.file_attributes),
+            Body::CreateResponse(res) => number(res.file_attributes),
+            Body::CloseResponse(res) => number(res.file_attributes),
+            _ => vec![],
+        },
+    },
+    Field {
+        name: "smb2.end_of_file",
+        kind: Kind::Number,
+        help: "file size in a CREATE or CLOSE response",
+        values: |m| match m.body {
+            Body::CreateResponse(res) => number(res.end_of_file),
+            Body::CloseResponse(res) => number(res.end_of_file),
+            _ => vec![],
+        },
+    },
+    Field {
+        name: "smb2.offset",
+        kind: Kind::Number,
+        help: "file offset of a READ or WRITE request",
+        values: |m| match m.body {
+            Body::ReadRequest(req) => number(req.offset),
+            Body::WriteRequest(req) => number(req.offset),
+            _ => vec![],
+        },
+    },
+    Field {
+        name: "smb2.length",
+        kind: Kind::Number,
+        help: "length asked by a READ request, or data length of a READ response or WRITE request",
+        values: |m| match m.body {
+            Body::ReadRequest(req) => number(req.length),
+            Body::ReadResponse(res) => number(res.data.len() as u64),
+            Body::WriteRequest(req) => number(req.data.len() as u64),
+            Body::WriteResponse(res) => number(res.count),
+            _ => vec![],
+        },
+    },
+    Field {
+        name: "smb2.ioctl.function",
+        kind: Kind::Number,
+        help: "control code of an IOCTL",
+        values: |m| match m.body {
+            Body::IoctlRequest(req) => number(req.ctl_code),
+            Body::IoctlResponse(res) => number(res.ctl_code),
+            _ => vec![],
+        },
+    },
+    Field {
+        name: "smb2.class",
+        kind: Kind::Number,
+        help: "info class of a SET_INFO request",
+        values: |m| match m.body {
+            Body::SetInfoRequest(req) => number(req.file_info_class),
+            _ => vec![],
+        },
+    },
+    Field {
+        name: "smb2.notify.name",
+        kind: Kind::Text,
+        help: "names changed in a CHANGE_NOTIFY response",
+        values: |m| match m.body {
+            Body::NotifyResponse(res) => res
+                .entries
+                .iter()
+                .map(|e| Value::Text(e.name.clone()))
+                .collect(),
+            _ => vec![],
+        },
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Field> {
+    FIELDS.iter().find(|f| f.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_values() {
+        assert_eq!(Value::parse(Kind::Number, "0x1F"), Some(Value::Number(31)));
+        assert_eq!(Value::parse(Kind::Number, "31"), Some(Value::Number(31)));
+        assert_eq!(Value::parse(Kind::Number, "-1"), None);
+        assert_eq!(
+            Value::parse(Kind::Address, "fe80::1"),
+            Some(Value::Address("fe80::1".parse().unwrap()))
+        );
+        assert_eq!(Value::parse(Kind::Address, "srv"), None);
+        assert_eq!(Value::parse(Kind::Bool, "1"), Some(Value::Bool(true)));
+        assert_eq!(Value::parse(Kind::Bool, "yes"), None);
+        assert_eq!(
+            Value::parse(Kind::Opcode, "query_directory"),
+            Some(Value::Opcode(Opcodes::Find))
+        );
+        assert_eq!(
+            Value::parse(Kind::Status, "ACCESS_DENIED"),
+            Some(Value::Status(NtStatus(0xC0000022)))
+        );
+    }
+
+    #[test]
+    fn names_are_unique() {
+        for (i, field) in FIELDS.iter().enumerate() {
+            assert!(
+                FIELDS[..i].iter().all(|f| f.name != field.name),
+                "{} twice",
+                field.name
+            );
+            assert_eq!(find(field.name).map(|f| f.help), Some(field.help));
+        }
+        assert!(find("smb2").is_none());
+    }
+}
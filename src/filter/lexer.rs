@@ -0,0 +1,205 @@
+use std::ops::Range;
+
+use super::Error;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// Regular expression match, `~` or `matches`.
+    Matches,
+}
+
+impl Op {
+    pub fn is_ordering(&self) -> bool {
+        matches!(self, Self::Lt | Self::Le | Self::Gt | Self::Ge)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Token {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Op(Op),
+    /// Double quoted, with `\"` and `\\` escapes (other backslashes are kept, for regexes).
+    Str(String),
+    /// Field name or unquoted value: a number, an address, a command or status name...
+    Word(String),
+}
+
+/// Token with its byte range in the expression, for error messages.
+pub type Spanned = (Token, Range<usize>);
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | ':' | '-' | '/')
+}
+
+pub fn tokenize(expr: &str) -> Result<Vec<Spanned>, Error> {
+    let mut tokens = vec![];
+    let mut chars = expr.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let mut next_is = |expected: char| chars.next_if(|(_, c)| *c == expected).is_some();
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '&' if next_is('&') => Token::And,
+            '|' if next_is('|') => Token::Or,
+            '=' if next_is('=') => Token::Op(Op::Eq),
+            '!' if next_is('=') => Token::Op(Op::Ne),
+            '!' => Token::Not,
+            '<' if next_is('=') => Token::Op(Op::Le),
+            '<' => Token::Op(Op::Lt),
+            '>' if next_is('=') => Token::Op(Op::Ge),
+            '>' => Token::Op(Op::Gt),
+            '~' => Token::Op(Op::Matches),
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, c @ ('"' | '\\'))) => value.push(c),
+                            Some((_, c)) => {
+                                value.push('\\');
+                                value.push(c);
+                            }
+                            None => return Err(Error::UnterminatedString(start..expr.len())),
+                        },
+                        Some((_, c)) => value.push(c),
+                        None => return Err(Error::UnterminatedString(start..expr.len())),
+                    }
+                }
+                Token::Str(value)
+            }
+            c if is_word_char(c) => {
+                let mut word = c.to_string();
+                while let Some((_, c)) = chars.next_if(|(_, c)| is_word_char(*c)) {
+                    word.push(c);
+                }
+                match word.as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    "matches" => Token::Op(Op::Matches),
+                    "eq" => Token::Op(Op::Eq),
+                    "ne" => Token::Op(Op::Ne),
+                    _ => Token::Word(word),
+                }
+            }
+            c => return Err(Error::UnexpectedChar(start..start + c.len_utf8())),
+        };
+        let end = chars.peek().map_or(expr.len(), |(i, _)| *i);
+        tokens.push((token, start..end));
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(expr: &str) -> Vec<Token> {
+        tokenize(expr)
+            .unwrap()
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect()
+    }
+
+    #[test]
+    fn symbols_and_keywords() {
+        assert_eq!(
+            tokens("!(a==1)&&b!=2||c<3 and d<=4 or e>5 not f>=6 g~h i matches j k eq l ne m"),
+            vec![
+                Token::Not,
+                Token::LParen,
+                Token::Word("a".into()),
+                Token::Op(Op::Eq),
+                Token::Word("1".into()),
+                Token::RParen,
+                Token::And,
+                Token::Word("b".into()),
+                Token::Op(Op::Ne),
+                Token::Word("2".into()),
+                Token::Or,
+                Token::Word("c".into()),
+                Token::Op(Op::Lt),
+                Token::Word("3".into()),
+                Token::And,
+                Token::Word("d".into()),
+                Token::Op(Op::Le),
+                Token::Word("4".into()),
+                Token::Or,
+                Token::Word("e".into()),
+                Token::Op(Op::Gt),
+                Token::Word("5".into()),
+                Token::Not,
+                Token::Word("f".into()),
+                Token::Op(Op::Ge),
+                Token::Word("6".into()),
+                Token::Word("g".into()),
+                Token::Op(Op::Matches),
+                Token::Word("h".into()),
+                Token::Word("i".into()),
+                Token::Op(Op::Matches),
+                Token::Word("j".into()),
+                Token::Word("k".into()),
+                Token::Op(Op::Eq),
+                Token::Word("l".into()),
+                Token::Op(Op::Ne),
+                Token::Word("m".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn words_keep_addresses_and_names() {
+        assert_eq!(
+            tokens("ip.addr == fe80::1 && smb2.status == STATUS_ACCESS_DENIED"),
+            vec![
+                Token::Word("ip.addr".into()),
+                Token::Op(Op::Eq),
+                Token::Word("fe80::1".into()),
+                Token::And,
+                Token::Word("smb2.status".into()),
+                Token::Op(Op::Eq),
+                Token::Word("STATUS_ACCESS_DENIED".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn string_escapes() {
+        // `\"` and `\\` are unescaped, other backslashes are left for the regex
+        assert_eq!(
+            tokens(r#""a \"b\" c\\d \.docx$""#),
+            vec![Token::Str(r#"a "b" c\d \.docx$"#.into())]
+        );
+        // the usage example, `"\\.docx$"` in the shell, is the regex `\.docx$`
+        assert_eq!(tokens(r#""\\.docx$""#), vec![Token::Str(r"\.docx$".into())]);
+    }
+
+    #[test]
+    fn spans() {
+        let spanned = tokenize("a == \"é\"").unwrap();
+        let spans: Vec<_> = spanned.into_iter().map(|(_, span)| span).collect();
+        assert_eq!(spans, vec![0..1, 2..4, 5..9]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(tokenize("a # b"), Err(Error::UnexpectedChar(2..3)));
+        assert_eq!(tokenize("a & b"), Err(Error::UnexpectedChar(2..3)));
+        assert_eq!(tokenize("a = b"), Err(Error::UnexpectedChar(2..3)));
+        assert_eq!(tokenize("a ~ \"b"), Err(Error::UnterminatedString(4..6)));
+        assert_eq!(tokenize("a ~ \"b\\"), Err(Error::UnterminatedString(4..7)));
+    }
+}
@@ -0,0 +1,464 @@
+//! Display filters: boolean expressions over the decoded fields of a message, in the spirit of
+//! Wireshark's (`smb2.cmd == create && smb2.status != STATUS_SUCCESS && smb2.path ~ "\.docx$"`).
+//!
+//! A field standing for several values (`tcp.port`, `smb2.dialect`) matches when any of them
+//! does, except for `!=` which needs all of them to differ. A comparison on a field the message
+//! doesn't have is false, whatever the operator.
+pub mod fields;
+pub mod lexer;
+
+use regex::Regex;
+use std::{fmt::Display, ops::Range};
+
+use fields::{Field, Kind, Message, Value};
+use lexer::{Op, Spanned, Token};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Error {
+    UnexpectedChar(Range<usize>),
+    UnterminatedString(Range<usize>),
+    UnexpectedToken(Range<usize>),
+    /// The expression stops where an operand was expected.
+    UnexpectedEnd(Range<usize>),
+    UnclosedParen(Range<usize>),
+    UnknownField(Range<usize>),
+    InvalidValue(Range<usize>, Kind),
+    /// `<` and friends on something that isn't a number.
+    NotOrdered(Range<usize>, Kind),
+    /// `~` on something that isn't a string.
+    NotText(Range<usize>, Kind),
+    InvalidRegex(Range<usize>, String),
+}
+
+impl Error {
+    /// Bytes of the expression at fault.
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            Self::UnexpectedChar(span)
+            | Self::UnterminatedString(span)
+            | Self::UnexpectedToken(span)
+            | Self::UnexpectedEnd(span)
+            | Self::UnclosedParen(span)
+            | Self::UnknownField(span)
+            | Self::InvalidValue(span, _)
+            | Self::NotOrdered(span, _)
+            | Self::NotText(span, _)
+            | Self::InvalidRegex(span, _) => span.clone(),
+        }
+    }
+
+    /// The expression with the offending token underlined, on two lines.
+    pub fn pointer(&self, expr: &str) -> String {
+        let span = self.span();
+        let start = expr[..span.start].chars().count();
+        let len = expr[span].chars().count().max(1);
+        format!("  {expr}\n  {}{}", " ".repeat(start), "^".repeat(len))
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnexpectedChar(_) => write!(f, "unexpected character"),
+            Self::UnterminatedString(_) => write!(f, "unterminated string"),
+            Self::UnexpectedToken(_) => write!(f, "unexpected token"),
+            Self::UnexpectedEnd(_) => write!(f, "expression ends early"),
+            Self::UnclosedParen(_) => write!(f, "unclosed parenthesis"),
+            Self::UnknownField(_) => write!(f, "unknown field (see --filter-fields)"),
+            Self::InvalidValue(_, kind) => write!(f, "expected {kind}"),
+            Self::NotOrdered(_, kind) => write!(f, "{kind} can't be ordered, only == and !="),
+            Self::NotText(_, kind) => write!(f, "~ needs a string field, not {kind}"),
+            Self::InvalidRegex(_, err) => write!(f, "invalid regular expression: {err}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    /// A field alone: set for flags, present for anything else.
+    Test(&'static Field),
+    Compare(&'static Field, Op, Value),
+    Matches(&'static Field, Regex),
+}
+
+struct Parser<'a> {
+    tokens: &'a [Spanned],
+    at: usize,
+    /// Where an error about a missing token points: the end of the expression.
+    end: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Result<&Spanned, Error> {
+        let token = self
+            .tokens
+            .get(self.at)
+            .ok_or(Error::UnexpectedEnd(self.end..self.end))?;
+        self.at += 1;
+        Ok(token)
+    }
+
+    fn or(&mut self) -> Result<Expr, Error> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.at += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, Error> {
+        let mut expr = self.not()?;
+        while self.peek() == Some(&Token::And) {
+            self.at += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, Error> {
+        if self.peek() == Some(&Token::Not) {
+            self.at += 1;
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, Error> {
+        let (token, span) = self.next()?.clone();
+        let field = match token {
+            Token::LParen => {
+                let expr = self.or()?;
+                return match self.tokens.get(self.at) {
+                    Some((Token::RParen, _)) => {
+                        self.at += 1;
+                        Ok(expr)
+                    }
+                    Some((_, span)) => Err(Error::UnexpectedToken(span.clone())),
+                    None => Err(Error::UnclosedParen(span)),
+                };
+            }
+            Token::Word(name) => fields::find(&name).ok_or(Error::UnknownField(span))?,
+            _ => return Err(Error::UnexpectedToken(span)),
+        };
+
+        let op = match self.peek() {
+            Some(Token::Op(op)) => *op,
+            _ => return Ok(Expr::Test(field)),
+        };
+        let op_span = self.next()?.1.clone();
+        let (token, span) = self.next()?.clone();
+        let raw = match token {
+            Token::Word(raw) | Token::Str(raw) => raw,
+            _ => return Err(Error::UnexpectedToken(span)),
+        };
+        if op == Op::Matches {
+            if field.kind != Kind::Text {
+                return Err(Error::NotText(op_span, field.kind));
+            }
+            let regex = Regex::new(&raw).map_err(|err| {
+                // the crate's message draws its own pointer, only its last line says what's wrong
+                let err = err.to_string();
+                let reason = err.lines().last().unwrap_or_default();
+                Error::InvalidRegex(span, reason.trim_start_matches("error: ").to_owned())
+            })?;
+            return Ok(Expr::Matches(field, regex));
+        }
+        if op.is_ordering() && field.kind != Kind::Number {
+            return Err(Error::NotOrdered(op_span, field.kind));
+        }
+        let value = Value::parse(field.kind, &raw).ok_or(Error::InvalidValue(span, field.kind))?;
+        Ok(Expr::Compare(field, op, value))
+    }
+}
+
+impl Expr {
+    pub fn parse(expr: &str) -> Result<Self, Error> {
+        let tokens = lexer::tokenize(expr)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            at: 0,
+            end: expr.len(),
+        };
+        let parsed = parser.or()?;
+        match tokens.get(parser.at) {
+            Some((_, span)) => Err(Error::UnexpectedToken(span.clone())),
+            None => Ok(parsed),
+        }
+    }
+
+    pub fn matches(&self, msg: &Message) -> bool {
+        match self {
+            Self::And(a, b) => a.matches(msg) && b.matches(msg),
+            Self::Or(a, b) => a.matches(msg) || b.matches(msg),
+            Self::Not(expr) => !expr.matches(msg),
+            Self::Test(field) => {
+                let values = (field.values)(msg);
+                match field.kind {
+                    Kind::Bool => values.contains(&Value::Bool(true)),
+                    _ => !values.is_empty(),
+                }
+            }
+            Self::Compare(field, op, value) => {
+                let values = (field.values)(msg);
+                let ordered = |v: &Value| match (v, value) {
+                    (Value::Number(v), Value::Number(value)) => Some(v.cmp(value)),
+                    _ => None,
+                };
+                match op {
+                    Op::Eq => values.contains(value),
+                    Op::Ne => !values.is_empty() && !values.contains(value),
+                    Op::Lt => values.iter().any(|v| ordered(v).is_some_and(|o| o.is_lt())),
+                    Op::Le => values.iter().any(|v| ordered(v).is_some_and(|o| o.is_le())),
+                    Op::Gt => values.iter().any(|v| ordered(v).is_some_and(|o| o.is_gt())),
+                    Op::Ge => values.iter().any(|v| ordered(v).is_some_and(|o| o.is_ge())),
+                    Op::Matches => false,
+                }
+            }
+            Self::Matches(field, regex) => (field.values)(msg)
+                .iter()
+                .any(|v| matches!(v, Value::Text(text) if regex.is_match(text))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        smb::{
+            body::{create::CreateRequest, error::ErrorResponse, Body},
+            flags::Flags,
+            opcodes::Opcodes,
+            SMBHeader,
+        },
+        tcpdump::header::Header,
+    };
+
+    fn tcp() -> Header {
+        Header::parse(
+            "12:00:00.000000 IP 10.0.0.2.50000 > 10.0.0.1.445: Flags [P.], seq 1000:1110, \
+             ack 5000, win 502, length 110",
+        )
+        .unwrap()
+    }
+
+    fn header(opcode: Opcodes) -> SMBHeader {
+        SMBHeader {
+            magic: *b"\xfeSMB",
+            hlen: 64,
+            cred_charge: 1,
+            nt_status: 0,
+            opcode,
+            cred_req_res: 1,
+            flags: Flags::empty(),
+            chain_offset: 0,
+            cmd_seq: 5,
+            pid: 0,
+            tid: 1,
+            uid: 9,
+            signature: 0,
+        }
+    }
+
+    fn create(name: &str) -> Body {
+        Body::CreateRequest(CreateRequest {
+            oplock_level: 0,
+            impersonation_level: 2,
+            desired_access: 0x0012_0089,
+            file_attributes: 0,
+            share_access: 7,
+            disposition: 1,
+            options: 0,
+            name: name.to_owned(),
+            contexts: vec![],
+        })
+    }
+
+    /// Whether `expr` matches a CREATE of `name` from 10.0.0.2:50000 to 10.0.0.1:445.
+    fn matches(expr: &str, name: &str) -> bool {
+        let (tcp, header, body) = (tcp(), header(Opcodes::Create), create(name));
+        let msg = Message {
+            frame: 3,
+            tcp: &tcp,
+            header: &header,
+            body: &body,
+            create_name: None,
+        };
+        Expr::parse(expr).unwrap().matches(&msg)
+    }
+
+    /// The tree of an expression, `(or a (and b c))`.
+    fn shape(expr: &Expr) -> String {
+        match expr {
+            Expr::And(a, b) => format!("(and {} {})", shape(a), shape(b)),
+            Expr::Or(a, b) => format!("(or {} {})", shape(a), shape(b)),
+            Expr::Not(a) => format!("(not {})", shape(a)),
+            Expr::Test(field) | Expr::Compare(field, ..) | Expr::Matches(field, _) => {
+                field.name.to_owned()
+            }
+        }
+    }
+
+    fn parsed(expr: &str) -> String {
+        shape(&Expr::parse(expr).unwrap())
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(
+            parsed("tcp.flags.syn || tcp.flags.ack && tcp.flags.push"),
+            "(or tcp.flags.syn (and tcp.flags.ack tcp.flags.push))"
+        );
+        assert_eq!(
+            parsed("(tcp.flags.syn || tcp.flags.ack) && tcp.flags.push"),
+            "(and (or tcp.flags.syn tcp.flags.ack) tcp.flags.push)"
+        );
+        assert_eq!(
+            parsed("!tcp.flags.syn && not tcp.flags.ack"),
+            "(and (not tcp.flags.syn) (not tcp.flags.ack))"
+        );
+        assert_eq!(
+            parsed("tcp.flags.syn or tcp.flags.ack or tcp.flags.push"),
+            "(or (or tcp.flags.syn tcp.flags.ack) tcp.flags.push)"
+        );
+        assert!(matches(
+            "tcp.port == 1 || tcp.port == 445 && frame.number == 3",
+            ""
+        ));
+        assert!(!matches(
+            "(tcp.port == 1 || tcp.port == 445) && frame.number == 4",
+            ""
+        ));
+    }
+
+    #[test]
+    fn multi_value_fields() {
+        // any of the ports for ==, all of them for !=
+        assert!(matches("tcp.port == 445", ""));
+        assert!(matches("tcp.port == 50000", ""));
+        assert!(!matches("tcp.port != 445", ""));
+        assert!(matches("tcp.port != 139", ""));
+        assert!(matches("!(tcp.port == 139)", ""));
+        assert!(matches("tcp.port < 446", ""));
+        assert!(matches("tcp.port > 49999", ""));
+        assert!(!matches("tcp.port > 50000", ""));
+        assert!(matches("ip.addr == 10.0.0.1 && ip.src == 10.0.0.2", ""));
+    }
+
+    #[test]
+    fn absent_fields() {
+        // a CREATE request has no status, whatever the operator
+        assert!(!matches("smb2.status == STATUS_SUCCESS", ""));
+        assert!(!matches("smb2.status != STATUS_SUCCESS", ""));
+        assert!(!matches("smb2.status", ""));
+        assert!(matches("!smb2.status", ""));
+    }
+
+    #[test]
+    fn values() {
+        assert!(matches("smb2.cmd == create && smb2.cmd == CREATE", ""));
+        assert!(matches("smb2.cmd == 0x05 && smb2.cmd != close", ""));
+        assert!(matches("smb2.msg_id == 5 && smb2.sesid == 0x9", ""));
+        assert!(matches("smb2.path == \"docs\\\\a b.txt\"", "docs\\a b.txt"));
+        assert!(matches("tcp.flags.push && !tcp.flags.syn", ""));
+        assert!(matches("tcp.flags.push == true && tcp.flags.syn == 0", ""));
+    }
+
+    #[test]
+    fn regex_escapes() {
+        // the usage example, `"\\.docx$"` once the shell is done with it
+        let expr = r#"smb2.cmd == create && smb2.path ~ "\\.docx$""#;
+        assert!(matches(expr, "reports\\q1.docx"));
+        assert!(!matches(expr, "reports\\q1_docx"));
+        assert!(!matches(expr, "reports\\q1.docx.tmp"));
+        // a single backslash is kept for the regex too
+        assert!(matches(r#"smb2.path matches "\.docx$""#, "q1.docx"));
+        assert!(matches(r#"smb2.path ~ "^reports\\\\""#, "reports\\q1.docx"));
+    }
+
+    #[test]
+    fn create_response_path() {
+        // the example of the module doc, on the response refusing to open a .docx
+        let expr =
+            r#"smb2.cmd == create && smb2.status != STATUS_SUCCESS && smb2.path ~ "\.docx$""#;
+        let tcp = tcp();
+        let mut header = header(Opcodes::Create);
+        header.flags = Flags::FlagsServer2Redir;
+        header.nt_status = 0xC0000022;
+        let body = Body::Error(ErrorResponse {
+            context_count: 0,
+            details: vec![],
+        });
+        let msg = |create_name| Message {
+            frame: 4,
+            tcp: &tcp,
+            header: &header,
+            body: &body,
+            create_name,
+        };
+        let expr = Expr::parse(expr).unwrap();
+        assert!(expr.matches(&msg(Some("reports\\q1.docx"))));
+        assert!(!expr.matches(&msg(Some("reports\\q1.xlsx"))));
+        // the request wasn't captured
+        assert!(!expr.matches(&msg(None)));
+    }
+
+    fn error(expr: &str) -> Error {
+        Expr::parse(expr).unwrap_err()
+    }
+
+    #[test]
+    fn error_spans() {
+        assert_eq!(error("smb2.cmd =="), Error::UnexpectedEnd(11..11));
+        assert_eq!(error(""), Error::UnexpectedEnd(0..0));
+        assert_eq!(error("(tcp.port == 1"), Error::UnclosedParen(0..1));
+        assert_eq!(
+            error("(tcp.port == 1 tcp.syn"),
+            Error::UnexpectedToken(15..22)
+        );
+        assert_eq!(error("tcp.port == 1 )"), Error::UnexpectedToken(14..15));
+        assert_eq!(error("== 1"), Error::UnexpectedToken(0..2));
+        assert_eq!(error("tcp.port == &&"), Error::UnexpectedToken(12..14));
+        assert_eq!(error("smb2.nosuch == 1"), Error::UnknownField(0..11));
+        assert_eq!(
+            error("tcp.port == abc"),
+            Error::InvalidValue(12..15, Kind::Number)
+        );
+        assert_eq!(
+            error("smb2.cmd == nosuch"),
+            Error::InvalidValue(12..18, Kind::Opcode)
+        );
+        assert_eq!(
+            error("smb2.path < 3"),
+            Error::NotOrdered(10..11, Kind::Text)
+        );
+        assert_eq!(
+            error("tcp.port ~ \"1\""),
+            Error::NotText(9..10, Kind::Number)
+        );
+        assert!(matches!(
+            error("smb2.path ~ \"(\""),
+            Error::InvalidRegex(span, _) if span == (12..15)
+        ));
+        assert_eq!(error("tcp.port # 1"), Error::UnexpectedChar(9..10));
+    }
+
+    #[test]
+    fn pointer() {
+        let expr = "smb2.path < \"é\" && tcp.port == x";
+        assert_eq!(
+            error(expr).pointer(expr),
+            "  smb2.path < \"é\" && tcp.port == x\n            ^"
+        );
+        let expr = "é == 1";
+        assert_eq!(error(expr).pointer(expr), "  é == 1\n  ^");
+    }
+}
@@ -1,61 +1,491 @@
 #![feature(iterator_try_collect)]
-#![feature(never_type)]
 
+pub mod analysis;
+pub mod anonymize;
+pub mod capture;
+pub mod cli;
+pub mod dcerpc;
+pub mod filter;
+pub mod ntlmssp;
+pub mod pcap;
 pub mod prettify;
 pub mod smb;
 
 pub mod tcp;
 pub mod tcpdump;
+pub mod tui;
 
-use tcpdump::TcpdumpIter;
+use prettify::color::{paint, say, Role};
+use std::{collections::HashSet, io::Write};
+use strum::IntoEnumIterator;
+use tcp::flags::Flag;
 
-fn main() {
-    let mut gdynamic = None;
+fn fail(msg: impl std::fmt::Display) -> ! {
+    eprintln!("smbdump: {msg}");
+    std::process::exit(1)
+}
 
-    for (i, msg) in TcpdumpIter::default().enumerate() {
-        let msg = msg.expect("error reading tcpdump stream");
+fn open(path: &str, format: capture::Format) -> capture::Segments {
+    capture::open(path, format).unwrap_or_else(|err| match err {
+        capture::Error::Open(..) => fail(err),
+        err => fail(format!("error reading {path}: {err}")),
+    })
+}
 
-        let dynamic = match gdynamic {
-            Some(dynamic) => dynamic,
-            None => prettify::conn::Dynamic::new(msg.header.src.ip(), msg.header.dst.ip()),
+/// Reads a whole capture into the operations `--diff` compares, without printing it.
+fn transcript(path: &str, format: capture::Format) -> analysis::diff::Transcript {
+    let mut transcript = analysis::diff::Transcript::default();
+    for (i, msg) in open(path, format).enumerate() {
+        let msg = msg.unwrap_or_else(|err| fail(format!("error reading {path}: {err}")));
+        let frame = analysis::Frame::new(i, msg.header.time, msg.header.src, msg.header.dst);
+        let Ok(msgs) = smb::SMBMsg::parse_packet(&msg.data.data) else {
+            continue;
         };
-        if gdynamic.is_none() {
-            gdynamic = Some(dynamic);
+        for msg in msgs {
+            let body = msg.body().unwrap_or(smb::body::Body::Other);
+            transcript.feed(&frame, &msg.header, &body);
         }
+    }
+    transcript
+}
 
-        let data = msg.data.data;
+/// Writes the captures to `out` with `--anonymize`, and the mapping table next to it.
+fn anonymize(args: &cli::Args, out: &str) {
+    let mapping = args
+        .mapping
+        .clone()
+        .unwrap_or_else(|| format!("{out}.mapping"));
+    let write_error = |err: std::io::Error| -> ! { fail(format!("error writing {out}: {err}")) };
+    let file = std::fs::File::create(out).unwrap_or_else(|err| write_error(err));
+    let file = std::io::BufWriter::new(file);
+    // pcap when asked for by the extension, tcpdump text otherwise
+    let (mut pcap, mut text) = if out.ends_with(".pcap") {
+        let pcap = pcap::PcapWriter::new(file).unwrap_or_else(|err| write_error(err));
+        (Some(pcap), None)
+    } else {
+        (None, Some(file))
+    };
 
-        print!(
-            "{i} ({:?} {}) [seq {:?}, ack {:?}, win {}, {:?}]: ",
-            dynamic.direction(msg.header.src.ip(), msg.header.dst.ip()),
-            data.len(),
-            msg.header.seq,
-            msg.header.ack,
-            msg.header.win,
-            msg.header.flags
+    let mut msgs = vec![];
+    for path in &args.inputs {
+        for msg in open(path, args.format) {
+            msgs.push(msg.unwrap_or_else(|err| fail(format!("error reading {path}: {err}"))));
+        }
+    }
+    // a first pass learns the names of the capture, so that no pseudonym is one of them
+    let mut anonymizer = anonymize::Anonymizer::default();
+    for msg in &msgs {
+        anonymizer.packet(&mut msg.clone());
+    }
+    let mut anonymizer = anonymizer.restart();
+    for mut msg in msgs {
+        anonymizer.packet(&mut msg);
+        let written = match (&mut pcap, &mut text) {
+            (Some(pcap), _) => pcap.write(&msg),
+            (_, Some(text)) => match msg.to_text() {
+                Some(line) => writeln!(text, "{line}"),
+                None => fail(format!(
+                    "{out}: tcpdump text can't hold IPv6, write a .pcap"
+                )),
+            },
+            (None, None) => unreachable!("one of them is set"),
+        };
+        written.unwrap_or_else(|err| write_error(err));
+    }
+    let mut file = pcap
+        .map(pcap::PcapWriter::into_inner)
+        .or(text)
+        .expect("one of them is set");
+    file.flush().unwrap_or_else(|err| write_error(err));
+    std::fs::write(&mapping, anonymizer.mapping())
+        .unwrap_or_else(|err| fail(format!("error writing {mapping}: {err}")));
+}
+
+/// Reads the captures into the messages `--tui` browses, with what the analysis makes of them.
+fn browse(args: &cli::Args) -> Vec<tui::Entry> {
+    let mut analyzer = analysis::Analyzer::default();
+    let mut entries = vec![];
+    let mut i = 0;
+    for path in &args.inputs {
+        for msg in open(path, args.format) {
+            let msg = msg.unwrap_or_else(|err| fail(format!("error reading {path}: {err}")));
+            let number = i;
+            i += 1;
+            let tcp = msg.header;
+            let frame = analysis::Frame::new(number, tcp.time, tcp.src, tcp.dst);
+            analyzer.feed_tcp(&frame, &tcp.flags);
+            let msgs = match smb::SMBMsg::parse_packet(&msg.data.data) {
+                Ok(msgs) => msgs,
+                Err(smb::Error::UnsupportedVersion) => {
+                    analyzer.feed_smb1(&frame);
+                    continue;
+                }
+                Err(_) => continue,
+            };
+            for msg in msgs {
+                let context = analyzer.sessions.context(frame.conn, &msg.header);
+                let mut events = vec![];
+                let (body, body_error) = match msg.body() {
+                    Ok(body) => (body, None),
+                    Err(err) => {
+                        let event = analyzer.feed_malformed(&frame, &msg.header, &err);
+                        events.extend(prettify::event::event_line(&event));
+                        (smb::body::Body::Other, Some(format!("{err:?}")))
+                    }
+                };
+                let create_name = analyzer
+                    .opens
+                    .create_name(frame.conn, &msg.header)
+                    .map(str::to_owned);
+                let feed = analyzer.feed(&frame, &msg.header, &body);
+                let operation = feed.iter().find_map(|event| match event {
+                    analysis::Event::Operation(op) => Some(op),
+                    _ => None,
+                });
+                let operation =
+                    operation.or_else(|| analyzer.ops.pending(frame.conn, msg.header.cmd_seq));
+                let summary = prettify::ops::message(operation, &msg.header);
+                events.extend(feed.iter().filter_map(prettify::event::event_line));
+                entries.push(tui::Entry {
+                    frame: number,
+                    time: frame.time,
+                    conn: frame.conn,
+                    tcp: tcp.clone(),
+                    header: msg.header,
+                    body,
+                    body_error,
+                    create_name,
+                    payload: msg.payload,
+                    summary,
+                    context,
+                    events,
+                });
+            }
+        }
+    }
+    entries
+}
+
+fn main() {
+    let args = match cli::Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(cli::Error::Help) => {
+            println!("{}", cli::USAGE);
+            return;
+        }
+        Err(cli::Error::FilterFields) => {
+            for field in filter::fields::FIELDS {
+                let kind = format!("{:?}", field.kind).to_lowercase();
+                say!("{:24} {kind:8} {}", field.name, field.help);
+            }
+            return;
+        }
+        Err(cli::Error::ThemeRoles) => {
+            let theme = prettify::color::Theme::default();
+            for role in prettify::color::Role::iter() {
+                say!("{:12} {}", role.name(), theme.sgr(role));
+            }
+            return;
+        }
+        Err(err @ cli::Error::Filter(..)) => {
+            eprintln!("smbdump: {err}");
+            std::process::exit(2);
+        }
+        Err(err) => {
+            eprintln!("smbdump: {err}, see --help");
+            std::process::exit(2);
+        }
+    };
+    // JSON strings, diagram sources and HTML are no place for escape sequences
+    let color = match args.output {
+        cli::Output::Json | cli::Output::Sequence(_) | cli::Output::Html => {
+            prettify::color::When::Never
+        }
+        _ => args.color,
+    };
+    if let Err(err) = prettify::color::init(color, args.theme.as_deref()) {
+        eprintln!("smbdump: ignoring the theme, {err}");
+    }
+
+    if args.diff {
+        let (a, b) = (&args.inputs[0], &args.inputs[1]);
+        let (ta, tb) = (transcript(a, args.format), transcript(b, args.format));
+        let divergences = analysis::diff::diff(ta.steps(), tb.steps());
+        say!(
+            "{}",
+            prettify::diff::report(a, b, (ta.steps().len(), tb.steps().len()), &divergences)
         );
-        if data.is_empty() {
-            println!("\x1b[37;3;4mno smb message\x1b[0m");
-        } else {
-            match smb::SMBMsg::parse_from_raw(data.clone()) {
-                Ok(msg) => {
-                    println!(
-                        "\n {:?}{}",
+        return;
+    }
+
+    if let Some(out) = &args.anonymize {
+        anonymize(&args, out);
+        return;
+    }
+
+    if args.tui {
+        let entries = browse(&args);
+        let title = format!("smbdump {}", args.inputs.join(" "));
+        if let Err(err) = tui::run(entries, args.filter, title) {
+            fail(err);
+        }
+        return;
+    }
+
+    let mut gdynamic = None;
+    let mut analyzer = analysis::Analyzer::default();
+    let mut json = prettify::json::Writer::default();
+    // time of the first packet, what the one line output counts from
+    let mut start = None;
+    // requests passing the filter, whose operation is listed with the response
+    let mut listed_requests = HashSet::new();
+    let mut diagram = match args.output {
+        cli::Output::Sequence(style) => Some(prettify::sequence::Diagram::new(style)),
+        _ => None,
+    };
+    let mut html = (args.output == cli::Output::Html).then(prettify::html::Report::default);
+    let filter = &args.filter;
+    let verbosity = args.verbosity;
+    // frames are numbered across the inputs, as if they were one capture
+    let mut i = 0;
+
+    for path in &args.inputs {
+        for msg in open(path, args.format) {
+            let msg = msg.unwrap_or_else(|err| fail(format!("error reading {path}: {err}")));
+            let number = i;
+            i += 1;
+
+            let dynamic = match gdynamic {
+                Some(dynamic) => dynamic,
+                None => prettify::conn::Dynamic::new(msg.header.src.ip(), msg.header.dst.ip()),
+            };
+            if gdynamic.is_none() {
+                gdynamic = Some(dynamic);
+            }
+
+            let start = *start.get_or_insert(msg.header.time);
+            let data = msg.data.data;
+            let tcp = &msg.header;
+            let frame =
+                analysis::Frame::new(number, msg.header.time, msg.header.src, msg.header.dst);
+            let listed = filter.packet(number, msg.header.src, msg.header.dst);
+            let shown = args.output == cli::Output::Text && listed;
+            // printed at once, once we know whether a message of the packet passes the filter
+            let mut out = format!(
+                "{number} ({:?} {}) [seq {:?}, ack {:?}, win {}, {:?}]: ",
+                dynamic.direction(msg.header.src.ip(), msg.header.dst.ip()),
+                data.len(),
+                msg.header.seq,
+                msg.header.ack,
+                msg.header.win,
+                msg.header.flags
+            );
+            let mut selected = !filter.selects_messages();
+            if verbosity >= 3 {
+                let headers = [msg.data.ip_header, msg.data.tcp_header].concat();
+                let dump = prettify::byte::dump(args.dump, &headers, 0);
+                out += &paint(Role::Dim, dump);
+            }
+            analyzer.feed_tcp(&frame, &msg.header.flags);
+            if let Some(diagram) = diagram.as_mut().filter(|_| listed) {
+                let events: Vec<_> = [(Flag::SYN, "SYN"), (Flag::FIN, "FIN"), (Flag::RST, "RST")]
+                    .into_iter()
+                    .filter(|(flag, _)| msg.header.flags.is_set(*flag))
+                    .map(|(_, name)| name)
+                    .collect();
+                if !events.is_empty() {
+                    let (src, dst) = (msg.header.src, msg.header.dst);
+                    let text = format!("{} :{} to :{}", events.join("+"), src.port(), dst.port());
+                    diagram.note(number, src.ip(), text);
+                }
+            }
+            if data.is_empty() {
+                if shown && selected {
+                    let sep = if verbosity >= 3 { "\n " } else { "" };
+                    say!("{out}{sep}{}", paint(Role::NoMessage, "no smb message"));
+                }
+                continue;
+            }
+
+            let msgs = match smb::SMBMsg::parse_packet(&data) {
+                Ok(msgs) => msgs,
+                Err(err) => {
+                    if err == smb::Error::UnsupportedVersion {
+                        analyzer.feed_smb1(&frame);
+                    }
+                    if shown && selected {
+                        let dump = match verbosity {
+                            0 => String::new(),
+                            _ => prettify::byte::dump(args.dump, &data, 0),
+                        };
+                        let error =
+                            paint(Role::ParseError, format!("smb msg parse error: {err:?}"));
+                        say!("{out}{error}{dump}");
+                    }
+                    continue;
+                }
+            };
+
+            // with --dump fields the packet is dumped once, annotated, instead of message by message
+            let dissect = verbosity >= 2 && args.dump == prettify::byte::Dump::Fields;
+            if dissect {
+                let spans = prettify::dissect::packet_spans(&data, &msgs);
+                out += &prettify::dissect::annotated(&data, &spans);
+            }
+
+            let mut commands = vec![];
+            let mut text = String::new();
+            for msg in msgs {
+                // lines of this message, only kept if it's shown
+                let mut lines = String::new();
+                let body = msg.body();
+                let context = analyzer.sessions.context(frame.conn, &msg.header);
+                let context = if context.is_empty() {
+                    context
+                } else {
+                    format!("{} ", paint(Role::Context, context))
+                };
+                if let Ok(smb::body::Body::Error(_)) = body {
+                    // the decoded reason replaces the hex dump
+                    lines += &format!("\n {context}{:?}\n", msg.header);
+                } else if verbosity >= 2 && !dissect {
+                    lines += &format!(
+                        "\n {context}{:?}{}\n",
                         msg.header,
-                        prettify::byte::byte_iter_as_str(&mut msg.payload.iter(), 16)
-                            .expect("i/o error")
+                        prettify::byte::dump(args.dump, &msg.payload, msg.header.hlen.into())
                     );
+                } else {
+                    lines += &format!("\n {context}{:?}\n", msg.header);
                 }
-                Err(err) => {
-                    let mut it = data.iter();
-                    println!(
-                        "\x1b[31;1;3;4msmb msg parse error: {:?}\x1b[0m{}",
-                        err,
-                        prettify::byte::byte_iter_as_str(&mut it, 16)
-                            .expect("i/o error")
+                let mut events = vec![];
+                let (body, body_error) = match body {
+                    Ok(body) => {
+                        match &body {
+                            smb::body::Body::Other => {}
+                            smb::body::Body::Error(err) => {
+                                let line = prettify::error::error_line(msg.header.status(), err);
+                                lines += &format!(" {}\n", paint(Role::Failure, line));
+                            }
+                            body => {
+                                lines += &format!(" {body:?}\n");
+                            }
+                        }
+                        (body, None)
+                    }
+                    Err(err) => {
+                        let error = format!("smb body parse error: {err:?}");
+                        lines += &format!("{}\n", paint(Role::BodyError, error));
+                        let event = analyzer.feed_malformed(&frame, &msg.header, &err);
+                        events.extend(prettify::event::event_line(&event));
+                        (smb::body::Body::Other, Some(err))
+                    }
+                };
+
+                let listed = listed
+                    && filter.message(&filter::fields::Message {
+                        frame: number,
+                        tcp,
+                        header: &msg.header,
+                        body: &body,
+                        create_name: analyzer.opens.create_name(frame.conn, &msg.header),
+                    });
+                let shown = shown && listed;
+                selected |= shown;
+                let feed = analyzer.feed(&frame, &msg.header, &body);
+                // the operation the message is part of, what the diagrams and the HTML describe
+                let operation = feed.iter().find_map(|event| match event {
+                    analysis::Event::Operation(op) => Some(op),
+                    _ => None,
+                });
+                let operation =
+                    operation.or_else(|| analyzer.ops.pending(frame.conn, msg.header.cmd_seq));
+                let summary = prettify::ops::message(operation, &msg.header);
+                if let Some(diagram) = diagram.as_mut().filter(|_| listed) {
+                    let response = msg.header.is_response();
+                    diagram.message(
+                        number,
+                        tcp.src.ip(),
+                        tcp.dst.ip(),
+                        summary.clone(),
+                        response,
                     );
                 }
-            };
+                if args.output == cli::Output::Oneline {
+                    if listed && !msg.header.is_response() {
+                        listed_requests.insert((frame.conn, msg.header.cmd_seq));
+                    }
+                    for event in &feed {
+                        if let analysis::Event::Operation(op) = event {
+                            if listed_requests.remove(&(op.conn, op.msg_id)) | listed {
+                                say!("{}", prettify::ops::line(op, start));
+                            }
+                        }
+                    }
+                }
+                events.extend(feed.iter().filter_map(prettify::event::event_line));
+                for line in &events {
+                    lines += &format!(" {line}\n");
+                }
+                if args.output == cli::Output::Json && listed {
+                    let body = body_error.as_ref().map_or(Ok(&body), Err);
+                    say!("{}", json.record(&frame, tcp, &msg.header, body, &events));
+                }
+                if let Some(html) = html.as_mut().filter(|_| listed) {
+                    let body = body_error.as_ref().map_or(Ok(&body), Err);
+                    html.message(
+                        &frame,
+                        tcp,
+                        &msg.header,
+                        body,
+                        &msg.payload,
+                        &summary,
+                        context.trim_end(),
+                        &events,
+                    );
+                }
+                if shown {
+                    commands.push(format!(
+                        "{:?} {}",
+                        msg.header.opcode,
+                        if msg.header.is_response() {
+                            "response"
+                        } else {
+                            "request"
+                        }
+                    ));
+                    text += &lines;
+                }
+            }
+            if shown && selected {
+                if verbosity == 0 {
+                    say!("{out}{}", commands.join(", "));
+                } else {
+                    out += text.strip_suffix('\n').unwrap_or(&text);
+                    say!("{out}");
+                }
+            }
+        }
+    }
+
+    analyzer.finish();
+    if let (cli::Output::Oneline, Some(start)) = (args.output, start) {
+        for op in analyzer.ops.unanswered() {
+            if listed_requests.contains(&(op.conn, op.msg_id)) {
+                say!("{}", prettify::ops::unanswered_line(op, start));
+            }
         }
     }
+    if let Some(diagram) = &diagram {
+        say!("{}", diagram.render());
+    }
+    if let Some(html) = &html {
+        say!("{}", html.render(&analyzer, &args.report, &args.inputs));
+    }
+    if matches!(args.output, cli::Output::Text | cli::Output::Report) {
+        prettify::report::end_of_capture(&analyzer, &args.report);
+    }
+    if let Some(path) = &args.audit_csv {
+        std::fs::write(path, prettify::audit::csv(analyzer.audit.entries()))
+            .unwrap_or_else(|err| fail(format!("error writing {path}: {err}")));
+    }
 }
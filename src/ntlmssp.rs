@@ -0,0 +1,166 @@
+//! Just enough of [MS-NLMP] to know who is authenticating, NTLMSSP messages are found inside the
+//! SPNEGO tokens of SESSION_SETUP without decoding the ASN.1 around them.
+use crate::smb::{
+    reader::{Reader, Span},
+    types::utf16le,
+};
+
+const SIGNATURE: &[u8; 8] = b"NTLMSSP\0";
+const NEGOTIATE_UNICODE: u32 = 0x00000001;
+const NEGOTIATE_ANONYMOUS: u32 = 0x00000800;
+
+/// DER encoded OIDs of the Kerberos mechanisms, the standard one and the one Microsoft uses.
+const KRB5_OIDS: [&[u8]; 2] = [
+    &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x12, 0x01, 0x02, 0x02],
+    &[0x2a, 0x86, 0x48, 0x82, 0xf7, 0x12, 0x01, 0x02, 0x02],
+];
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Message {
+    Negotiate,
+    Challenge {
+        target_name: String,
+    },
+    Authenticate {
+        domain: String,
+        user: String,
+        workstation: String,
+        /// Null session, no user and no NT response.
+        anonymous: bool,
+    },
+    Unknown(u32),
+}
+
+/// What a GSS token carries, as far as we care.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Token {
+    Ntlmssp(Message),
+    Kerberos,
+    Empty,
+    Other,
+}
+
+impl Token {
+    pub fn find(blob: &[u8]) -> Self {
+        if blob.is_empty() {
+            return Self::Empty;
+        }
+        if let Some(start) = blob.windows(SIGNATURE.len()).position(|w| w == SIGNATURE) {
+            if let Some(msg) = Message::parse(&blob[start..]) {
+                return Self::Ntlmssp(msg);
+            }
+        }
+        if KRB5_OIDS
+            .iter()
+            .any(|oid| blob.windows(oid.len()).any(|w| w == *oid))
+        {
+            return Self::Kerberos;
+        }
+        Self::Other
+    }
+}
+
+impl Message {
+    /// `raw` starts at the signature, field offsets are relative to it.
+    pub fn parse(raw: &[u8]) -> Option<Self> {
+        let mut r = Reader::new(raw, 0);
+        if r.bytes(SIGNATURE.len())? != SIGNATURE {
+            return None;
+        }
+        Some(match r.u32()? {
+            1 => Self::Negotiate,
+            2 => {
+                let target_name = field(&mut r)?;
+                let flags = r.u32()?;
+                Self::Challenge {
+                    target_name: string(&r, target_name, flags),
+                }
+            }
+            3 => {
+                let _lm_response = field(&mut r)?;
+                let nt_response = field(&mut r)?;
+                let domain = field(&mut r)?;
+                let user = field(&mut r)?;
+                let workstation = field(&mut r)?;
+                let _session_key = field(&mut r)?;
+                let flags = r.u32()?;
+                let user = string(&r, user, flags);
+                Self::Authenticate {
+                    domain: string(&r, domain, flags),
+                    anonymous: flags & NEGOTIATE_ANONYMOUS != 0
+                        || (user.is_empty() && nt_response.1 == 0),
+                    user,
+                    workstation: string(&r, workstation, flags),
+                }
+            }
+            other => Self::Unknown(other),
+        })
+    }
+}
+
+/// Where the variable fields of a message are, `raw` starting at the signature, and whether its
+/// strings are UTF-16. Names are the [MS-NLMP] fields'.
+pub fn spans(raw: &[u8]) -> Option<(Vec<Span>, bool)> {
+    let mut r = Reader::new(raw, 0);
+    if r.bytes(SIGNATURE.len())? != SIGNATURE {
+        return None;
+    }
+    let mut fields = vec![];
+    let flags = match r.u32()? {
+        1 => {
+            r.u32()?;
+            fields.push(("domain_name", field(&mut r)?));
+            fields.push(("workstation", field(&mut r)?));
+            // always OEM in a NEGOTIATE message
+            0
+        }
+        2 => {
+            fields.push(("target_name", field(&mut r)?));
+            let flags = r.u32()?;
+            // ServerChallenge and Reserved
+            r.skip(16)?;
+            fields.push(("target_info", field(&mut r)?));
+            flags
+        }
+        3 => {
+            for name in [
+                "lm_challenge_response",
+                "nt_challenge_response",
+                "domain_name",
+                "user_name",
+                "workstation",
+                "encrypted_random_session_key",
+            ] {
+                fields.push((name, field(&mut r)?));
+            }
+            r.u32()?
+        }
+        _ => return None,
+    };
+    let spans = fields
+        .into_iter()
+        .filter(|(_, (offset, len))| *len > 0 && offset + len <= raw.len())
+        .map(|(name, (offset, len))| Span {
+            name,
+            range: offset..offset + len,
+        })
+        .collect();
+    Some((spans, flags & NEGOTIATE_UNICODE != 0))
+}
+
+/// Len, MaxLen and Offset of a payload field, returned as (offset, len).
+fn field(r: &mut Reader) -> Option<(usize, usize)> {
+    let len = r.u16()?;
+    let _max_len = r.u16()?;
+    let offset = r.u32()?;
+    Some((offset as usize, len.into()))
+}
+
+fn string(r: &Reader, (offset, len): (usize, usize), flags: u32) -> String {
+    let raw = r.slice_at(offset, len).unwrap_or_default();
+    if flags & NEGOTIATE_UNICODE != 0 {
+        utf16le(raw)
+    } else {
+        String::from_utf8_lossy(raw).into_owned()
+    }
+}
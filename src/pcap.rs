@@ -0,0 +1,297 @@
+//! Reader for libpcap capture files (what `tcpdump -w` and Wireshark write), yielding the TCP
+//! segments the same way the tcpdump text reader does, and a writer for segments read either way.
+use chrono::{DateTime, NaiveTime, Timelike};
+use std::{
+    io::{self, Read, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+
+use crate::{
+    tcp::flags::FlagCollection,
+    tcpdump::{data::Data, header::Header, TcpdumpMsg},
+};
+
+/// Magic numbers of the file header, as read in little endian.
+pub const MAGIC_MICROS: u32 = 0xa1b2c3d4;
+pub const MAGIC_NANOS: u32 = 0xa1b23c4d;
+/// Section header block type of pcapng, which is a different format.
+pub const MAGIC_PCAPNG: u32 = 0x0a0d0d0a;
+
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_IPV4: u32 = 228;
+const LINKTYPE_IPV6: u32 = 229;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const IPPROTO_TCP: u8 = 6;
+/// Snapshot length of the files written, larger than any segment.
+const SNAPLEN: u32 = 262144;
+/// Longest record read whatever the header says, far above what big TCP offloads produce.
+const MAX_RECORD: u32 = 16 << 20;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    InvalidMagic(u32),
+    /// pcapng files have to be converted with `editcap -F pcap` first.
+    Pcapng,
+    UnsupportedLinkType(u32),
+    /// The file ends in the middle of a record.
+    Truncated,
+    /// A record claims more bytes than the snapshot length allows, the file is corrupt.
+    RecordTooLong(u32),
+}
+
+pub struct PcapIter<R: Read> {
+    stream: R,
+    big_endian: bool,
+    nanos: bool,
+    link_type: u32,
+    /// Longest record accepted, the snapshot length of the header but at least `SNAPLEN`, as
+    /// some writers put 0 or less than they captured there, and at most `MAX_RECORD`.
+    max_len: u32,
+}
+
+impl<R: Read> PcapIter<R> {
+    /// Reads the file header.
+    pub fn new(mut stream: R) -> Result<Self, Error> {
+        let mut header = [0; 24];
+        stream
+            .read_exact(&mut header)
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::UnexpectedEof => Error::Truncated,
+                _ => Error::Io(err),
+            })?;
+        let magic = u32::from_le_bytes(header[..4].try_into().unwrap());
+        let (big_endian, nanos) = match magic {
+            MAGIC_MICROS => (false, false),
+            MAGIC_NANOS => (false, true),
+            _ if magic.swap_bytes() == MAGIC_MICROS => (true, false),
+            _ if magic.swap_bytes() == MAGIC_NANOS => (true, true),
+            MAGIC_PCAPNG => return Err(Error::Pcapng),
+            _ => return Err(Error::InvalidMagic(magic)),
+        };
+        let mut iter = Self {
+            stream,
+            big_endian,
+            nanos,
+            link_type: 0,
+            max_len: 0,
+        };
+        iter.max_len = iter.u32(&header[16..20]).clamp(SNAPLEN, MAX_RECORD);
+        iter.link_type = iter.u32(&header[20..24]) & 0x0fff_ffff;
+        match iter.link_type {
+            LINKTYPE_NULL | LINKTYPE_ETHERNET | LINKTYPE_RAW | LINKTYPE_LINUX_SLL
+            | LINKTYPE_IPV4 | LINKTYPE_IPV6 | LINKTYPE_LINUX_SLL2 => Ok(iter),
+            other => Err(Error::UnsupportedLinkType(other)),
+        }
+    }
+
+    fn u32(&self, raw: &[u8]) -> u32 {
+        let raw = raw.try_into().unwrap();
+        if self.big_endian {
+            u32::from_be_bytes(raw)
+        } else {
+            u32::from_le_bytes(raw)
+        }
+    }
+
+    /// Next record, `None` at the end of the file.
+    fn record(&mut self) -> Option<Result<(NaiveTime, Vec<u8>), Error>> {
+        let mut header = [0; 16];
+        match self.stream.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return None,
+            Err(err) => return Some(Err(Error::Io(err))),
+        }
+        let secs = self.u32(&header[..4]);
+        let frac = self.u32(&header[4..8]);
+        let len = self.u32(&header[8..12]);
+        if len > self.max_len {
+            return Some(Err(Error::RecordTooLong(len)));
+        }
+        let nanos = if self.nanos {
+            frac
+        } else {
+            frac.saturating_mul(1000)
+        };
+        let time = DateTime::from_timestamp(secs.into(), nanos)
+            .map(|t| t.time())
+            .unwrap_or_default();
+
+        let mut packet = vec![0; len as usize];
+        if let Err(err) = self.stream.read_exact(&mut packet) {
+            return Some(Err(match err.kind() {
+                io::ErrorKind::UnexpectedEof => Error::Truncated,
+                _ => Error::Io(err),
+            }));
+        }
+        Some(Ok((time, packet)))
+    }
+
+    /// IP packet in a link layer frame, `None` for anything else (ARP, LLDP...).
+    fn ip_packet<'a>(&self, frame: &'a [u8]) -> Option<&'a [u8]> {
+        let ethertype = |raw: &[u8]| Some(u16::from_be_bytes(raw.try_into().ok()?));
+        match self.link_type {
+            LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 => Some(frame),
+            // address family in the byte order of the capturing host
+            LINKTYPE_NULL => frame.get(4..),
+            LINKTYPE_ETHERNET => {
+                let mut offset = 12;
+                let mut kind = ethertype(frame.get(offset..offset + 2)?)?;
+                while kind == ETHERTYPE_VLAN {
+                    offset += 4;
+                    kind = ethertype(frame.get(offset..offset + 2)?)?;
+                }
+                matches!(kind, ETHERTYPE_IPV4 | ETHERTYPE_IPV6).then(|| frame.get(offset + 2..))?
+            }
+            LINKTYPE_LINUX_SLL => {
+                let kind = ethertype(frame.get(14..16)?)?;
+                matches!(kind, ETHERTYPE_IPV4 | ETHERTYPE_IPV6).then(|| frame.get(16..))?
+            }
+            LINKTYPE_LINUX_SLL2 => {
+                let kind = ethertype(frame.get(..2)?)?;
+                matches!(kind, ETHERTYPE_IPV4 | ETHERTYPE_IPV6).then(|| frame.get(20..))?
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Splits an IPv4 or IPv6 packet carrying TCP in its headers and payload, `None` for other
+/// protocols and for fragments.
+fn tcp_segment(time: NaiveTime, ip: &[u8]) -> Option<TcpdumpMsg> {
+    let (ip_header, segment, src, dst) = match ip.first()? >> 4 {
+        4 => {
+            let ihl = usize::from(ip.first()? & 0x0f) * 4;
+            let total = usize::from(u16::from_be_bytes(ip.get(2..4)?.try_into().ok()?));
+            let fragment = u16::from_be_bytes(ip.get(6..8)?.try_into().ok()?);
+            if *ip.get(9)? != IPPROTO_TCP || fragment & 0x3fff != 0 {
+                return None;
+            }
+            let src: [u8; 4] = ip.get(12..16)?.try_into().ok()?;
+            let dst: [u8; 4] = ip.get(16..20)?.try_into().ok()?;
+            (
+                ip.get(..ihl)?,
+                // the Ethernet minimum frame size pads short packets
+                ip.get(ihl..total.min(ip.len()))?,
+                IpAddr::V4(Ipv4Addr::from(src)),
+                IpAddr::V4(Ipv4Addr::from(dst)),
+            )
+        }
+        6 => {
+            let payload = usize::from(u16::from_be_bytes(ip.get(4..6)?.try_into().ok()?));
+            if *ip.get(6)? != IPPROTO_TCP {
+                return None;
+            }
+            let src: [u8; 16] = ip.get(8..24)?.try_into().ok()?;
+            let dst: [u8; 16] = ip.get(24..40)?.try_into().ok()?;
+            (
+                ip.get(..40)?,
+                ip.get(40..(40 + payload).min(ip.len()))?,
+                IpAddr::V6(Ipv6Addr::from(src)),
+                IpAddr::V6(Ipv6Addr::from(dst)),
+            )
+        }
+        _ => return None,
+    };
+
+    let word = |at: usize| {
+        Some(u32::from_be_bytes(
+            segment.get(at..at + 4)?.try_into().ok()?,
+        ))
+    };
+    let port = |at: usize| {
+        Some(u16::from_be_bytes(
+            segment.get(at..at + 2)?.try_into().ok()?,
+        ))
+    };
+    let data_offset = usize::from(segment.get(12)? >> 4) * 4;
+    let flags = FlagCollection::from_bits(*segment.get(13)?);
+    let (tcp_header, data) = segment.split_at_checked(data_offset)?;
+
+    Some(TcpdumpMsg {
+        header: Header {
+            time,
+            src: SocketAddr::new(src, port(0)?),
+            dst: SocketAddr::new(dst, port(2)?),
+            seq: Some(word(4)?),
+            ack: Some(word(8)?),
+            win: port(14)?,
+            options: None,
+            length: data.len() as u128,
+            flags,
+        },
+        data: Data {
+            ip_header: ip_header.to_vec(),
+            tcp_header: tcp_header.to_vec(),
+            data: data.to_vec(),
+        },
+    })
+}
+
+impl<R: Read> Iterator for PcapIter<R> {
+    type Item = Result<TcpdumpMsg, Error>;
+
+    /// Next TCP segment, packets of other protocols are skipped.
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (time, frame) = match self.record()? {
+                Ok(record) => record,
+                Err(err) => return Some(Err(err)),
+            };
+            if let Some(msg) = self.ip_packet(&frame).and_then(|ip| tcp_segment(time, ip)) {
+                return Some(Ok(msg));
+            }
+        }
+    }
+}
+
+/// Writes segments as raw IP packets, the link layer of the input being lost already. Times have
+/// no date, they are written as the first day of 1970.
+pub struct PcapWriter<W: Write> {
+    stream: W,
+}
+
+impl<W: Write> PcapWriter<W> {
+    /// Writes the file header.
+    pub fn new(mut stream: W) -> io::Result<Self> {
+        let mut header = vec![];
+        header.extend(MAGIC_MICROS.to_le_bytes());
+        header.extend(2u16.to_le_bytes());
+        header.extend(4u16.to_le_bytes());
+        // time zone and timestamp accuracy, always zero
+        header.extend([0; 8]);
+        header.extend(SNAPLEN.to_le_bytes());
+        header.extend(LINKTYPE_RAW.to_le_bytes());
+        stream.write_all(&header)?;
+        Ok(Self { stream })
+    }
+
+    pub fn write(&mut self, msg: &TcpdumpMsg) -> io::Result<()> {
+        let packet = [
+            msg.data.ip_header.as_slice(),
+            &msg.data.tcp_header,
+            &msg.data.data,
+        ]
+        .concat();
+        let time = msg.header.time;
+        let len = packet.len() as u32;
+        let mut record = vec![];
+        record.extend(time.num_seconds_from_midnight().to_le_bytes());
+        record.extend((time.nanosecond() / 1000).to_le_bytes());
+        record.extend(len.to_le_bytes());
+        record.extend(len.to_le_bytes());
+        self.stream.write_all(&record)?;
+        self.stream.write_all(&packet)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.stream
+    }
+}
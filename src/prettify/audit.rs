@@ -0,0 +1,124 @@
+//! Audit timeline, as text for reading and as CSV for spreadsheets.
+use super::color::{paint, Role};
+use crate::analysis::audit::{AuditAction, AuditEntry};
+
+/// Names of the parts of a security descriptor in a SET_INFO `AdditionalInformation`.
+fn security_parts(parts: u32) -> String {
+    let names: Vec<&str> = [
+        (0x01, "owner"),
+        (0x02, "group"),
+        (0x04, "dacl"),
+        (0x08, "sacl"),
+        (0x10, "label"),
+    ]
+    .iter()
+    .filter(|(bit, _)| parts & bit != 0)
+    .map(|(_, name)| *name)
+    .collect();
+    if names.is_empty() {
+        format!("0x{parts:x}")
+    } else {
+        names.join("+")
+    }
+}
+
+/// What the action did beyond its name, empty when there's nothing to add.
+fn detail(entry: &AuditEntry) -> String {
+    let ops = |what: &str| {
+        if entry.count > 1 {
+            format!(" in {} {what}s", entry.count)
+        } else {
+            String::new()
+        }
+    };
+    match &entry.action {
+        AuditAction::Read(bytes) => format!("{bytes} bytes{}", ops("read")),
+        AuditAction::Write(bytes) => format!("{bytes} bytes{}", ops("write")),
+        AuditAction::Rename { to } | AuditAction::Link { to } => format!("to \\{to}"),
+        AuditAction::SetAttributes { attributes } => format!("attributes 0x{attributes:x}"),
+        AuditAction::SetSize(size) => format!("size {size}"),
+        AuditAction::SetSecurity { parts } => security_parts(*parts),
+        AuditAction::Open | AuditAction::Create | AuditAction::Overwrite | AuditAction::Delete => {
+            String::new()
+        }
+    }
+}
+
+fn user(entry: &AuditEntry) -> String {
+    entry
+        .user
+        .as_ref()
+        .map(|u| u.to_string())
+        .unwrap_or_default()
+}
+
+/// Full path of the file, `\\server\share\path` when the TREE_CONNECT was captured.
+fn full_path(entry: &AuditEntry) -> String {
+    match &entry.share {
+        Some(share) => format!("{share}\\{}", entry.path),
+        None => format!("\\\\{}\\?\\{}", entry.conn.server.ip(), entry.path),
+    }
+}
+
+pub fn entry_line(entry: &AuditEntry) -> String {
+    let who = match &entry.user {
+        Some(_) => format!("{} ({})", user(entry), entry.conn.client.ip()),
+        None => entry.conn.client.ip().to_string(),
+    };
+    let mut line = format!(
+        "  {} frame {:<6} {who} {} {}",
+        entry.time.format("%H:%M:%S%.3f"),
+        entry.frame,
+        entry.action.name().to_uppercase(),
+        full_path(entry)
+    );
+    let detail = detail(entry);
+    if !detail.is_empty() {
+        line += &format!(" {detail}");
+    }
+    if entry.status.is_success() {
+        line += ": ok";
+    } else {
+        line = paint(Role::Failure, format!("{line}: {:?}", entry.status));
+    }
+    line
+}
+
+/// RFC 4180 field, quoted when it has to be. Paths and user names come from the client, so one
+/// that a spreadsheet would take for a formula gets a leading `'` to keep it text.
+fn csv_field(field: &str) -> String {
+    let field = if field.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{field}")
+    } else {
+        field.to_owned()
+    };
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+pub fn csv(entries: &[AuditEntry]) -> String {
+    let mut out =
+        "time,frame,user,client,server,share,path,action,detail,status,count\n".to_owned();
+    for entry in entries {
+        let fields = [
+            entry.time.format("%H:%M:%S%.6f").to_string(),
+            entry.frame.to_string(),
+            user(entry),
+            entry.conn.client.to_string(),
+            entry.conn.server.to_string(),
+            entry.share.clone().unwrap_or_default(),
+            entry.path.clone(),
+            entry.action.name().to_owned(),
+            detail(entry),
+            format!("{:?}", entry.status),
+            entry.count.to_string(),
+        ];
+        let fields: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        out += &fields.join(",");
+        out += "\n";
+    }
+    out
+}
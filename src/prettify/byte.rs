@@ -1,5 +1,7 @@
 use std::{fmt::Write, ops::Deref};
 
+use super::color::{paint, Role};
+
 pub fn byte_iter_as_str(
     iter: impl Iterator<Item = impl Deref<Target = u8>>,
     wrap: usize,
@@ -23,16 +25,82 @@ pub fn byte_iter(
         if c.is_ascii_graphic() {
             write!(f, "{}", c as char)?;
         } else if c == 0 {
-            #[cfg(not(feature = "color"))]
-            write!(f, "\\0")?;
-            #[cfg(feature = "color")]
-            write!(f, "\x1b[1;35m\\0\x1b[0m")?;
+            write!(f, "{}", paint(Role::NullByte, "\\0"))?;
         } else {
-            #[cfg(not(feature = "color"))]
-            write!(f, "\\x{c:02X}")?;
-            #[cfg(feature = "color")]
-            write!(f, "\x1b[1;31m\\x{c:02X}\x1b[0m")?;
+            write!(f, "{}", paint(Role::Byte, format!("\\x{c:02X}")))?;
         }
     }
     Ok(())
 }
+
+/// How the bytes of a packet are printed.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum Dump {
+    /// Printable ASCII as is and the other bytes escaped (`\x0A`, `\0`), 16 bytes a line.
+    #[default]
+    Escaped,
+    /// Offsets, hex columns and an ASCII gutter.
+    Hex,
+    /// `Hex` plus a column decoding the bytes as UTF-16LE, the encoding of SMB2 names and paths.
+    HexUtf16,
+    /// `Hex` of whole packets with the bytes of each decoded field colored and listed, see
+    /// `dissect`. Anything that isn't a parsed packet gets a plain `Hex`.
+    Fields,
+}
+
+/// Dump of `bytes` in `style`, `base` being the offset of the first byte (the SMB2 header size
+/// for a body, as offsets in the spec are relative to the header). Every line starts with a line
+/// break, like `byte_iter`.
+pub fn dump(style: Dump, bytes: &[u8], base: usize) -> String {
+    match style {
+        Dump::Escaped => byte_iter_as_str(bytes.iter(), 16).expect("i/o error"),
+        Dump::Hex | Dump::Fields => hexdump(bytes, base, false),
+        Dump::HexUtf16 => hexdump(bytes, base, true),
+    }
+}
+
+/// Classic hexdump, lines start at multiples of 16 so that offsets read like the spec's.
+pub fn hexdump(bytes: &[u8], base: usize, utf16: bool) -> String {
+    let mut out = String::new();
+    let first = base - base % 16;
+    let end = base + bytes.len();
+    let byte = |offset: usize| (base..end).contains(&offset).then(|| bytes[offset - base]);
+
+    for line in (first..end).step_by(16) {
+        out += &format!("\n  {line:04x} ");
+        for offset in line..line + 16 {
+            if offset % 8 == 0 {
+                out.push(' ');
+            }
+            match byte(offset) {
+                Some(b) => out += &format!("{b:02x} "),
+                None => out += "   ",
+            }
+        }
+
+        out += " |";
+        for offset in line..line + 16 {
+            out.push(match byte(offset) {
+                Some(b) if b.is_ascii_graphic() || b == b' ' => b as char,
+                Some(_) => '.',
+                None => ' ',
+            });
+        }
+        out.push('|');
+
+        if utf16 {
+            out += "  ";
+            for offset in (line..line + 16).step_by(2) {
+                out.push(match (byte(offset), byte(offset + 1)) {
+                    (Some(lo), Some(hi)) => char::from_u32(u16::from_le_bytes([lo, hi]).into())
+                        // combining marks would merge with the previous character
+                        .filter(|c| !c.is_control() && !('\u{300}'..='\u{36f}').contains(c))
+                        .unwrap_or('.'),
+                    (None, None) => ' ',
+                    _ => '.',
+                });
+            }
+        }
+    }
+    out
+}
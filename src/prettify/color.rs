@@ -0,0 +1,249 @@
+//! Styling of the output: every colored piece of text goes through [`paint`] with the role it
+//! plays, the theme maps roles to ANSI SGR codes and whether they are used at all is decided at
+//! runtime, from `--color`, `NO_COLOR`, `CLICOLOR_FORCE` and whether stdout is a terminal.
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    io::{IsTerminal, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+/// Environment variable holding a theme, in the syntax of `--theme`.
+pub const THEME_VAR: &str = "SMBDUMP_COLORS";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum When {
+    /// Unless `NO_COLOR` is set, when stdout is a terminal or `CLICOLOR_FORCE` is set, and the
+    /// `color` feature is on.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// What a piece of text is, the theme decides what it looks like.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, EnumIter)]
+pub enum Role {
+    /// Packet directions.
+    Request,
+    Response,
+    External,
+    /// Non printable bytes of the dumps.
+    NullByte,
+    Byte,
+    /// IP/TCP header dumps and request/response matching.
+    Dim,
+    /// Session and tree of a message.
+    Context,
+    NoMessage,
+    ParseError,
+    BodyError,
+    /// Error responses, failed operations.
+    Failure,
+    Credit,
+    Lock,
+    Lease,
+    Notify,
+    Session,
+    Info,
+    Warning,
+    Error,
+    /// `--diff` sides and first divergence.
+    OnlyInA,
+    OnlyInB,
+    Highlight,
+    /// Fields of `--dump fields`, given in turn.
+    Field1,
+    Field2,
+    Field3,
+    Field4,
+    Field5,
+    Field6,
+}
+
+impl Role {
+    /// Name in a theme.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Request => "request",
+            Self::Response => "response",
+            Self::External => "external",
+            Self::NullByte => "null-byte",
+            Self::Byte => "byte",
+            Self::Dim => "dim",
+            Self::Context => "context",
+            Self::NoMessage => "no-message",
+            Self::ParseError => "parse-error",
+            Self::BodyError => "body-error",
+            Self::Failure => "failure",
+            Self::Credit => "credit",
+            Self::Lock => "lock",
+            Self::Lease => "lease",
+            Self::Notify => "notify",
+            Self::Session => "session",
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+            Self::OnlyInA => "only-in-a",
+            Self::OnlyInB => "only-in-b",
+            Self::Highlight => "highlight",
+            Self::Field1 => "field-1",
+            Self::Field2 => "field-2",
+            Self::Field3 => "field-3",
+            Self::Field4 => "field-4",
+            Self::Field5 => "field-5",
+            Self::Field6 => "field-6",
+        }
+    }
+
+    fn default_sgr(&self) -> &'static str {
+        match self {
+            Self::Request => "1;33",
+            Self::Response => "1;36",
+            Self::External => "1;30",
+            Self::NullByte => "1;35",
+            Self::Byte => "1;31",
+            Self::Dim => "2",
+            Self::Context => "1",
+            Self::NoMessage => "37;3;4",
+            Self::ParseError => "31;1;3;4",
+            Self::BodyError => "31;3",
+            Self::Failure => "31",
+            Self::Credit => "33",
+            Self::Lock => "31;1",
+            Self::Lease => "35",
+            Self::Notify => "34",
+            Self::Session => "32",
+            Self::Info => "36",
+            Self::Warning => "33",
+            Self::Error => "31;1",
+            Self::OnlyInA => "31",
+            Self::OnlyInB => "32",
+            Self::Highlight => "1",
+            Self::Field1 => "30;46",
+            Self::Field2 => "30;43",
+            Self::Field3 => "30;42",
+            Self::Field4 => "30;45",
+            Self::Field5 => "37;44",
+            Self::Field6 => "30;47",
+        }
+    }
+}
+
+/// SGR parameters of each role, an empty one leaves the text as is.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Theme(HashMap<Role, String>);
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self(
+            Role::iter()
+                .map(|role| (role, role.default_sgr().to_owned()))
+                .collect(),
+        )
+    }
+}
+
+impl Theme {
+    /// Overrides roles from `role=sgr` pairs separated by `:`, like `error=1;91:dim=90`.
+    pub fn apply(&mut self, spec: &str) -> Result<(), String> {
+        for pair in spec.split(':').filter(|pair| !pair.is_empty()) {
+            let (name, sgr) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("expected role=codes, got {pair:?}"))?;
+            let role = Role::iter()
+                .find(|role| role.name() == name)
+                .ok_or_else(|| format!("unknown role {name:?}"))?;
+            if !sgr.chars().all(|c| c.is_ascii_digit() || c == ';') {
+                return Err(format!("invalid codes {sgr:?} for {name}"));
+            }
+            self.0.insert(role, sgr.to_owned());
+        }
+        Ok(())
+    }
+
+    pub fn sgr(&self, role: Role) -> &str {
+        self.0.get(&role).map_or("", String::as_str)
+    }
+}
+
+fn env_set(name: &str) -> bool {
+    std::env::var_os(name).is_some_and(|value| !value.is_empty())
+}
+
+/// Decides whether to style the output and with what, `theme` is `--theme` which replaces the
+/// one from the environment. Returns what was wrong with the environment's theme, if anything.
+pub fn init(when: When, theme: Option<&str>) -> Result<(), String> {
+    let enabled = match when {
+        When::Always => true,
+        When::Never => false,
+        When::Auto if env_set("NO_COLOR") => false,
+        When::Auto if env_set("CLICOLOR_FORCE") => {
+            std::env::var("CLICOLOR_FORCE") != Ok("0".into())
+        }
+        When::Auto => cfg!(feature = "color") && std::io::stdout().is_terminal(),
+    };
+    ENABLED.store(enabled, Ordering::Relaxed);
+
+    let mut chosen = Theme::default();
+    let result = match theme {
+        Some(spec) => chosen.apply(spec),
+        None => match std::env::var(THEME_VAR) {
+            Ok(spec) => chosen
+                .apply(&spec)
+                .map_err(|err| format!("{THEME_VAR}: {err}")),
+            Err(_) => Ok(()),
+        },
+    };
+    if result.is_err() {
+        chosen = Theme::default();
+    }
+    let _ = THEME.set(chosen);
+    result
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// `text` styled as `role`, or as is when colors are off.
+pub fn paint(role: Role, text: impl Display) -> String {
+    if !enabled() {
+        return text.to_string();
+    }
+    let sgr = THEME.get_or_init(Theme::default).sgr(role);
+    if sgr.is_empty() {
+        text.to_string()
+    } else {
+        format!("\x1b[{sgr}m{text}\x1b[0m")
+    }
+}
+
+/// Prints a line, exiting quietly when stdout is closed (piped into `head`).
+pub fn print_line(line: &str) {
+    if let Err(err) = writeln!(std::io::stdout().lock(), "{line}") {
+        if err.kind() == std::io::ErrorKind::BrokenPipe {
+            std::process::exit(0);
+        }
+        panic!("failed printing to stdout: {err}");
+    }
+}
+
+/// `println!` for the output, so that a closed pipe ends the program quietly.
+macro_rules! say {
+    () => {
+        $crate::prettify::color::print_line("")
+    };
+    ($($arg:tt)*) => {
+        $crate::prettify::color::print_line(&format!($($arg)*))
+    };
+}
+pub(crate) use say;
@@ -1,5 +1,7 @@
 use std::{fmt::Debug, net::IpAddr};
 
+use super::color::{paint, Role};
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct Dynamic {
     client: IpAddr,
@@ -22,7 +24,7 @@ impl Dynamic {
     }
 }
 
-#[derive(Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
 pub enum Direction {
     REQUEST,
     RESPONSE,
@@ -30,21 +32,11 @@ pub enum Direction {
 }
 impl Debug for Direction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let name = match self {
-            Self::REQUEST => "REQUEST",
-            Self::RESPONSE => "RESPONSE",
-            Self::EXTERNAL => "EXTERNAL",
+        let (name, role) = match self {
+            Self::REQUEST => ("REQUEST", Role::Request),
+            Self::RESPONSE => ("RESPONSE", Role::Response),
+            Self::EXTERNAL => ("EXTERNAL", Role::External),
         };
-
-        if cfg!(not(feature = "color")) {
-            write!(f, "{name}")
-        } else {
-            let color = match self {
-                Self::REQUEST => "33",
-                Self::RESPONSE => "36",
-                Self::EXTERNAL => "30",
-            };
-            write!(f, "\x1b[1;{color}m{name}\x1b[0m")
-        }
+        write!(f, "{}", paint(role, name))
     }
 }
@@ -0,0 +1,81 @@
+use super::leases::millis;
+use crate::analysis::{
+    credits::{CreditEvent, CreditState},
+    ConnKey,
+};
+
+pub fn event_line(event: &CreditEvent) -> String {
+    match event {
+        CreditEvent::Exhausted { outstanding } => {
+            format!("CREDITS exhausted, client blocked with {outstanding} requests in flight")
+        }
+        CreditEvent::Replenished {
+            granted,
+            blocked,
+            since_frame,
+        } => format!(
+            "CREDITS {granted} granted, client was blocked for {} (since frame {since_frame})",
+            millis(*blocked)
+        ),
+        CreditEvent::Undergranted {
+            since_frame,
+            responses,
+            requested,
+            granted,
+        } => format!(
+            "CREDITS server granting less than requested since frame {since_frame}: \
+             {granted} of {requested} over {responses} responses"
+        ),
+        CreditEvent::OutOfWindow {
+            msg_id,
+            charge,
+            low,
+            high,
+            reused,
+        } => format!(
+            "CREDITS MessageId {msg_id} (charge {charge}) {}, window is [{low}, {high})",
+            if *reused {
+                "already used"
+            } else {
+                "not granted yet"
+            }
+        ),
+    }
+}
+
+pub fn state_line(conn: &ConnKey, state: &CreditState) -> String {
+    let mut line = format!(
+        "  {} -> {}: {} granted, {} consumed, {} available, window [{}, {}), {} max in flight",
+        conn.client,
+        conn.server,
+        state.granted,
+        state.consumed,
+        state.available(),
+        state.low,
+        state.high,
+        state.max_in_flight
+    );
+    if !state.synced {
+        line += " (joined mid-connection, estimated)";
+    }
+    if state.exhausted > 0 {
+        line += &format!(
+            "\n    ran out of credits {} times, blocked for {}",
+            state.exhausted,
+            millis(state.blocked)
+        );
+    }
+    if state.undergranted > 0 {
+        line += &format!(
+            "\n    undergranted for long periods {} times",
+            state.undergranted
+        );
+    }
+    if state.out_of_window > 0 {
+        line += &format!(
+            "\n    {} requests outside of the granted window",
+            state.out_of_window
+        );
+    }
+    line
+}
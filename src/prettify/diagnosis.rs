@@ -0,0 +1,126 @@
+use super::{
+    color::{paint, Role},
+    leases::millis,
+};
+use crate::analysis::{
+    diagnosis::{Diagnosis, Finding},
+    sessions::User,
+    Severity,
+};
+
+fn user(user: &Option<User>) -> String {
+    match user {
+        Some(user) => user.to_string(),
+        None => "?".to_owned(),
+    }
+}
+
+/// What went wrong, in a sentence.
+pub fn summary(finding: &Finding) -> String {
+    match finding {
+        Finding::NegotiateFailed { offered, status } => {
+            format!("dialect negotiation failed with {status:?}, client offered {offered:?}")
+        }
+        Finding::Smb1OnlyServer => "server only speaks SMB1".to_owned(),
+        Finding::SigningNotSupported => {
+            "client requires signing but the server doesn't support it".to_owned()
+        }
+        Finding::UnsignedRequests { count } => {
+            format!("server requires signing but {count} requests were sent unsigned")
+        }
+        Finding::ValidateNegotiateFailed {
+            status: Some(status),
+        } => {
+            format!("FSCTL_VALIDATE_NEGOTIATE_INFO failed with {status:?}")
+        }
+        Finding::ValidateNegotiateFailed { status: None } => {
+            "connection torn down during FSCTL_VALIDATE_NEGOTIATE_INFO".to_owned()
+        }
+        Finding::LogonFailed { user: u, status } => {
+            format!("logon of {} failed with {status:?}", user(u))
+        }
+        Finding::GuestBlocked => "server only granted a guest session".to_owned(),
+        Finding::BadNetworkName { path } => format!("share {path} doesn't exist"),
+        Finding::TreeAccessDenied { path, user: u } => {
+            format!("{} denied access to share {path}", user(u))
+        }
+        Finding::CreateAccessDenied { path, user: u } => {
+            format!("{} denied access to \\{path}", user(u))
+        }
+        Finding::ResetAfterNegotiate { answered: true } => {
+            "server reset the connection right after negotiating".to_owned()
+        }
+        Finding::ResetAfterNegotiate { answered: false } => {
+            "server reset the connection instead of answering NEGOTIATE".to_owned()
+        }
+        Finding::NoResponse {
+            opcode,
+            msg_id,
+            waited,
+        } => format!(
+            "{opcode:?} MessageId {msg_id} got no response after {}",
+            millis(*waited)
+        ),
+    }
+}
+
+/// What Windows makes of it and where to look.
+pub fn hint(finding: &Finding) -> &'static str {
+    match finding {
+        Finding::NegotiateFailed { .. } => {
+            "no dialect in common, check the min/max protocol settings on both ends"
+        }
+        Finding::Smb1OnlyServer => {
+            "recent Windows don't speak SMB1 anymore, enable SMB2 on the server (or SMB1 on the \
+             client, at your own risk)"
+        }
+        Finding::SigningNotSupported | Finding::UnsignedRequests { .. } => {
+            "check RequireSecuritySignature on the client and `server signing` on the server"
+        }
+        Finding::ValidateNegotiateFailed { .. } => {
+            "something between client and server altered the NEGOTIATE, or the server \
+             implements it wrong; Windows reports \"The specified network name is no longer \
+             available\""
+        }
+        Finding::LogonFailed { .. } => {
+            "Windows reports \"The user name or password is incorrect\" or an account \
+             restriction, check the account and the credentials manager"
+        }
+        Finding::GuestBlocked => {
+            "insecure guest logons are blocked by default (AllowInsecureGuestAuth), the account \
+             was most likely not recognized by the server"
+        }
+        Finding::BadNetworkName { .. } => {
+            "Windows reports \"The network name cannot be found\" (0x80070043), check the share \
+             name"
+        }
+        Finding::TreeAccessDenied { .. } | Finding::CreateAccessDenied { .. } => {
+            "check the share permissions and the NTFS/POSIX ACLs"
+        }
+        Finding::ResetAfterNegotiate { .. } => {
+            "the server refuses every dialect offered, or a firewall cuts SMB"
+        }
+        Finding::NoResponse { .. } => "server hang or network drop, Windows times out after 60s",
+    }
+}
+
+pub fn severity(severity: Severity) -> String {
+    match severity {
+        Severity::Info => paint(Role::Info, "INFO"),
+        Severity::Warning => paint(Role::Warning, "WARNING"),
+        Severity::Error => paint(Role::Error, "ERROR"),
+    }
+}
+
+pub fn diagnosis_lines(diagnosis: &Diagnosis) -> String {
+    let severity = severity(diagnosis.finding.severity());
+    let frames: Vec<_> = diagnosis.frames.iter().map(usize::to_string).collect();
+    format!(
+        "  [{severity}] {} ({} -> {}, frames {})\n    {}",
+        summary(&diagnosis.finding),
+        diagnosis.conn.client,
+        diagnosis.conn.server,
+        frames.join(", "),
+        hint(&diagnosis.finding)
+    )
+}
@@ -0,0 +1,65 @@
+use super::color::{paint, Role};
+use crate::analysis::diff::{Divergence, Step};
+
+fn step_line(step: &Step) -> String {
+    let mut line = format!("frame {} {:?}", step.frame, step.opcode);
+    for name in ["path", "name", "ctl code", "status"] {
+        if let Some((_, value)) = step.fields.iter().find(|(n, _)| *n == name) {
+            line += &format!(" {value}");
+        }
+    }
+    line
+}
+
+/// Report of the divergences between captures `a` and `b`, the first one stands out as it
+/// is usually the cause of the others.
+pub fn report(a: &str, b: &str, steps: (usize, usize), divergences: &[Divergence]) -> String {
+    let mut out = format!(
+        "comparing {a} ({} operations) with {b} ({} operations)",
+        steps.0, steps.1
+    );
+    if divergences.is_empty() {
+        out += "\nno difference";
+        return out;
+    }
+    for (i, divergence) in divergences.iter().enumerate() {
+        // the first divergence is usually the cause, the others consequences
+        let highlight = |line: String| {
+            if i == 0 {
+                paint(Role::Highlight, line)
+            } else {
+                line
+            }
+        };
+        match divergence {
+            Divergence::Differs {
+                a: sa,
+                b: sb,
+                fields,
+            } => {
+                out += "\n";
+                out += &highlight(format!(
+                    "  {:?} differs (frame {} in {a}, frame {} in {b})",
+                    sa.opcode, sa.frame, sb.frame
+                ));
+                let width = fields.iter().map(|(n, _, _)| n.len()).max().unwrap_or(0);
+                for (name, va, vb) in fields {
+                    out += &format!(
+                        "\n    {name:<width$}  {} | {}",
+                        paint(Role::OnlyInA, va.as_deref().unwrap_or("-")),
+                        paint(Role::OnlyInB, vb.as_deref().unwrap_or("-"))
+                    );
+                }
+            }
+            Divergence::OnlyInA(step) => {
+                let line = format!("  only in {a}: {}", step_line(step));
+                out += &format!("\n{}", highlight(paint(Role::OnlyInA, line)));
+            }
+            Divergence::OnlyInB(step) => {
+                let line = format!("  only in {b}: {}", step_line(step));
+                out += &format!("\n{}", highlight(paint(Role::OnlyInB, line)));
+            }
+        }
+    }
+    out
+}
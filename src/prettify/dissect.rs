@@ -0,0 +1,121 @@
+//! Packet bytes annotated with the fields they belong to, like Wireshark's packet bytes pane:
+//! every field the parsers decode gets a color in the hexdump and a line in the legend under it
+//! (offset, length, name and first bytes). Bytes no field covers (padding, reserved bits the
+//! parsers skip) are left plain.
+use super::color::{paint, Role};
+use crate::smb::{body::Body, reader::Span, SMBMsg};
+
+/// Colors given to the fields in turn, so that neighbours differ.
+const PALETTE: [Role; 6] = [
+    Role::Field1,
+    Role::Field2,
+    Role::Field3,
+    Role::Field4,
+    Role::Field5,
+    Role::Field6,
+];
+
+/// Bytes of a field shown in the legend, longer ones are cut.
+const LEGEND_BYTES: usize = 8;
+
+/// Spans of a TCP payload (NetBIOS length, then the header and body of every message) relative
+/// to its start. `msgs` are the messages `SMBMsg::parse_packet` found in it.
+pub fn packet_spans(data: &[u8], msgs: &[SMBMsg]) -> Vec<Span> {
+    let shifted = |spans: Vec<Span>, by: usize| {
+        spans.into_iter().map(move |span| Span {
+            name: span.name,
+            range: span.range.start + by..span.range.end + by,
+        })
+    };
+
+    let mut spans = vec![];
+    let mut msgs = msgs.iter();
+    let mut pos = 0;
+    while let Some(head) = data.get(pos..pos + 4) {
+        spans.push(Span {
+            name: "netbios_length",
+            range: pos..pos + 4,
+        });
+        let len = u32::from_be_bytes(head.try_into().expect("4 bytes")) as usize;
+        // a compound chain follows, each header at NextCommand bytes from the previous one
+        let mut start = pos + 4;
+        for msg in msgs.by_ref() {
+            spans.extend(shifted(msg.header.spans(), start));
+            spans.extend(shifted(Body::spans(&msg.header, &msg.payload), start));
+            match msg.header.chain_offset {
+                0 => break,
+                next => start += next as usize,
+            }
+        }
+        pos += 4 + len;
+    }
+    spans
+}
+
+/// Hexdump of `data` with the bytes of each span colored, then the legend. Every line starts
+/// with a line break, like the other dumps.
+pub fn annotated(data: &[u8], spans: &[Span]) -> String {
+    // the field each byte belongs to, the last recorded one if buffers overlap
+    let mut owner = vec![None; data.len()];
+    for (i, span) in spans.iter().enumerate() {
+        for byte in owner.iter_mut().take(span.range.end).skip(span.range.start) {
+            *byte = Some(i);
+        }
+    }
+    let role = |i: usize| PALETTE[i % PALETTE.len()];
+
+    let mut out = String::new();
+    for line in (0..data.len()).step_by(16) {
+        let end = (line + 16).min(data.len());
+        let mut hex = String::new();
+        let mut ascii = String::new();
+        // runs of bytes of the same field, cut at the middle gap, are painted at once
+        let mut run = line;
+        while run < end {
+            let mut stop = run + 1;
+            while stop < end && owner[stop] == owner[run] && stop % 8 != 0 {
+                stop += 1;
+            }
+            let bytes = &data[run..stop];
+            let text = bytes.iter().map(|b| format!("{b:02x}")).collect::<Vec<_>>();
+            let chars = bytes
+                .iter()
+                .map(|&b| match b {
+                    b if b.is_ascii_graphic() || b == b' ' => b as char,
+                    _ => '.',
+                })
+                .collect::<String>();
+            let (text, chars) = match owner[run] {
+                Some(i) => (paint(role(i), text.join(" ")), paint(role(i), chars)),
+                None => (text.join(" "), chars),
+            };
+            hex += &format!("{}{text} ", if run % 8 == 0 { " " } else { "" });
+            ascii += &chars;
+            run = stop;
+        }
+        // missing bytes of the last line keep the gutter aligned
+        let missing = line + 16 - end;
+        let pad = missing * 3 + usize::from(end <= line + 8);
+        out += &format!("\n  {line:04x} {hex}{} |{ascii}|", " ".repeat(pad));
+    }
+
+    for (i, span) in spans.iter().enumerate() {
+        let bytes = data.get(span.range.clone()).unwrap_or_default();
+        let mut shown = bytes
+            .iter()
+            .take(LEGEND_BYTES)
+            .map(|b| format!("{b:02x}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        if bytes.len() > LEGEND_BYTES {
+            shown += " ...";
+        }
+        out += &format!(
+            "\n  {:04x} {:>5}  {} {shown}",
+            span.range.start,
+            span.range.len(),
+            paint(role(i), format!("{:24}", span.name)),
+        );
+    }
+    out
+}
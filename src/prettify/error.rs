@@ -0,0 +1,35 @@
+use crate::smb::{
+    body::error::{ErrorDetail, ErrorResponse},
+    status::NtStatus,
+};
+
+/// Status of a failed response and the structured reason the server gave, if any.
+pub fn error_line(status: NtStatus, err: &ErrorResponse) -> String {
+    let mut line = format!("{status:?}");
+    for detail in &err.details {
+        line += &match detail {
+            ErrorDetail::Symlink(link) => {
+                format!(
+                ": stopped on {} symlink to {:?} (print name {:?}), {} bytes of the path unparsed",
+                if link.relative { "relative" } else { "absolute" },
+                link.substitute_name,
+                link.print_name,
+                link.unparsed_path_length
+            )
+            }
+            ErrorDetail::ShareRedirect(redirect) => {
+                let targets: Vec<String> = redirect.targets.iter().map(|t| t.to_string()).collect();
+                format!(
+                    ": share {:?} moved to [{}]",
+                    redirect.resource_name,
+                    targets.join(", ")
+                )
+            }
+            ErrorDetail::BufferTooSmall(size) => format!(": {size} bytes required"),
+            ErrorDetail::Raw { error_id, data } => {
+                format!(": error id 0x{error_id:08X} data {data:02X?}")
+            }
+        };
+    }
+    line
+}
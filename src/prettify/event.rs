@@ -0,0 +1,40 @@
+use super::{
+    color::{paint, Role},
+    leases::millis,
+};
+use crate::analysis::{matcher::Match, Event};
+
+/// Line to print under a message for an event, if it's worth printing.
+pub fn event_line(event: &Event) -> Option<String> {
+    Some(match event {
+        Event::Match(m) => paint(Role::Dim, match_line(m)?),
+        Event::Credit(event) => paint(Role::Credit, super::credits::event_line(event)),
+        Event::Pipe(event) => super::rpc::event_line(event),
+        Event::Lock(event) => paint(Role::Lock, super::locks::event_line(event)),
+        Event::Lease(event) => paint(Role::Lease, super::leases::event_line(event)),
+        Event::Notify(event) => paint(Role::Notify, super::notify::event_line(event)),
+        Event::Lint(violation) => super::lint::violation_line(violation),
+        Event::Session(event) => paint(Role::Session, super::sessions::event_line(event)),
+        // printed on their own by `--output oneline`
+        Event::Operation(_) => return None,
+    })
+}
+
+pub fn match_line(m: &Match) -> Option<String> {
+    Some(match m {
+        Match::Request | Match::Unsolicited => return None,
+        Match::Interim { request } => format!("interim response to frame {}", request.frame),
+        Match::Response { request, elapsed } => {
+            let mut line = format!(
+                "response to frame {} in {}",
+                request.frame,
+                millis(*elapsed)
+            );
+            if let Some((frame, async_id)) = request.interim {
+                line += &format!(" (went async at frame {frame}, AsyncId {async_id})");
+            }
+            line
+        }
+        Match::Orphan => "response to a request that wasn't captured".to_owned(),
+    })
+}
@@ -0,0 +1,260 @@
+//! Self-contained HTML report (`--output html`): the conversation as a table with a filter box
+//! per column, each message's decoded header and body as a collapsible tree and its bytes as a
+//! hexdump, then the status codes seen with what they mean and the end of capture sections.
+//! Styles and scripts are inline so that the file can be attached to a ticket and opened offline.
+use std::collections::BTreeMap;
+
+use super::{
+    byte::hexdump,
+    json::{self, Json},
+    report,
+};
+use crate::{
+    analysis::{Analyzer, Frame},
+    smb::{
+        body::{Body, Error},
+        status::NtStatus,
+        SMBHeader,
+    },
+    tcpdump::header::Header,
+};
+
+/// End of capture sections that are findings rather than statistics.
+const FINDINGS: [&str; 2] = ["diagnosis", "conformance"];
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 1em 2em; color: #222; }
+h1 { font-size: 1.4em; }
+h2 { font-size: 1.2em; margin-top: 2em; border-bottom: 1px solid #ccc; }
+table { border-collapse: collapse; font-size: 0.9em; }
+th, td { border: 1px solid #ddd; padding: 2px 6px; text-align: left; vertical-align: top; }
+thead th { background: #f0f0f0; position: sticky; top: 0; }
+thead input { width: 100%; box-sizing: border-box; font-size: 0.9em; }
+tr.response td.command { color: #06c; }
+tr.request td.command { color: #960; }
+td.error, .error { color: #c00; font-weight: bold; }
+td.warning { color: #b60; }
+pre { margin: 0.3em 0; font-size: 0.85em; }
+ul.tree { list-style: none; margin: 0; padding-left: 1.2em; font-family: monospace; }
+ul.tree .key { color: #555; }
+details summary { cursor: pointer; color: #06c; }
+.notes { color: #555; font-size: 0.85em; }
+";
+
+const SCRIPT: &str = "
+const inputs = [...document.querySelectorAll('#conversation thead input')];
+const filter = () => {
+  const active = inputs
+    .map(input => [Number(input.dataset.col), input.value.toLowerCase()])
+    .filter(([, text]) => text);
+  for (const row of document.querySelectorAll('#conversation tbody tr')) {
+    row.hidden = !active.every(([col, text]) =>
+      row.cells[col].textContent.toLowerCase().includes(text));
+  }
+};
+inputs.forEach(input => input.addEventListener('input', filter));
+const toggle = open => document
+  .querySelectorAll('#conversation details')
+  .forEach(details => details.open = open);
+";
+
+/// Columns of the conversation table, the last one (decoded tree and bytes) isn't filterable.
+const COLUMNS: [&str; 9] = [
+    "frame", "time", "from", "to", "command", "status", "summary", "context", "details",
+];
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out += "&amp;",
+            '<' => out += "&lt;",
+            '>' => out += "&gt;",
+            '"' => out += "&quot;",
+            '\'' => out += "&#39;",
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Nested lists for objects and arrays, the ones deeper than the top level folded.
+fn tree(value: &Json) -> String {
+    let item = |key: String, value: &Json| match value {
+        Json::Object(fields) if !fields.is_empty() => format!(
+            "<li><details><summary class=\"key\">{key}</summary>{}</details></li>",
+            tree(value)
+        ),
+        Json::Array(items) if !items.is_empty() => format!(
+            "<li><details><summary class=\"key\">{key} [{}]</summary>{}</details></li>",
+            items.len(),
+            tree(value)
+        ),
+        _ => format!(
+            "<li><span class=\"key\">{key}:</span> {}</li>",
+            escape(&value.to_string())
+        ),
+    };
+    let items: String = match value {
+        Json::Object(fields) => fields
+            .iter()
+            .map(|(key, value)| item(escape(key), value))
+            .collect(),
+        Json::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(i, value)| item(i.to_string(), value))
+            .collect(),
+        value => return escape(&value.to_string()),
+    };
+    format!("<ul class=\"tree\">{items}</ul>")
+}
+
+/// Messages gathered while reading the capture, written out with the analysis at the end.
+#[derive(Default)]
+pub struct Report {
+    rows: Vec<String>,
+    statuses: BTreeMap<NtStatus, usize>,
+}
+
+impl Report {
+    /// Adds a message to the conversation, `summary` being its line in the operations view and
+    /// `payload` its body bytes.
+    #[allow(clippy::too_many_arguments)]
+    pub fn message(
+        &mut self,
+        frame: &Frame,
+        tcp: &Header,
+        header: &SMBHeader,
+        body: Result<&Body, &Error>,
+        payload: &[u8],
+        summary: &str,
+        context: &str,
+        events: &[String],
+    ) {
+        let status = header.is_response().then(|| header.status());
+        if let Some(status) = status {
+            *self.statuses.entry(status).or_default() += 1;
+        }
+        let status_class = match status {
+            Some(status) if status.is_error() => "error",
+            Some(status) if !status.is_success() => "warning",
+            _ => "",
+        };
+
+        let mut decoded = vec![("smb2", json::header(header))];
+        match body {
+            Ok(body) => decoded.push(("body", json::body(body))),
+            Err(err) => decoded.push(("body_error", format!("{err:?}").into())),
+        }
+        let mut details = format!(
+            "<details><summary>decoded</summary>{}</details>\
+             <details><summary>bytes</summary><pre>{}</pre></details>",
+            tree(&Json::Object(decoded)),
+            escape(hexdump(payload, header.hlen.into(), false).trim_start_matches('\n')),
+        );
+        if !events.is_empty() {
+            details += &format!("<div class=\"notes\">{}</div>", escape(&events.join("\n")));
+        }
+
+        let cells = [
+            (frame.number.to_string(), ""),
+            (frame.time.format("%H:%M:%S%.6f").to_string(), ""),
+            (tcp.src.to_string(), ""),
+            (tcp.dst.to_string(), ""),
+            (header.opcode.spec_name().to_owned(), "command"),
+            (
+                status.map(|s| format!("{s:?}")).unwrap_or_default(),
+                status_class,
+            ),
+            (summary.to_owned(), ""),
+            (context.to_owned(), ""),
+        ];
+        let mut row = format!(
+            "<tr class=\"{}\">",
+            if header.is_response() {
+                "response"
+            } else {
+                "request"
+            }
+        );
+        for (text, class) in cells {
+            row += &format!("<td class=\"{class}\">{}</td>", escape(&text));
+        }
+        row += &format!("<td>{details}</td></tr>");
+        self.rows.push(row);
+    }
+
+    fn statuses(&self) -> String {
+        let mut out = String::from(
+            "<table><thead><tr><th>status</th><th>code</th><th>responses</th>\
+             <th>meaning</th></tr></thead><tbody>",
+        );
+        for (status, count) in &self.statuses {
+            let class = if status.is_error() {
+                " class=\"error\""
+            } else {
+                ""
+            };
+            out += &format!(
+                "<tr><td{class}>{}</td><td>0x{:08X}</td><td>{count}</td><td>{}</td></tr>",
+                escape(&format!("{status:?}")),
+                status.0,
+                escape(status.description().unwrap_or_default()),
+            );
+        }
+        out + "</tbody></table>"
+    }
+
+    /// The whole document, `inputs` being the captures it was made from.
+    pub fn render(
+        &self,
+        analyzer: &Analyzer,
+        options: &report::Options,
+        inputs: &[String],
+    ) -> String {
+        let sections = report::sections(analyzer, options);
+        let section = |(title, lines): &(&str, String)| {
+            format!("<h3>{}</h3><pre>{}</pre>", escape(title), escape(lines))
+        };
+        let findings: String = sections
+            .iter()
+            .filter(|(title, _)| FINDINGS.contains(title))
+            .map(section)
+            .collect();
+        let statistics: String = sections
+            .iter()
+            .filter(|(title, _)| !FINDINGS.contains(title))
+            .map(section)
+            .collect();
+
+        let head: String = COLUMNS.iter().map(|c| format!("<th>{c}</th>")).collect();
+        let filters: String = (0..COLUMNS.len() - 1)
+            .map(|col| format!("<th><input data-col=\"{col}\" placeholder=\"filter\"></th>"))
+            .chain(["<th><button onclick=\"toggle(true)\">expand</button> \
+                     <button onclick=\"toggle(false)\">collapse</button></th>"
+                .to_owned()])
+            .collect();
+
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>smbdump {inputs}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+             <h1>smbdump {inputs}</h1>\n<p>{count} messages</p>\n\
+             <h2>findings</h2>\n{findings}\n\
+             <h2>conversation</h2>\n<table id=\"conversation\"><thead><tr>{head}</tr><tr>{filters}</tr></thead>\n\
+             <tbody>\n{rows}\n</tbody></table>\n\
+             <h2>status codes</h2>\n{statuses}\n\
+             <h2>statistics</h2>\n{statistics}\n\
+             <script>{SCRIPT}</script>\n</body>\n</html>",
+            inputs = escape(&inputs.join(", ")),
+            count = self.rows.len(),
+            findings = if findings.is_empty() {
+                "<p>nothing to report</p>".to_owned()
+            } else {
+                findings
+            },
+            rows = self.rows.join("\n"),
+            statuses = self.statuses(),
+        )
+    }
+}
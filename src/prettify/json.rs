@@ -0,0 +1,750 @@
+//! JSON lines output (`--output json`): one object per SMB2 message, for jq and scripts.
+//!
+//! Schema version 3. Fields are only ever added within a version, anything that renames, removes
+//! or changes the type of a field bumps `schema`. Numbers are unsigned integers, except the
+//! 128 bits `smb2.signature` and the 64 bits identifiers (`message_id`, `session_id`, `async_id`,
+//! `previous_session_id`), which are hex strings as JSON numbers lose precision above 2^53.
+//! File times (`creation_time`, `last_access_time`, `last_write_time`, `change_time`) are UTC
+//! "YYYY-MM-DDTHH:MM:SS.fffffffZ" strings, null when 0 (not set, or not to be changed by a
+//! SET_INFO) and "0xffffffffffffffff"/"0xfffffffffffffffe" for the SET_INFO values that stop and
+//! resume their automatic updates. Version 2 had the file times as numbers, version 1 the
+//! identifiers too.
+//!
+//! ```text
+//! schema       3
+//! frame        frame number, as printed in text mode
+//! time         capture time of day, "HH:MM:SS.ffffff"
+//! conn         {id, client, server}, id numbers the connections in order of appearance
+//! direction    "client_to_server" or "server_to_client"
+//! tcp          {src, dst, seq, ack, win, flags, len}, seq and ack are null when not captured,
+//!              flags as printed by tcpdump ("P.")
+//! smb2         the SMB2 header:
+//!                command       {code, name}
+//!                status        {code, name}, name is null for unknown codes
+//!                flags         {value, names}, names from MS-SMB2 (SERVER_TO_REDIR, SIGNED...)
+//!                credit_charge, credits, next_command, message_id, session_id
+//!                async_id      for async messages, which have no process_id/tree_id
+//!                process_id, tree_id
+//!                signature
+//! body         decoded command, {type, ...}, type is the name of the structure
+//!              ("CreateRequest", "Error"...), null when the command has no decoder
+//! body_error   why the body couldn't be decoded, only present then
+//! events       what the analysis had to say about the message, as printed in text mode
+//! ```
+//!
+//! Flag sets in bodies are `{value, names}` like the header flags, FileIds are
+//! "persistent:volatile" in hex and GUIDs in their usual text form.
+use chrono::DateTime;
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{
+    analysis::{ConnKey, Frame},
+    ntlmssp::{Message, Token},
+    prettify::conn::Direction,
+    smb::{
+        body::{
+            create::CreateContext, error::ErrorDetail, ioctl::ctl_code_name,
+            negotiate::NegotiateContext, notify::NotifyAction, setinfo::SetInfo, Body, Error,
+        },
+        flags::Flags,
+        SMBHeader,
+    },
+    tcpdump::header::Header,
+};
+
+pub const SCHEMA_VERSION: u64 = 3;
+
+/// A 64 bits identifier, as a 16 digits hex string.
+fn id(value: u64) -> Json {
+    format!("{value:016x}").into()
+}
+
+/// A FILETIME, 100 ns intervals since 1601, see the module doc for the special values.
+fn filetime(value: u64) -> Json {
+    const SECONDS_TO_UNIX_EPOCH: i64 = 11_644_473_600;
+    if value == 0 {
+        return Json::Null;
+    }
+    let time = (value < u64::MAX - 1)
+        .then(|| {
+            let seconds = (value / 10_000_000) as i64 - SECONDS_TO_UNIX_EPOCH;
+            DateTime::from_timestamp(seconds, 0)
+        })
+        .flatten();
+    match time {
+        Some(time) => format!(
+            "{}.{:07}Z",
+            time.format("%Y-%m-%dT%H:%M:%S"),
+            value % 10_000_000
+        )
+        .into(),
+        None => format!("0x{value:016x}").into(),
+    }
+}
+
+/// Just enough of a JSON document model to write one, keys keep their insertion order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(u64),
+    Text(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Null => write!(f, "null"),
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Number(n) => write!(f, "{n}"),
+            Self::Text(s) => {
+                write!(f, "\"")?;
+                for c in s.chars() {
+                    match c {
+                        '"' => write!(f, "\\\"")?,
+                        '\\' => write!(f, "\\\\")?,
+                        '\n' => write!(f, "\\n")?,
+                        '\r' => write!(f, "\\r")?,
+                        '\t' => write!(f, "\\t")?,
+                        c if (c as u32) < 0x20 || c == '\x7f' => write!(f, "\\u{:04x}", c as u32)?,
+                        c => write!(f, "{c}")?,
+                    }
+                }
+                write!(f, "\"")
+            }
+            Self::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                write!(f, "]")
+            }
+            Self::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}:{value}", Self::Text((*key).to_owned()))?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+macro_rules! json_number {
+    ($($t:ty),*) => {
+        $(impl From<$t> for Json {
+            fn from(n: $t) -> Self {
+                Self::Number(n as u64)
+            }
+        })*
+    };
+}
+json_number!(u8, u16, u32, u64, usize);
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Self {
+        Self::Bool(b)
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Self {
+        Self::Text(s)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self {
+        Self::Text(s.to_owned())
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(items: Vec<T>) -> Self {
+        Self::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+/// `{"type": kind, ...fields}`
+fn typed(kind: &'static str, fields: Vec<(&'static str, Json)>) -> Json {
+    let mut object = vec![("type", kind.into())];
+    object.extend(fields);
+    Json::Object(object)
+}
+
+fn flags<F: bitflags::Flags>(flags: &F) -> Json
+where
+    F::Bits: Into<u64>,
+{
+    let names: Vec<&str> = flags.iter_names().map(|(name, _)| name).collect();
+    Json::Object(vec![
+        ("value", Json::Number(flags.bits().into())),
+        ("names", names.into()),
+    ])
+}
+
+fn header_flags(value: &Flags) -> Json {
+    let names: Vec<&str> = [
+        (Flags::FlagsServer2Redir, "SERVER_TO_REDIR"),
+        (Flags::FlagsAsyncCommand, "ASYNC_COMMAND"),
+        (Flags::FlagsRelatedOps, "RELATED_OPERATIONS"),
+        (Flags::FlagsSigned, "SIGNED"),
+        (Flags::FlagsDfsOps, "DFS_OPERATIONS"),
+        (Flags::FlagsRelayOps, "REPLAY_OPERATION"),
+    ]
+    .into_iter()
+    .filter(|(flag, _)| value.contains(flag.clone()))
+    .map(|(_, name)| name)
+    .collect();
+    Json::Object(vec![
+        ("value", value.bits().into()),
+        ("names", names.into()),
+    ])
+}
+
+pub fn header(header: &SMBHeader) -> Json {
+    let mut fields = vec![
+        (
+            "command",
+            Json::Object(vec![
+                ("code", (header.opcode as u16).into()),
+                ("name", format!("{:?}", header.opcode).into()),
+            ]),
+        ),
+        (
+            "status",
+            Json::Object(vec![
+                ("code", header.nt_status.into()),
+                ("name", header.status().name().into()),
+            ]),
+        ),
+        ("flags", header_flags(&header.flags)),
+        ("credit_charge", header.cred_charge.into()),
+        ("credits", header.cred_req_res.into()),
+        ("next_command", header.chain_offset.into()),
+        ("message_id", id(header.cmd_seq)),
+        ("session_id", id(header.uid)),
+    ];
+    if header.is_async() {
+        // the AsyncId takes the place of the ProcessId and TreeId
+        fields.push((
+            "async_id",
+            id(u64::from(header.tid) << 32 | u64::from(header.pid)),
+        ));
+    } else {
+        fields.push(("process_id", header.pid.into()));
+        fields.push(("tree_id", header.tid.into()));
+    }
+    fields.push(("signature", format!("{:032x}", header.signature).into()));
+    Json::Object(fields)
+}
+
+fn auth(blob: &[u8]) -> Json {
+    let (mechanism, ntlmssp) = match Token::find(blob) {
+        Token::Ntlmssp(message) => {
+            let message = match message {
+                Message::Negotiate => typed("negotiate", vec![]),
+                Message::Challenge { target_name } => {
+                    typed("challenge", vec![("target_name", target_name.into())])
+                }
+                Message::Authenticate {
+                    domain,
+                    user,
+                    workstation,
+                    anonymous,
+                } => typed(
+                    "authenticate",
+                    vec![
+                        ("domain", domain.into()),
+                        ("user", user.into()),
+                        ("workstation", workstation.into()),
+                        ("anonymous", anonymous.into()),
+                    ],
+                ),
+                Message::Unknown(kind) => typed("unknown", vec![("message_type", kind.into())]),
+            };
+            ("ntlmssp", message)
+        }
+        Token::Kerberos => ("kerberos", Json::Null),
+        Token::Empty => ("none", Json::Null),
+        Token::Other => ("other", Json::Null),
+    };
+    Json::Object(vec![
+        ("mechanism", mechanism.into()),
+        ("ntlmssp", ntlmssp),
+        ("length", blob.len().into()),
+    ])
+}
+
+fn negotiate_contexts(contexts: &[NegotiateContext]) -> Json {
+    Json::Array(
+        contexts
+            .iter()
+            .map(|c| {
+                Json::Object(vec![
+                    ("type", c.context_type.into()),
+                    ("summary", format!("{c:?}").into()),
+                    ("ids", c.ids().into()),
+                    ("length", c.data.len().into()),
+                ])
+            })
+            .collect(),
+    )
+}
+
+fn create_contexts(contexts: &[CreateContext]) -> Json {
+    Json::Array(
+        contexts
+            .iter()
+            .map(|c| {
+                Json::Object(vec![
+                    ("name", c.name.as_str().into()),
+                    ("length", c.data.len().into()),
+                ])
+            })
+            .collect(),
+    )
+}
+
+fn ctl_code(code: u32) -> Json {
+    Json::Object(vec![
+        ("code", code.into()),
+        ("name", ctl_code_name(code).into()),
+    ])
+}
+
+fn notify_action(action: &NotifyAction) -> Json {
+    match action {
+        NotifyAction::Unknown(code) => format!("0x{code:08x}").into(),
+        action => format!("{action:?}").into(),
+    }
+}
+
+fn set_info(info: &SetInfo) -> Json {
+    match info {
+        SetInfo::Basic {
+            creation_time,
+            last_access_time,
+            last_write_time,
+            change_time,
+            file_attributes,
+        } => typed(
+            "basic",
+            vec![
+                ("creation_time", filetime(*creation_time)),
+                ("last_access_time", filetime(*last_access_time)),
+                ("last_write_time", filetime(*last_write_time)),
+                ("change_time", filetime(*change_time)),
+                ("file_attributes", (*file_attributes).into()),
+            ],
+        ),
+        SetInfo::Rename {
+            replace_if_exists,
+            name,
+        } => typed(
+            "rename",
+            vec![
+                ("replace_if_exists", (*replace_if_exists).into()),
+                ("name", name.as_str().into()),
+            ],
+        ),
+        SetInfo::Link {
+            replace_if_exists,
+            name,
+        } => typed(
+            "link",
+            vec![
+                ("replace_if_exists", (*replace_if_exists).into()),
+                ("name", name.as_str().into()),
+            ],
+        ),
+        SetInfo::Disposition { delete } => typed("disposition", vec![("delete", (*delete).into())]),
+        SetInfo::EndOfFile(size) => typed("end_of_file", vec![("size", (*size).into())]),
+        SetInfo::Allocation(size) => typed("allocation", vec![("size", (*size).into())]),
+        SetInfo::Security { len } => typed("security", vec![("length", (*len).into())]),
+        SetInfo::Other(len) => typed("other", vec![("length", (*len).into())]),
+    }
+}
+
+fn error_detail(detail: &ErrorDetail) -> Json {
+    match detail {
+        ErrorDetail::Symlink(link) => typed(
+            "symlink",
+            vec![
+                ("unparsed_path_length", link.unparsed_path_length.into()),
+                ("substitute_name", link.substitute_name.as_str().into()),
+                ("print_name", link.print_name.as_str().into()),
+                ("relative", link.relative.into()),
+            ],
+        ),
+        ErrorDetail::ShareRedirect(redirect) => typed(
+            "share_redirect",
+            vec![
+                ("resource_name", redirect.resource_name.as_str().into()),
+                (
+                    "targets",
+                    redirect
+                        .targets
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .into(),
+                ),
+            ],
+        ),
+        ErrorDetail::BufferTooSmall(size) => {
+            typed("buffer_too_small", vec![("required", (*size).into())])
+        }
+        ErrorDetail::Raw { error_id, data } => typed(
+            "raw",
+            vec![
+                ("error_id", (*error_id).into()),
+                ("length", data.len().into()),
+            ],
+        ),
+    }
+}
+
+pub fn body(body: &Body) -> Json {
+    match body {
+        Body::NegotiateRequest(req) => typed(
+            "NegotiateRequest",
+            vec![
+                ("security_mode", flags(&req.security_mode)),
+                ("capabilities", flags(&req.capabilities)),
+                ("client_guid", req.client_guid.to_string().into()),
+                (
+                    "dialects",
+                    req.dialects.iter().map(|d| d.0).collect::<Vec<_>>().into(),
+                ),
+                ("contexts", negotiate_contexts(&req.contexts)),
+            ],
+        ),
+        Body::NegotiateResponse(res) => typed(
+            "NegotiateResponse",
+            vec![
+                ("security_mode", flags(&res.security_mode)),
+                ("dialect", res.dialect.0.into()),
+                ("server_guid", res.server_guid.to_string().into()),
+                ("capabilities", flags(&res.capabilities)),
+                ("max_transact_size", res.max_transact_size.into()),
+                ("max_read_size", res.max_read_size.into()),
+                ("max_write_size", res.max_write_size.into()),
+                ("security_buffer_length", res.security_buffer_len.into()),
+                ("contexts", negotiate_contexts(&res.contexts)),
+            ],
+        ),
+        Body::SessionSetupRequest(req) => typed(
+            "SessionSetupRequest",
+            vec![
+                ("flags", req.flags.into()),
+                ("security_mode", req.security_mode.into()),
+                ("capabilities", req.capabilities.into()),
+                ("previous_session_id", id(req.previous_session_id)),
+                ("auth", auth(&req.security_buffer)),
+            ],
+        ),
+        Body::SessionSetupResponse(res) => typed(
+            "SessionSetupResponse",
+            vec![
+                ("session_flags", flags(&res.session_flags)),
+                ("auth", auth(&res.security_buffer)),
+            ],
+        ),
+        Body::TreeConnectRequest(req) => typed(
+            "TreeConnectRequest",
+            vec![
+                ("flags", req.flags.into()),
+                ("path", req.path.as_str().into()),
+            ],
+        ),
+        Body::TreeConnectResponse(res) => typed(
+            "TreeConnectResponse",
+            vec![
+                ("share_type", res.share_type.into()),
+                ("share_flags", res.share_flags.into()),
+                ("capabilities", res.capabilities.into()),
+                ("maximal_access", res.maximal_access.into()),
+            ],
+        ),
+        Body::CreateRequest(req) => typed(
+            "CreateRequest",
+            vec![
+                ("oplock_level", req.oplock_level.into()),
+                ("impersonation_level", req.impersonation_level.into()),
+                ("desired_access", req.desired_access.into()),
+                ("file_attributes", req.file_attributes.into()),
+                ("share_access", req.share_access.into()),
+                ("disposition", req.disposition.into()),
+                ("options", req.options.into()),
+                ("name", req.name.as_str().into()),
+                ("contexts", create_contexts(&req.contexts)),
+            ],
+        ),
+        Body::CreateResponse(res) => typed(
+            "CreateResponse",
+            vec![
+                ("oplock_level", res.oplock_level.into()),
+                ("flags", res.flags.into()),
+                ("action", res.action.into()),
+                ("creation_time", filetime(res.creation_time)),
+                ("last_access_time", filetime(res.last_access_time)),
+                ("last_write_time", filetime(res.last_write_time)),
+                ("change_time", filetime(res.change_time)),
+                ("allocation_size", res.allocation_size.into()),
+                ("end_of_file", res.end_of_file.into()),
+                ("file_attributes", res.file_attributes.into()),
+                ("file_id", format!("{:?}", res.file_id).into()),
+                ("contexts", create_contexts(&res.contexts)),
+            ],
+        ),
+        Body::CloseRequest(req) => typed(
+            "CloseRequest",
+            vec![
+                ("flags", req.flags.into()),
+                ("file_id", format!("{:?}", req.file_id).into()),
+            ],
+        ),
+        Body::CloseResponse(res) => typed(
+            "CloseResponse",
+            vec![
+                ("flags", res.flags.into()),
+                ("end_of_file", res.end_of_file.into()),
+                ("file_attributes", res.file_attributes.into()),
+            ],
+        ),
+        Body::ReadRequest(req) => typed(
+            "ReadRequest",
+            vec![
+                ("flags", req.flags.into()),
+                ("length", req.length.into()),
+                ("offset", req.offset.into()),
+                ("file_id", format!("{:?}", req.file_id).into()),
+                ("minimum_count", req.minimum_count.into()),
+                ("channel", req.channel.into()),
+                ("remaining_bytes", req.remaining_bytes.into()),
+            ],
+        ),
+        Body::ReadResponse(res) => typed(
+            "ReadResponse",
+            vec![
+                ("data_length", res.data.len().into()),
+                ("data_remaining", res.data_remaining.into()),
+            ],
+        ),
+        Body::WriteRequest(req) => typed(
+            "WriteRequest",
+            vec![
+                ("offset", req.offset.into()),
+                ("file_id", format!("{:?}", req.file_id).into()),
+                ("channel", req.channel.into()),
+                ("remaining_bytes", req.remaining_bytes.into()),
+                ("flags", req.flags.into()),
+                ("data_length", req.data.len().into()),
+            ],
+        ),
+        Body::WriteResponse(res) => typed(
+            "WriteResponse",
+            vec![
+                ("count", res.count.into()),
+                ("remaining", res.remaining.into()),
+            ],
+        ),
+        Body::IoctlRequest(req) => typed(
+            "IoctlRequest",
+            vec![
+                ("ctl_code", ctl_code(req.ctl_code)),
+                ("file_id", format!("{:?}", req.file_id).into()),
+                ("max_input_response", req.max_input_response.into()),
+                ("max_output_response", req.max_output_response.into()),
+                ("flags", req.flags.into()),
+                ("input_length", req.input.len().into()),
+                ("output_length", req.output.len().into()),
+            ],
+        ),
+        Body::IoctlResponse(res) => typed(
+            "IoctlResponse",
+            vec![
+                ("ctl_code", ctl_code(res.ctl_code)),
+                ("file_id", format!("{:?}", res.file_id).into()),
+                ("flags", res.flags.into()),
+                ("input_length", res.input.len().into()),
+                ("output_length", res.output.len().into()),
+            ],
+        ),
+        Body::LockRequest(req) => typed(
+            "LockRequest",
+            vec![
+                ("lock_sequence_number", req.lock_sequence_number.into()),
+                ("lock_sequence_index", req.lock_sequence_index.into()),
+                ("file_id", format!("{:?}", req.file_id).into()),
+                (
+                    "locks",
+                    Json::Array(
+                        req.locks
+                            .iter()
+                            .map(|lock| {
+                                Json::Object(vec![
+                                    ("offset", lock.offset.into()),
+                                    ("length", lock.length.into()),
+                                    ("flags", flags(&lock.flags)),
+                                ])
+                            })
+                            .collect(),
+                    ),
+                ),
+            ],
+        ),
+        Body::LockResponse(_) => typed("LockResponse", vec![]),
+        Body::OplockBreak(brk) => typed(
+            "OplockBreak",
+            vec![
+                ("oplock_level", brk.oplock_level.into()),
+                ("file_id", format!("{:?}", brk.file_id).into()),
+            ],
+        ),
+        Body::LeaseBreakNotification(brk) => typed(
+            "LeaseBreakNotification",
+            vec![
+                ("new_epoch", brk.new_epoch.into()),
+                ("flags", brk.flags.into()),
+                ("lease_key", brk.lease_key.to_string().into()),
+                ("current_state", flags(&brk.current_state)),
+                ("new_state", flags(&brk.new_state)),
+            ],
+        ),
+        Body::LeaseBreakAck(ack) => typed(
+            "LeaseBreakAck",
+            vec![
+                ("flags", ack.flags.into()),
+                ("lease_key", ack.lease_key.to_string().into()),
+                ("state", flags(&ack.state)),
+            ],
+        ),
+        Body::NotifyRequest(req) => typed(
+            "NotifyRequest",
+            vec![
+                ("flags", req.flags.into()),
+                ("output_buffer_length", req.output_buffer_length.into()),
+                ("file_id", format!("{:?}", req.file_id).into()),
+                ("completion_filter", flags(&req.completion_filter)),
+            ],
+        ),
+        Body::NotifyResponse(res) => typed(
+            "NotifyResponse",
+            vec![(
+                "entries",
+                Json::Array(
+                    res.entries
+                        .iter()
+                        .map(|e| {
+                            Json::Object(vec![
+                                ("action", notify_action(&e.action)),
+                                ("name", e.name.as_str().into()),
+                            ])
+                        })
+                        .collect(),
+                ),
+            )],
+        ),
+        Body::SetInfoRequest(req) => typed(
+            "SetInfoRequest",
+            vec![
+                ("info_type", req.info_type.into()),
+                ("file_info_class", req.file_info_class.into()),
+                ("additional_information", req.additional_information.into()),
+                ("file_id", format!("{:?}", req.file_id).into()),
+                ("info", set_info(&req.info)),
+            ],
+        ),
+        Body::SetInfoResponse(_) => typed("SetInfoResponse", vec![]),
+        Body::Error(err) => typed(
+            "Error",
+            vec![
+                ("context_count", err.context_count.into()),
+                (
+                    "details",
+                    Json::Array(err.details.iter().map(error_detail).collect()),
+                ),
+            ],
+        ),
+        Body::Other => Json::Null,
+    }
+}
+
+/// Writes the records, numbering the connections as they show up.
+#[derive(Default)]
+pub struct Writer {
+    conns: HashMap<ConnKey, usize>,
+}
+
+impl Writer {
+    pub fn record(
+        &mut self,
+        frame: &Frame,
+        tcp: &Header,
+        header: &SMBHeader,
+        body: Result<&Body, &Error>,
+        events: &[String],
+    ) -> String {
+        let next = self.conns.len();
+        let conn = *self.conns.entry(frame.conn).or_insert(next);
+        let mut record = vec![
+            ("schema", SCHEMA_VERSION.into()),
+            ("frame", frame.number.into()),
+            ("time", frame.time.format("%H:%M:%S%.6f").to_string().into()),
+            (
+                "conn",
+                Json::Object(vec![
+                    ("id", conn.into()),
+                    ("client", frame.conn.client.to_string().into()),
+                    ("server", frame.conn.server.to_string().into()),
+                ]),
+            ),
+            (
+                "direction",
+                match frame.direction {
+                    Direction::RESPONSE => "server_to_client",
+                    _ => "client_to_server",
+                }
+                .into(),
+            ),
+            (
+                "tcp",
+                Json::Object(vec![
+                    ("src", tcp.src.to_string().into()),
+                    ("dst", tcp.dst.to_string().into()),
+                    ("seq", tcp.seq.into()),
+                    ("ack", tcp.ack.into()),
+                    ("win", tcp.win.into()),
+                    ("flags", format!("{:?}", tcp.flags).into()),
+                    ("len", (tcp.length as u64).into()),
+                ]),
+            ),
+            ("smb2", self::header(header)),
+        ];
+        match body {
+            Ok(body) => record.push(("body", self::body(body))),
+            Err(err) => {
+                record.push(("body", Json::Null));
+                record.push(("body_error", format!("{err:?}").into()));
+            }
+        }
+        record.push(("events", events.to_vec().into()));
+        Json::Object(record).to_string()
+    }
+}
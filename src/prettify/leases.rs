@@ -0,0 +1,96 @@
+use chrono::TimeDelta;
+
+use crate::analysis::leases::{Held, Holder, LeaseEvent};
+
+fn holder(holder: &Holder) -> String {
+    match holder {
+        Holder::Lease(key) => format!("lease {key}"),
+        Holder::Oplock(file_id) => format!("oplock {file_id:?}"),
+    }
+}
+
+pub fn millis(delta: TimeDelta) -> String {
+    format!(
+        "{:.3}ms",
+        delta.num_microseconds().unwrap_or(i64::MAX) as f64 / 1000.0
+    )
+}
+
+pub fn event_line(event: &LeaseEvent) -> String {
+    match event {
+        LeaseEvent::Granted {
+            holder: h,
+            path,
+            client,
+            requested,
+            granted,
+            epoch,
+        } => {
+            let mut line = format!(
+                "GRANTED {} \\{path} to {client}: {granted:?} (requested {requested:?})",
+                holder(h)
+            );
+            if let Some(epoch) = epoch {
+                line += &format!(" epoch {epoch}");
+            }
+            line
+        }
+        LeaseEvent::Break {
+            holder: h,
+            path,
+            client,
+            from,
+            to,
+            ack_required,
+            waiting,
+        } => {
+            let mut line = format!(
+                "BREAK {} \\{path} of {client}: {from:?} -> {to:?}{}",
+                holder(h),
+                if *ack_required { " (ack required)" } else { "" }
+            );
+            if !waiting.is_empty() {
+                let waiting: Vec<String> = waiting.iter().map(|c| c.to_string()).collect();
+                line += &format!(", stalling {}", waiting.join(", "));
+            }
+            line
+        }
+        LeaseEvent::Acked {
+            holder: h,
+            path,
+            client,
+            state,
+            delay,
+        } => {
+            let mut line = format!("BREAK ACK {} \\{path} by {client}: {state:?}", holder(h));
+            if let Some(delay) = delay {
+                line += &format!(" after {}", millis(*delay));
+            }
+            line
+        }
+    }
+}
+
+/// State history and break acknowledgment delays of a lease/oplock.
+pub fn held_lines(held: &Held) -> String {
+    let mut lines = format!("\\{} ({})", held.path, held.client);
+    for change in &held.history {
+        lines += &format!(
+            "\n  {} frame {}: {:?}",
+            change.time, change.frame, change.state
+        );
+    }
+    for brk in &held.breaks {
+        lines += &format!(
+            "\n  break at frame {} {:?} -> {:?}: {}",
+            brk.frame,
+            brk.from,
+            brk.to,
+            match brk.ack {
+                Some((frame, delay)) => format!("acked at frame {frame} after {}", millis(delay)),
+                None => "never acknowledged".to_owned(),
+            }
+        );
+    }
+    lines
+}
@@ -0,0 +1,47 @@
+use std::collections::BTreeMap;
+
+use super::diagnosis::severity;
+use crate::analysis::lint::{Rule, Violation};
+
+pub fn violation_line(violation: &Violation) -> String {
+    let rule = violation.rule;
+    format!(
+        "[{}] {} {} ({}, MS-SMB2 {})",
+        severity(rule.severity),
+        rule.id,
+        rule.text,
+        violation.detail,
+        rule.section
+    )
+}
+
+/// Violations grouped by rule, worst first, with the frames that broke each one.
+pub fn summary_lines(violations: &[Violation]) -> String {
+    let mut by_rule: BTreeMap<(std::cmp::Reverse<_>, &str), (&Rule, Vec<usize>)> = BTreeMap::new();
+    for violation in violations {
+        let rule = violation.rule;
+        by_rule
+            .entry((std::cmp::Reverse(rule.severity), rule.id))
+            .or_insert((rule, vec![]))
+            .1
+            .push(violation.frame);
+    }
+
+    let mut lines = vec![];
+    for (rule, frames) in by_rule.into_values() {
+        let mut shown: Vec<_> = frames.iter().take(10).map(usize::to_string).collect();
+        if frames.len() > shown.len() {
+            shown.push("...".to_owned());
+        }
+        lines.push(format!(
+            "  [{}] {} {} (MS-SMB2 {}): {} times, frames {}",
+            severity(rule.severity),
+            rule.id,
+            rule.text,
+            rule.section,
+            frames.len(),
+            shown.join(", ")
+        ));
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,67 @@
+use crate::analysis::locks::{HeldLock, LockEvent};
+
+fn held(lock: &HeldLock) -> String {
+    format!(
+        "{} {} {}+{} (frame {})",
+        lock.client,
+        if lock.exclusive {
+            "exclusive"
+        } else {
+            "shared"
+        },
+        lock.offset,
+        lock.length,
+        lock.frame
+    )
+}
+
+pub fn event_line(event: &LockEvent) -> String {
+    match event {
+        LockEvent::Conflict {
+            share,
+            path,
+            client,
+            status,
+            ranges,
+            holders,
+        } => {
+            let ranges: Vec<String> = ranges.iter().map(|(o, l)| format!("{o}+{l}")).collect();
+            let mut line = format!(
+                "LOCK CONFLICT {} [{}] {status:?} for {client}",
+                full_path(share.as_deref(), path),
+                ranges.join(", ")
+            );
+            if holders.is_empty() {
+                line += ", no conflicting lock seen in the capture";
+            }
+            for holder in holders {
+                line += &format!("\n  held by {}", held(holder));
+            }
+            line
+        }
+    }
+}
+
+/// `\\server\share\path`, or `\path` when the share isn't known.
+pub fn full_path(share: Option<&str>, path: &str) -> String {
+    match share {
+        Some(share) => format!("{share}\\{path}"),
+        None => format!("\\{path}"),
+    }
+}
+
+pub fn held_lines(
+    server: &std::net::IpAddr,
+    share: Option<&str>,
+    path: &str,
+    locks: &[HeldLock],
+) -> String {
+    let mut lines = match share {
+        Some(_) => full_path(share, path),
+        None => format!("\\\\{server}\\{path}"),
+    };
+    for lock in locks {
+        lines += &format!("\n  {}", held(lock));
+    }
+    lines
+}
@@ -1,2 +1,23 @@
-pub mod conn;
+pub mod audit;
 pub mod byte;
+pub mod color;
+pub mod conn;
+pub mod credits;
+pub mod diagnosis;
+pub mod diff;
+pub mod dissect;
+pub mod error;
+pub mod event;
+pub mod html;
+pub mod json;
+pub mod leases;
+pub mod lint;
+pub mod locks;
+pub mod notify;
+pub mod ops;
+pub mod report;
+pub mod rpc;
+pub mod sequence;
+pub mod sessions;
+pub mod srt;
+pub mod summary;
@@ -0,0 +1,57 @@
+use super::{leases::millis, locks::full_path};
+use crate::analysis::notify::{DirStats, NotifyEvent, Watch};
+
+pub fn watch_line(watch: &Watch) -> String {
+    format!(
+        "{} by {}{} {:?} (since frame {})",
+        full_path(watch.share.as_deref(), &watch.path),
+        watch.client,
+        if watch.tree { " [tree]" } else { "" },
+        watch.filter,
+        watch.frame
+    )
+}
+
+pub fn event_line(event: &NotifyEvent) -> String {
+    match event {
+        NotifyEvent::Fired {
+            watch,
+            status,
+            entries,
+            waited,
+        } => {
+            let mut line = format!(
+                "NOTIFY {} {status:?} after {}",
+                watch_line(watch),
+                millis(*waited)
+            );
+            for entry in entries {
+                line += &format!("\n  {:?} {:?}", entry.action, entry.name);
+            }
+            line
+        }
+        NotifyEvent::Ended { watch, waited } => {
+            format!(
+                "NOTIFY {} ended, handle closed after {}",
+                watch_line(watch),
+                millis(*waited)
+            )
+        }
+    }
+}
+
+pub fn stats_line(stats: &DirStats) -> String {
+    let mut line = format!(
+        "{} by {}: {} watches, {} fires ({} entries, {} enum dir)",
+        full_path(stats.share.as_deref(), &stats.path),
+        stats.client,
+        stats.watches,
+        stats.fires,
+        stats.entries,
+        stats.enum_dir
+    );
+    if let Some(rate) = stats.rate() {
+        line += &format!(", {rate:.2} fires/s");
+    }
+    line
+}
@@ -0,0 +1,91 @@
+//! One line per operation, request and response together:
+//! `12.345 C→S CREATE \\srv\data\report.docx (RW, open_if) → STATUS_SHARING_VIOLATION 3.200ms`.
+use chrono::NaiveTime;
+
+use super::{
+    color::{paint, Role},
+    conn::Direction,
+    leases::millis,
+};
+use crate::{
+    analysis::{elapsed, ops::Operation},
+    smb::SMBHeader,
+};
+
+/// The command and what it's about, `CREATE \\srv\data\report.docx (RW, open_if)`.
+pub fn request(op: &Operation) -> String {
+    let mut text = op.opcode.spec_name().to_owned();
+    if !op.target.is_empty() {
+        text += &format!(" {}", op.target);
+    }
+    if !op.details.is_empty() {
+        text += &format!(" ({})", op.details.join(", "));
+    }
+    text
+}
+
+/// The status and what else the response said, `STATUS_SUCCESS created`.
+pub fn response(op: &Operation) -> Option<String> {
+    let status = op.status?;
+    let status = if status.is_error() {
+        paint(Role::Failure, format!("{status:?}"))
+    } else {
+        format!("{status:?}")
+    };
+    Some(
+        [status]
+            .into_iter()
+            .chain(op.outcome.clone())
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
+
+/// What a message of an operation says: the request, or the command and the response.
+pub fn message(op: Option<&Operation>, header: &SMBHeader) -> String {
+    let command = header.opcode.spec_name();
+    match (op, header.is_response()) {
+        (Some(op), true) => match response(op) {
+            Some(response) => format!("{command} {response}"),
+            // a break notification
+            None => request(op),
+        },
+        (Some(op), false) => request(op),
+        // interim responses
+        (None, true) => format!("{command} {:?}", header.status()),
+        (None, false) => command.to_owned(),
+    }
+}
+
+fn describe(op: &Operation, start: NaiveTime) -> String {
+    let since = elapsed(start, op.time)
+        .num_microseconds()
+        .unwrap_or_default() as f64
+        / 1e6;
+    let direction = match op.direction {
+        Direction::RESPONSE => paint(Role::Response, "S→C"),
+        _ => paint(Role::Request, "C→S"),
+    };
+    format!("{since:.3} {direction} {}", request(op))
+}
+
+/// Line of an operation, `start` being the time of the first packet of the capture.
+pub fn line(op: &Operation, start: NaiveTime) -> String {
+    let mut line = describe(op, start);
+    if let Some(response) = response(op) {
+        line += &format!(" → {response}");
+    }
+    if let Some(elapsed) = op.elapsed {
+        line += &format!(" {}", paint(Role::Dim, millis(elapsed)));
+    }
+    line
+}
+
+/// Line of a request the capture has no final response for.
+pub fn unanswered_line(op: &Operation, start: NaiveTime) -> String {
+    format!(
+        "{} → {}",
+        describe(op, start),
+        paint(Role::Warning, "no response")
+    )
+}
@@ -0,0 +1,169 @@
+//! End of capture sections, printed after the message by message output.
+use super::color::say;
+use crate::analysis::{srt::Breakdown, Analyzer};
+
+/// Which of the optional sections to print.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub srt_by: Breakdown,
+    /// Timeline of the file operations, off by default as it's as long as the capture.
+    pub audit: bool,
+}
+
+/// Titles and lines of the sections that have something to say, in the order they're printed.
+pub fn sections(analyzer: &Analyzer, options: &Options) -> Vec<(&'static str, String)> {
+    let mut sections: Vec<(&'static str, Vec<String>)> = vec![];
+    // adds a line to the section being written
+    macro_rules! line {
+        ($($arg:tt)*) => {
+            sections.last_mut().expect("a section").1.push(format!($($arg)*))
+        };
+    }
+
+    let diagnoses = analyzer.diagnosis.diagnoses();
+    if !diagnoses.is_empty() {
+        sections.push(("diagnosis", vec![]));
+        for diagnosis in diagnoses {
+            line!("{}", super::diagnosis::diagnosis_lines(diagnosis));
+        }
+    }
+
+    let violations = analyzer.linter.violations();
+    if !violations.is_empty() {
+        sections.push(("conformance", vec![]));
+        line!("{}", super::lint::summary_lines(violations));
+    }
+
+    let mut conns: Vec<_> = analyzer.sessions.conns().collect();
+    if !conns.is_empty() {
+        conns.sort_by_key(|(conn, _)| **conn);
+        sections.push(("connections", vec![]));
+        for (conn, state) in conns {
+            line!("{}", super::sessions::conn_lines(conn, state));
+        }
+    }
+
+    let mut clients: Vec<_> = analyzer.summary.clients().collect();
+    if !clients.is_empty() {
+        clients.sort_by_key(|(ip, _)| **ip);
+        sections.push(("per client", vec![]));
+        for (ip, stats) in clients {
+            line!("{}", super::summary::stats_lines(&ip.to_string(), stats));
+        }
+    }
+
+    let mut shares: Vec<_> = analyzer.summary.shares().collect();
+    if !shares.is_empty() {
+        shares.sort_by_key(|(path, _)| path.to_lowercase());
+        sections.push(("per share", vec![]));
+        for (path, stats) in shares {
+            line!("{}", super::summary::stats_lines(path, stats));
+        }
+    }
+
+    let rows = analyzer.srt.rows(options.srt_by);
+    if !rows.is_empty() {
+        sections.push(("service response times", vec![]));
+        line!("{}", super::srt::table(&rows, options.srt_by));
+    }
+
+    let mut unmatched: Vec<_> = analyzer.matcher.unmatched().collect();
+    if !unmatched.is_empty() {
+        unmatched.sort_by_key(|(_, o)| o.frame);
+        sections.push(("requests without a response", vec![]));
+        for (conn, request) in unmatched {
+            let mut line = format!(
+                "  frame {} {:?} MessageId {} ({} -> {})",
+                request.frame, request.opcode, request.msg_id, conn.client, conn.server
+            );
+            if let Some((frame, _)) = request.interim {
+                line += &format!(", pending since frame {frame}");
+            }
+            line!("{line}");
+        }
+    }
+
+    let mut credits: Vec<_> = analyzer.credits.states().collect();
+    if !credits.is_empty() {
+        credits.sort_by_key(|(conn, _)| **conn);
+        sections.push(("credits per connection", vec![]));
+        for (conn, state) in credits {
+            line!("{}", super::credits::state_line(conn, state));
+        }
+    }
+
+    let orphans = analyzer.matcher.orphans();
+    if !orphans.is_empty() {
+        sections.push(("responses without a request", vec![]));
+        for orphan in orphans {
+            line!(
+                "  frame {} {:?} MessageId {} ({} -> {})",
+                orphan.frame,
+                orphan.opcode,
+                orphan.msg_id,
+                orphan.conn.server,
+                orphan.conn.client
+            );
+        }
+    }
+
+    let mut held: Vec<_> = analyzer.locks.held().collect();
+    if !held.is_empty() {
+        held.sort_by(|a, b| a.0.cmp(b.0));
+        sections.push(("locks held at end of capture", vec![]));
+        for ((server, share, path), locks) in held {
+            line!(
+                "{}",
+                super::locks::held_lines(server, share.as_deref(), path, locks)
+            );
+        }
+    }
+
+    let mut held: Vec<_> = analyzer
+        .leases
+        .held()
+        .filter(|h| !h.breaks.is_empty())
+        .collect();
+    if !held.is_empty() {
+        held.sort_by_key(|h| h.history.first().map(|c| c.frame));
+        sections.push(("oplocks and leases that got broken", vec![]));
+        for held in held {
+            line!("{}", super::leases::held_lines(held));
+        }
+    }
+
+    let mut stats: Vec<_> = analyzer.notify.stats().collect();
+    if !stats.is_empty() {
+        stats.sort_by_key(|s| (std::cmp::Reverse(s.fires), s.share.clone(), s.path.clone()));
+        sections.push(("change notify watches per directory", vec![]));
+        for stats in stats {
+            line!("{}", super::notify::stats_line(stats));
+        }
+        let mut outstanding: Vec<_> = analyzer.notify.outstanding().collect();
+        outstanding.sort_by_key(|w| w.frame);
+        line!("outstanding at end of capture: {}", outstanding.len());
+        for watch in outstanding {
+            line!("  {}", super::notify::watch_line(watch));
+        }
+    }
+
+    let entries = analyzer.audit.entries();
+    if options.audit && !entries.is_empty() {
+        sections.push(("file operations", vec![]));
+        for entry in entries {
+            line!("{}", super::audit::entry_line(entry));
+        }
+    }
+
+    sections
+        .into_iter()
+        .map(|(title, lines)| (title, lines.join("\n")))
+        .collect()
+}
+
+pub fn end_of_capture(analyzer: &Analyzer, options: &Options) {
+    for (title, lines) in sections(analyzer, options) {
+        say!("\n{title}:");
+        say!("{lines}");
+    }
+}
@@ -0,0 +1,69 @@
+use crate::{
+    analysis::pipes::PipeEvent,
+    dcerpc::{interfaces, PduBody},
+};
+
+/// One line summary of a DCE/RPC PDU seen on a pipe.
+pub fn event_line(event: &PipeEvent) -> String {
+    let pdu = match &event.pdu {
+        Ok(pdu) => pdu,
+        Err(err) => return format!("DCERPC \\{} parse error: {err:?}", event.pipe),
+    };
+
+    let mut line = format!(
+        "DCERPC \\{} {:?} call_id {}",
+        event.pipe, pdu.header.ptype, pdu.header.call_id
+    );
+    if let Some(name) = event.call_name() {
+        line += &format!(" {name}");
+    }
+
+    match &pdu.body {
+        PduBody::Bind { contexts, .. } => {
+            for ctx in contexts {
+                let uuid = &ctx.abstract_syntax.uuid;
+                line += &format!(
+                    " [ctx {}: {} v{}.{}]",
+                    ctx.context_id,
+                    interfaces::lookup(uuid)
+                        .map(|i| i.name.to_owned())
+                        .unwrap_or_else(|| uuid.to_string()),
+                    ctx.abstract_syntax.version,
+                    ctx.abstract_syntax.version_minor
+                );
+            }
+        }
+        PduBody::BindAck {
+            secondary_addr,
+            results,
+            ..
+        } => {
+            line += &format!(" {secondary_addr:?}");
+            for res in results {
+                line += &format!(
+                    " [{} {}]",
+                    match res.result {
+                        0 => "acceptance",
+                        1 => "user_rejection",
+                        2 => "provider_rejection",
+                        3 => "negotiate_ack",
+                        _ => "?",
+                    },
+                    interfaces::lookup(&res.transfer_syntax.uuid)
+                        .map(|i| i.name.to_owned())
+                        .unwrap_or_else(|| res.transfer_syntax.uuid.to_string())
+                );
+            }
+        }
+        PduBody::BindNak { reject_reason } => line += &format!(" reason {reject_reason}"),
+        PduBody::Fault { status, .. } => line += &format!(" status {status:?}"),
+        PduBody::Request { .. } | PduBody::Response { .. } | PduBody::Other => {}
+    }
+
+    match &event.stub {
+        Some(Ok(stub)) => line += &format!("\n  {stub:?}"),
+        Some(Err(err)) => line += &format!("\n  stub decode error: {err:?}"),
+        None => {}
+    }
+    line
+}
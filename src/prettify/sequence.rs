@@ -0,0 +1,223 @@
+//! Sequence diagrams of the exchange, one arrow per message between the hosts of the capture
+//! and TCP connection events as notes: Mermaid and PlantUML sources to paste in tickets, or an
+//! ASCII ladder for the terminal.
+use std::net::IpAddr;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Style {
+    Mermaid,
+    PlantUml,
+    Ladder,
+}
+
+#[derive(Debug, Clone)]
+enum Step {
+    /// Requests are solid arrows, responses dashed.
+    Message {
+        from: usize,
+        to: usize,
+        label: String,
+        response: bool,
+        frame: usize,
+    },
+    Note {
+        over: usize,
+        text: String,
+        frame: usize,
+    },
+}
+
+/// Steps collected while reading the capture, rendered at the end once every host is known.
+#[derive(Debug, Clone)]
+pub struct Diagram {
+    style: Style,
+    /// Hosts in order of appearance, clients usually first.
+    hosts: Vec<IpAddr>,
+    steps: Vec<Step>,
+}
+
+/// Space between the lifelines of the ladder.
+const LADDER_GAP: usize = 44;
+
+impl Diagram {
+    pub fn new(style: Style) -> Self {
+        Self {
+            style,
+            hosts: vec![],
+            steps: vec![],
+        }
+    }
+
+    fn host(&mut self, addr: IpAddr) -> usize {
+        match self.hosts.iter().position(|h| *h == addr) {
+            Some(i) => i,
+            None => {
+                self.hosts.push(addr);
+                self.hosts.len() - 1
+            }
+        }
+    }
+
+    pub fn message(
+        &mut self,
+        frame: usize,
+        from: IpAddr,
+        to: IpAddr,
+        label: String,
+        response: bool,
+    ) {
+        let (from, to) = (self.host(from), self.host(to));
+        self.steps.push(Step::Message {
+            from,
+            to,
+            label,
+            response,
+            frame,
+        });
+    }
+
+    pub fn note(&mut self, frame: usize, over: IpAddr, text: String) {
+        let over = self.host(over);
+        self.steps.push(Step::Note { over, text, frame });
+    }
+
+    pub fn render(&self) -> String {
+        match self.style {
+            Style::Mermaid => self.mermaid(),
+            Style::PlantUml => self.plantuml(),
+            Style::Ladder => self.ladder(),
+        }
+    }
+
+    fn mermaid(&self) -> String {
+        // `;` and `#` end a statement and start an entity
+        let escape = |text: &str| text.replace('#', "#35;").replace(';', "#59;");
+        let mut out = vec!["sequenceDiagram".to_owned()];
+        for (i, host) in self.hosts.iter().enumerate() {
+            out.push(format!("    participant H{i} as {host}"));
+        }
+        for step in &self.steps {
+            out.push(match step {
+                Step::Message {
+                    from,
+                    to,
+                    label,
+                    response,
+                    ..
+                } => {
+                    let arrow = if *response { "-->>" } else { "->>" };
+                    format!("    H{from}{arrow}H{to}: {}", escape(label))
+                }
+                Step::Note { over, text, .. } => format!("    Note over H{over}: {}", escape(text)),
+            });
+        }
+        out.join("\n")
+    }
+
+    fn plantuml(&self) -> String {
+        // a backslash followed by n would be a line break
+        let escape = |text: &str| text.replace('\\', "\\\\");
+        let mut out = vec!["@startuml".to_owned()];
+        for (i, host) in self.hosts.iter().enumerate() {
+            out.push(format!("participant \"{host}\" as H{i}"));
+        }
+        for step in &self.steps {
+            out.push(match step {
+                Step::Message {
+                    from,
+                    to,
+                    label,
+                    response,
+                    ..
+                } => {
+                    let arrow = if *response { "-->" } else { "->" };
+                    format!("H{from} {arrow} H{to} : {}", escape(label))
+                }
+                Step::Note { over, text, .. } => format!("note over H{over} : {}", escape(text)),
+            });
+        }
+        out.push("@enduml".to_owned());
+        out.join("\n")
+    }
+
+    /// Hosts side by side, each step under the frame it comes from. Labels go on their own line
+    /// above the arrow, so they can be longer than the gap between the lifelines.
+    fn ladder(&self) -> String {
+        let column = |host: usize| 8 + host * LADDER_GAP;
+        let width = column(self.hosts.len().saturating_sub(1)) + 1;
+        let lifelines = || {
+            let mut line = vec![' '; width];
+            for host in 0..self.hosts.len() {
+                line[column(host)] = '|';
+            }
+            line
+        };
+        let text = |line: Vec<char>| line.into_iter().collect::<String>().trim_end().to_owned();
+        let write = |line: &mut Vec<char>, at: usize, text: &str| {
+            for (i, c) in text.chars().enumerate() {
+                if at + i < line.len() {
+                    line[at + i] = c;
+                } else {
+                    line.push(c);
+                }
+            }
+        };
+
+        let mut out = vec![];
+        let mut heads = vec![' '; width];
+        for (i, host) in self.hosts.iter().enumerate() {
+            let name = host.to_string();
+            let at = column(i).saturating_sub(name.len() / 2);
+            write(&mut heads, at, &name);
+        }
+        out.push(text(heads));
+        for step in &self.steps {
+            match step {
+                Step::Message {
+                    from,
+                    to,
+                    label,
+                    response,
+                    frame,
+                } => {
+                    let (left, right) = (column(*from.min(to)), column(*from.max(to)));
+                    let mut above = lifelines();
+                    write(&mut above, left + 2, label);
+                    out.push(text(above));
+
+                    let mut arrow = lifelines();
+                    write(&mut arrow, 0, &format!("{frame:>6}"));
+                    if left == right {
+                        // both ends on one host, a loopback capture
+                        write(&mut arrow, left, "|<>");
+                        out.push(text(arrow));
+                        continue;
+                    }
+                    let shaft = if *response { '-' } else { '=' };
+                    for c in &mut arrow[left + 1..right] {
+                        *c = shaft;
+                    }
+                    if from < to {
+                        arrow[right - 1] = '>';
+                    } else {
+                        arrow[left + 1] = '<';
+                    }
+                    out.push(text(arrow));
+                }
+                Step::Note {
+                    over,
+                    text: note,
+                    frame,
+                } => {
+                    let mut line = lifelines();
+                    write(&mut line, 0, &format!("{frame:>6}"));
+                    let note = format!("[{note}]");
+                    let at = column(*over).saturating_sub(note.len() / 2);
+                    write(&mut line, at, &note);
+                    out.push(text(line));
+                }
+            }
+        }
+        out.join("\n")
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{
+    analysis::{
+        sessions::{ConnState, SessionEvent, User},
+        ConnKey,
+    },
+    smb::body::negotiate::{cipher_name, signing_algorithm_name},
+};
+
+fn user(user: &Option<User>) -> String {
+    match user {
+        Some(user) => user.to_string(),
+        None => "?".to_owned(),
+    }
+}
+
+pub fn event_line(event: &SessionEvent) -> String {
+    match event {
+        SessionEvent::Negotiated {
+            dialect,
+            offered,
+            signing_required,
+        } => {
+            let mut line = format!("NEGOTIATED {dialect}");
+            if !offered.is_empty() {
+                line += &format!(" (offered {offered:?})");
+            }
+            if *signing_required {
+                line += ", signing required";
+            }
+            line
+        }
+        SessionEvent::LoggedOn {
+            session_id,
+            user: u,
+            flags,
+        } => format!(
+            "LOGGED ON {} as session 0x{session_id:x} {flags:?}",
+            user(u)
+        ),
+        SessionEvent::AuthFailed {
+            session_id,
+            user: u,
+            status,
+        } => format!(
+            "AUTH FAILED for {} on session 0x{session_id:x}: {status:?}",
+            user(u)
+        ),
+        SessionEvent::LoggedOff {
+            session_id,
+            user: u,
+        } => format!("LOGGED OFF {} from session 0x{session_id:x}", user(u)),
+        SessionEvent::TreeConnected {
+            session_id,
+            tree_id,
+            path,
+        } => format!("TREE CONNECTED {path} as tree 0x{tree_id:x} on session 0x{session_id:x}"),
+        SessionEvent::TreeConnectFailed { path, status } => {
+            format!("TREE CONNECT to {path} failed: {status:?}")
+        }
+        SessionEvent::TreeDisconnected { tree_id, path } => {
+            format!("TREE DISCONNECTED {path} (tree 0x{tree_id:x})")
+        }
+    }
+}
+
+pub fn conn_lines(conn: &ConnKey, state: &ConnState) -> String {
+    let mut out = format!("  {} -> {}:", conn.client, conn.server);
+    match state.dialect {
+        Some(dialect) => out += &format!(" {dialect}"),
+        None => out += " dialect unknown",
+    }
+    if state.signing_required() {
+        out += ", signing required";
+    }
+    if let Some(id) = state.signing_algorithm {
+        out += &format!(", signing with {}", signing_algorithm_name(id));
+    }
+    if let Some(id) = state.cipher {
+        out += &format!(", {} encryption", cipher_name(id));
+    }
+
+    let mut sessions: Vec<_> = state.sessions.iter().collect();
+    sessions.sort_by_key(|(id, _)| **id);
+    for (id, session) in sessions {
+        out += &format!("\n    session 0x{id:x} {}", user(&session.user));
+        if !session.established {
+            out += " (not established)";
+        }
+        if !session.flags.is_empty() {
+            out += &format!(" {:?}", session.flags);
+        }
+        let mut trees: Vec<_> = session.trees.iter().collect();
+        trees.sort_by_key(|(id, _)| **id);
+        for (id, tree) in trees {
+            out += &format!("\n      tree 0x{id:x} {}", tree.path);
+            if tree.encrypted {
+                out += " (encrypted)";
+            }
+        }
+    }
+    out
+}
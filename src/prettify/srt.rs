@@ -0,0 +1,44 @@
+use super::leases::millis;
+use crate::analysis::srt::{Breakdown, SrtRow};
+
+/// SRT table, one row per command (and share or client).
+pub fn table(rows: &[SrtRow], by: Breakdown) -> String {
+    let group = match by {
+        Breakdown::None => None,
+        Breakdown::Share => Some("share"),
+        Breakdown::Client => Some("client"),
+    };
+    let width = rows
+        .iter()
+        .filter_map(|r| r.group.as_ref().map(String::len))
+        .chain(group.map(str::len))
+        .max()
+        .unwrap_or(0);
+
+    let mut out = format!("  {:<16}", "command");
+    if let Some(group) = group {
+        out += &format!(" {group:<width$}");
+    }
+    out += &format!(
+        " {:>7} {:>11} {:>11} {:>11} {:>11} {:>11} {:>11} {:>8}",
+        "count", "min", "max", "mean", "median", "p95", "p99", "failures"
+    );
+    for row in rows {
+        out += &format!("\n  {:<16}", format!("{:?}", row.opcode));
+        if let Some(group) = &row.group {
+            out += &format!(" {group:<width$}");
+        }
+        out += &format!(
+            " {:>7} {:>11} {:>11} {:>11} {:>11} {:>11} {:>11} {:>8}",
+            row.count,
+            millis(row.min),
+            millis(row.max),
+            millis(row.mean),
+            millis(row.median),
+            millis(row.p95),
+            millis(row.p99),
+            row.failures
+        );
+    }
+    out
+}
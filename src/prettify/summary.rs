@@ -0,0 +1,59 @@
+use crate::analysis::summary::Stats;
+
+fn plural(count: usize, what: &str) -> String {
+    if count == 1 {
+        format!("{count} {what}")
+    } else {
+        format!("{count} {what}s")
+    }
+}
+
+/// Block of lines for one client or share.
+pub fn stats_lines(name: &str, stats: &Stats) -> String {
+    let mut out = format!(
+        "  {name}: {}, {}",
+        plural(stats.conns.len(), "connection"),
+        plural(stats.sessions.len(), "session")
+    );
+    if let (Some(first), Some(last)) = (stats.first_seen, stats.last_seen) {
+        out += &format!(
+            ", seen {} - {}",
+            first.format("%H:%M:%S%.3f"),
+            last.format("%H:%M:%S%.3f")
+        );
+    }
+    if !stats.users.is_empty() {
+        let users: Vec<&str> = stats.users.iter().map(String::as_str).collect();
+        out += &format!("\n    users: {}", users.join(", "));
+    }
+    if !stats.dialects.is_empty() {
+        let dialects: Vec<String> = stats.dialects.iter().map(|d| d.to_string()).collect();
+        out += &format!("\n    dialects: {}", dialects.join(", "));
+    }
+    let mut messages: Vec<_> = stats.messages.iter().collect();
+    messages.sort_by_key(|(_, count)| std::cmp::Reverse(**count));
+    let messages: Vec<String> = messages
+        .iter()
+        .map(|(opcode, count)| format!("{opcode:?} {count}"))
+        .collect();
+    out += &format!(
+        "\n    messages: {} ({})",
+        stats.messages.values().sum::<usize>(),
+        messages.join(", ")
+    );
+    if stats.bytes_read > 0 || stats.bytes_written > 0 {
+        out += &format!(
+            "\n    read {} bytes, written {} bytes",
+            stats.bytes_read, stats.bytes_written
+        );
+    }
+    if !stats.errors.is_empty() {
+        let errors: Vec<String> = stats
+            .errors
+            .iter()
+            .map(|(status, count)| format!("{status:?} {count}"))
+            .collect();
+        out += &format!("\n    errors: {}", errors.join(", "));
+    }
+    out
+}
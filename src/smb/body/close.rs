@@ -0,0 +1,46 @@
+use super::{structure_size, Error, __};
+use crate::smb::{reader::Reader, types::FileId};
+
+// 2.2.15 SMB2 CLOSE Request
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CloseRequest {
+    ///  Flags (2 bytes): SMB2_CLOSE_FLAG_POSTQUERY_ATTRIB (0x0001), if set the server MUST set the
+    /// attribute fields in the response to valid values.
+    pub flags: u16,
+    pub file_id: FileId,
+}
+
+// 2.2.16 SMB2 CLOSE Response
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CloseResponse {
+    pub flags: u16,
+    pub end_of_file: u64,
+    pub file_attributes: u32,
+}
+
+impl CloseRequest {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 24)?;
+        let flags = __!(r.field("flags").u16());
+        let _reserved = __!(r.field("reserved").u32());
+        let file_id = __!(r.field("file_id").file_id());
+        Ok(Self { flags, file_id })
+    }
+}
+
+impl CloseResponse {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 60)?;
+        let flags = __!(r.field("flags").u16());
+        let _reserved = __!(r.field("reserved").u32());
+        // CreationTime, LastAccessTime, LastWriteTime, ChangeTime, AllocationSize
+        __!(r.skip(8 * 5));
+        let end_of_file = __!(r.field("end_of_file").u64());
+        let file_attributes = __!(r.field("file_attributes").u32());
+        Ok(Self {
+            flags,
+            end_of_file,
+            file_attributes,
+        })
+    }
+}
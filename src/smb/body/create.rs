@@ -0,0 +1,174 @@
+use super::{structure_size, Error, __};
+use crate::smb::{reader::Reader, types::utf16le, types::FileId};
+
+// 2.2.13 SMB2 CREATE Request
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CreateRequest {
+    ///  RequestedOplockLevel (1 byte): The requested oplock level. 0x09 is
+    /// SMB2_OPLOCK_LEVEL_BATCH; 0xFF is SMB2_OPLOCK_LEVEL_LEASE, in which case a lease create
+    /// context is present.
+    pub oplock_level: u8,
+    pub impersonation_level: u32,
+    ///  DesiredAccess (4 bytes): The level of access that is required, as specified in section
+    /// 2.2.13.1.
+    pub desired_access: u32,
+    pub file_attributes: u32,
+    ///  ShareAccess (4 bytes): Specifies the sharing mode for the open. FILE_SHARE_READ (0x1),
+    /// FILE_SHARE_WRITE (0x2), FILE_SHARE_DELETE (0x4).
+    pub share_access: u32,
+    ///  CreateDisposition (4 bytes): Defines the action the server MUST take if the file that is
+    /// specified in the name field already exists.
+    pub disposition: u32,
+    pub options: u32,
+    /// Path relative to the share root, without leading backslash. Empty for the share root itself.
+    pub name: String,
+    pub contexts: Vec<CreateContext>,
+}
+
+// 2.2.14 SMB2 CREATE Response
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CreateResponse {
+    pub oplock_level: u8,
+    pub flags: u8,
+    ///  CreateAction (4 bytes): The action taken in establishing the open. FILE_SUPERSEDED (0x0),
+    /// FILE_OPENED (0x1), FILE_CREATED (0x2), FILE_OVERWRITTEN (0x3).
+    pub action: u32,
+    pub creation_time: u64,
+    pub last_access_time: u64,
+    pub last_write_time: u64,
+    pub change_time: u64,
+    pub allocation_size: u64,
+    pub end_of_file: u64,
+    pub file_attributes: u32,
+    pub file_id: FileId,
+    pub contexts: Vec<CreateContext>,
+}
+
+// 2.2.13.2 SMB2_CREATE_CONTEXT Request Values
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CreateContext {
+    ///  Tag such as "RqLs", "DHnQ" or "MxAc", kept as text when it is printable ASCII.
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+impl CreateRequest {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 57)?;
+        let _security_flags = __!(r.field("security_flags").u8());
+        let oplock_level = __!(r.field("oplock_level").u8());
+        let impersonation_level = __!(r.field("impersonation_level").u32());
+        let _create_flags = __!(r.field("create_flags").u64());
+        let _reserved = __!(r.field("reserved").u64());
+        let desired_access = __!(r.field("desired_access").u32());
+        let file_attributes = __!(r.field("file_attributes").u32());
+        let share_access = __!(r.field("share_access").u32());
+        let disposition = __!(r.field("disposition").u32());
+        let options = __!(r.field("options").u32());
+        let name_offset = __!(r.field("name_offset").u16());
+        let name_len = __!(r.field("name_len").u16());
+        let ctx_offset = __!(r.field("ctx_offset").u32());
+        let ctx_len = __!(r.field("ctx_len").u32());
+
+        let name = r
+            .field("name")
+            .slice_at(name_offset.into(), name_len.into())
+            .ok_or(Error::InvalidOffset)?;
+        let contexts = r
+            .field("contexts")
+            .slice_at(ctx_offset as usize, ctx_len as usize)
+            .ok_or(Error::InvalidOffset)?;
+
+        Ok(Self {
+            oplock_level,
+            impersonation_level,
+            desired_access,
+            file_attributes,
+            share_access,
+            disposition,
+            options,
+            name: utf16le(name),
+            contexts: CreateContext::parse_chain(contexts)?,
+        })
+    }
+}
+
+impl CreateResponse {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 89)?;
+        let oplock_level = __!(r.field("oplock_level").u8());
+        let flags = __!(r.field("flags").u8());
+        let action = __!(r.field("action").u32());
+        let creation_time = __!(r.field("creation_time").u64());
+        let last_access_time = __!(r.field("last_access_time").u64());
+        let last_write_time = __!(r.field("last_write_time").u64());
+        let change_time = __!(r.field("change_time").u64());
+        let allocation_size = __!(r.field("allocation_size").u64());
+        let end_of_file = __!(r.field("end_of_file").u64());
+        let file_attributes = __!(r.field("file_attributes").u32());
+        let _reserved = __!(r.field("reserved").u32());
+        let file_id = __!(r.field("file_id").file_id());
+        let ctx_offset = __!(r.field("ctx_offset").u32());
+        let ctx_len = __!(r.field("ctx_len").u32());
+
+        let contexts = r
+            .field("contexts")
+            .slice_at(ctx_offset as usize, ctx_len as usize)
+            .ok_or(Error::InvalidOffset)?;
+
+        Ok(Self {
+            oplock_level,
+            flags,
+            action,
+            creation_time,
+            last_access_time,
+            last_write_time,
+            change_time,
+            allocation_size,
+            end_of_file,
+            file_attributes,
+            file_id,
+            contexts: CreateContext::parse_chain(contexts)?,
+        })
+    }
+}
+
+impl CreateContext {
+    /// Parses the chain of contexts, each one's `Next` being the offset to the following from its
+    /// own start and `NameOffset`/`DataOffset` also being relative to it.
+    pub fn parse_chain(raw: &[u8]) -> Result<Vec<Self>, Error> {
+        let mut contexts = vec![];
+        let mut start = 0usize;
+        while start < raw.len() {
+            let mut r = Reader::new(&raw[start..], 0);
+            let next = __!(r.u32()) as usize;
+            let name_offset = __!(r.u16());
+            let name_len = __!(r.u16());
+            let _reserved = __!(r.u16());
+            let data_offset = __!(r.u16());
+            let data_len = __!(r.u32());
+
+            let name = r
+                .slice_at(name_offset.into(), name_len.into())
+                .ok_or(Error::InvalidOffset)?;
+            let data = r
+                .slice_at(data_offset.into(), data_len as usize)
+                .ok_or(Error::InvalidOffset)?;
+
+            contexts.push(Self {
+                name: if name.iter().all(u8::is_ascii_graphic) {
+                    String::from_utf8_lossy(name).into_owned()
+                } else {
+                    format!("{name:02X?}")
+                },
+                data: data.to_vec(),
+            });
+
+            if next == 0 {
+                break;
+            }
+            start += next;
+        }
+        Ok(contexts)
+    }
+}
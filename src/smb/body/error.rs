@@ -0,0 +1,160 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use super::{structure_size, Error, __};
+use crate::smb::{reader::Reader, status::NtStatus, types::utf16le};
+
+pub const SMB2_ERROR_ID_DEFAULT: u32 = 0x00000000;
+pub const SMB2_ERROR_ID_SHARE_REDIRECT: u32 = 0x72645253;
+
+// 2.2.2 SMB2 ERROR Response
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ErrorResponse {
+    ///  ErrorContextCount (1 byte): This field MUST be set to 0 for SMB dialects other than 3.1.1.
+    /// For the SMB dialect 3.1.1, if this field is nonzero, the ErrorData field MUST be formatted
+    /// as a variable-length array of SMB2 ERROR Context structures.
+    pub context_count: u8,
+    pub details: Vec<ErrorDetail>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ErrorDetail {
+    Symlink(SymlinkError),
+    ShareRedirect(ShareRedirect),
+    ///  For STATUS_BUFFER_TOO_SMALL, the minimum required size of the output buffer.
+    BufferTooSmall(u32),
+    Raw {
+        error_id: u32,
+        data: Vec<u8>,
+    },
+}
+
+// 2.2.2.2.1 Symbolic Link Error Response
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SymlinkError {
+    ///  UnparsedPathLength (2 bytes): The length, in bytes, of the unparsed portion of the path. The
+    /// unparsed portion is at the end of the path that was sent in the CREATE request.
+    pub unparsed_path_length: u16,
+    pub substitute_name: String,
+    pub print_name: String,
+    ///  Flags (4 bytes): SYMLINK_FLAG_RELATIVE (0x1) if the substitute name is relative.
+    pub relative: bool,
+}
+
+// 2.2.2.2.2 Share Redirect Error Context Response
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ShareRedirect {
+    pub resource_name: String,
+    pub targets: Vec<IpAddr>,
+}
+
+impl ErrorResponse {
+    pub fn parse(r: &mut Reader, status: u32) -> Result<Self, Error> {
+        structure_size(r, 9)?;
+        let context_count = __!(r.field("context_count").u8());
+        let _reserved = __!(r.field("reserved").u8());
+        let byte_count = __!(r.field("byte_count").u32());
+        let data = __!(r.field("data").bytes(byte_count as usize));
+
+        let mut details = vec![];
+        if context_count == 0 {
+            if !data.is_empty() {
+                details.push(ErrorDetail::parse(SMB2_ERROR_ID_DEFAULT, data, status)?);
+            }
+        } else {
+            let mut r = Reader::new(data, 0);
+            for _ in 0..context_count {
+                __!(r.align(8));
+                let len = __!(r.u32());
+                let error_id = __!(r.u32());
+                let data = __!(r.bytes(len as usize));
+                details.push(ErrorDetail::parse(error_id, data, status)?);
+            }
+        }
+
+        Ok(Self {
+            context_count,
+            details,
+        })
+    }
+}
+
+impl ErrorDetail {
+    fn parse(error_id: u32, data: &[u8], status: u32) -> Result<Self, Error> {
+        Ok(match (error_id, NtStatus(status)) {
+            (SMB2_ERROR_ID_SHARE_REDIRECT, _) => Self::ShareRedirect(ShareRedirect::parse(data)?),
+            (SMB2_ERROR_ID_DEFAULT, NtStatus::STOPPED_ON_SYMLINK) => {
+                Self::Symlink(SymlinkError::parse(data)?)
+            }
+            (SMB2_ERROR_ID_DEFAULT, NtStatus::BUFFER_TOO_SMALL) if data.len() >= 4 => {
+                Self::BufferTooSmall(__!(Reader::new(data, 0).u32()))
+            }
+            _ => Self::Raw {
+                error_id,
+                data: data.to_vec(),
+            },
+        })
+    }
+}
+
+impl SymlinkError {
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        let mut r = Reader::new(data, 0);
+        let _symlink_length = __!(r.u32());
+        let _tag = __!(r.u32());
+        let _reparse_tag = __!(r.u32());
+        let _reparse_data_length = __!(r.u16());
+        let unparsed_path_length = __!(r.u16());
+        let substitute_offset = __!(r.u16());
+        let substitute_length = __!(r.u16());
+        let print_offset = __!(r.u16());
+        let print_length = __!(r.u16());
+        let flags = __!(r.u32());
+
+        let path_buffer = Reader::new(r.rest(), 0);
+        let substitute = path_buffer
+            .slice_at(substitute_offset.into(), substitute_length.into())
+            .ok_or(Error::InvalidOffset)?;
+        let print = path_buffer
+            .slice_at(print_offset.into(), print_length.into())
+            .ok_or(Error::InvalidOffset)?;
+
+        Ok(Self {
+            unparsed_path_length,
+            substitute_name: utf16le(substitute),
+            print_name: utf16le(print),
+            relative: flags & 0x1 != 0,
+        })
+    }
+}
+
+impl ShareRedirect {
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        let mut r = Reader::new(data, 0);
+        let _structure_size = __!(r.u32());
+        let _notification_type = __!(r.u32());
+        let name_offset = __!(r.u32());
+        let name_length = __!(r.u32());
+        let _flags = __!(r.u16());
+        let _target_type = __!(r.u16());
+        let count = __!(r.u32());
+
+        let mut targets = vec![];
+        for _ in 0..count {
+            let kind = __!(r.u32());
+            let _reserved = __!(r.u32());
+            let addr: [u8; 16] = __!(r.array());
+            targets.push(match kind {
+                1 => IpAddr::V4(Ipv4Addr::new(addr[0], addr[1], addr[2], addr[3])),
+                _ => IpAddr::V6(Ipv6Addr::from(addr)),
+            });
+        }
+
+        let name = r
+            .slice_at(name_offset as usize, name_length as usize)
+            .ok_or(Error::InvalidOffset)?;
+        Ok(Self {
+            resource_name: utf16le(name),
+            targets,
+        })
+    }
+}
@@ -0,0 +1,151 @@
+use super::{structure_size, Error, __};
+use crate::smb::{reader::Reader, types::FileId};
+
+pub const FSCTL_DFS_GET_REFERRALS: u32 = 0x00060194;
+pub const FSCTL_PIPE_PEEK: u32 = 0x0011400C;
+pub const FSCTL_PIPE_WAIT: u32 = 0x00110018;
+pub const FSCTL_PIPE_TRANSCEIVE: u32 = 0x0011C017;
+pub const FSCTL_SRV_COPYCHUNK: u32 = 0x001440F2;
+pub const FSCTL_SRV_ENUMERATE_SNAPSHOTS: u32 = 0x00144064;
+pub const FSCTL_SRV_REQUEST_RESUME_KEY: u32 = 0x00140078;
+pub const FSCTL_SRV_READ_HASH: u32 = 0x001441BB;
+pub const FSCTL_LMR_REQUEST_RESILIENCY: u32 = 0x001401D4;
+pub const FSCTL_QUERY_NETWORK_INTERFACE_INFO: u32 = 0x001401FC;
+pub const FSCTL_VALIDATE_NEGOTIATE_INFO: u32 = 0x00140204;
+
+/// Name of a well known FSCTL/IOCTL code.
+pub fn ctl_code_name(code: u32) -> Option<&'static str> {
+    Some(match code {
+        FSCTL_DFS_GET_REFERRALS => "FSCTL_DFS_GET_REFERRALS",
+        FSCTL_PIPE_PEEK => "FSCTL_PIPE_PEEK",
+        FSCTL_PIPE_WAIT => "FSCTL_PIPE_WAIT",
+        FSCTL_PIPE_TRANSCEIVE => "FSCTL_PIPE_TRANSCEIVE",
+        FSCTL_SRV_COPYCHUNK => "FSCTL_SRV_COPYCHUNK",
+        FSCTL_SRV_ENUMERATE_SNAPSHOTS => "FSCTL_SRV_ENUMERATE_SNAPSHOTS",
+        FSCTL_SRV_REQUEST_RESUME_KEY => "FSCTL_SRV_REQUEST_RESUME_KEY",
+        FSCTL_SRV_READ_HASH => "FSCTL_SRV_READ_HASH",
+        FSCTL_LMR_REQUEST_RESILIENCY => "FSCTL_LMR_REQUEST_RESILIENCY",
+        FSCTL_QUERY_NETWORK_INTERFACE_INFO => "FSCTL_QUERY_NETWORK_INTERFACE_INFO",
+        FSCTL_VALIDATE_NEGOTIATE_INFO => "FSCTL_VALIDATE_NEGOTIATE_INFO",
+        _ => return None,
+    })
+}
+
+// 2.2.31 SMB2 IOCTL Request
+#[derive(Clone, Eq, PartialEq)]
+pub struct IoctlRequest {
+    ///  CtlCode (4 bytes): The control code of the FSCTL/IOCTL method.
+    pub ctl_code: u32,
+    pub file_id: FileId,
+    pub max_input_response: u32,
+    pub max_output_response: u32,
+    ///  Flags (4 bytes): SMB2_0_IOCTL_IS_FSCTL (0x1) if it is an FSCTL, 0 for an IOCTL.
+    pub flags: u32,
+    pub input: Vec<u8>,
+    pub output: Vec<u8>,
+}
+
+// 2.2.32 SMB2 IOCTL Response
+#[derive(Clone, Eq, PartialEq)]
+pub struct IoctlResponse {
+    pub ctl_code: u32,
+    pub file_id: FileId,
+    pub flags: u32,
+    pub input: Vec<u8>,
+    pub output: Vec<u8>,
+}
+
+impl IoctlRequest {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 57)?;
+        let _reserved = __!(r.field("reserved").u16());
+        let ctl_code = __!(r.field("ctl_code").u32());
+        let file_id = __!(r.field("file_id").file_id());
+        let input_offset = __!(r.field("input_offset").u32());
+        let input_count = __!(r.field("input_count").u32());
+        let max_input_response = __!(r.field("max_input_response").u32());
+        let output_offset = __!(r.field("output_offset").u32());
+        let output_count = __!(r.field("output_count").u32());
+        let max_output_response = __!(r.field("max_output_response").u32());
+        let flags = __!(r.field("flags").u32());
+
+        let input = __!(r
+            .field("input")
+            .slice_at(input_offset as usize, input_count as usize));
+        let output = __!(r
+            .field("output")
+            .slice_at(output_offset as usize, output_count as usize));
+
+        Ok(Self {
+            ctl_code,
+            file_id,
+            max_input_response,
+            max_output_response,
+            flags,
+            input: input.to_vec(),
+            output: output.to_vec(),
+        })
+    }
+}
+
+impl IoctlResponse {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 49)?;
+        let _reserved = __!(r.field("reserved").u16());
+        let ctl_code = __!(r.field("ctl_code").u32());
+        let file_id = __!(r.field("file_id").file_id());
+        let input_offset = __!(r.field("input_offset").u32());
+        let input_count = __!(r.field("input_count").u32());
+        let output_offset = __!(r.field("output_offset").u32());
+        let output_count = __!(r.field("output_count").u32());
+        let flags = __!(r.field("flags").u32());
+
+        let input = __!(r
+            .field("input")
+            .slice_at(input_offset as usize, input_count as usize));
+        let output = __!(r
+            .field("output")
+            .slice_at(output_offset as usize, output_count as usize));
+
+        Ok(Self {
+            ctl_code,
+            file_id,
+            flags,
+            input: input.to_vec(),
+            output: output.to_vec(),
+        })
+    }
+}
+
+struct CtlCode(u32);
+impl std::fmt::Debug for CtlCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match ctl_code_name(self.0) {
+            Some(name) => write!(f, "{name}"),
+            None => write!(f, "0x{:08X}", self.0),
+        }
+    }
+}
+
+impl std::fmt::Debug for IoctlRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IoctlRequest")
+            .field("ctl_code", &CtlCode(self.ctl_code))
+            .field("file_id", &self.file_id)
+            .field("flags", &self.flags)
+            .field("input_len", &self.input.len())
+            .field("output_len", &self.output.len())
+            .finish()
+    }
+}
+
+impl std::fmt::Debug for IoctlResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IoctlResponse")
+            .field("ctl_code", &CtlCode(self.ctl_code))
+            .field("file_id", &self.file_id)
+            .field("input_len", &self.input.len())
+            .field("output_len", &self.output.len())
+            .finish()
+    }
+}
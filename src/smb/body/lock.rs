@@ -0,0 +1,102 @@
+use bitflags::bitflags;
+
+use super::{structure_size, Error, __};
+use crate::smb::{reader::Reader, types::FileId};
+
+bitflags! {
+    #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+    pub struct LockFlags: u32 {
+        ///  The range MUST be locked shared, allowing other opens to read from or take a shared lock
+        /// on the range. All opens MUST NOT be allowed to write within the range.
+        const SharedLock = 0x00000001;
+
+        ///  The range MUST be locked exclusive, not allowing other opens to read, write, or lock within
+        /// the range.
+        const ExclusiveLock = 0x00000002;
+
+        ///  The range MUST be unlocked from a previous lock taken on this range. The unlock range MUST
+        /// be identical to the lock range.
+        const Unlock = 0x00000004;
+
+        ///  The lock operation MUST fail immediately if it conflicts with an existing lock, instead of
+        /// waiting for the range to become available.
+        const FailImmediately = 0x00000010;
+
+        const _ = !0;
+    }
+}
+
+// 2.2.26.1 SMB2_LOCK_ELEMENT Structure
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LockElement {
+    pub offset: u64,
+    pub length: u64,
+    pub flags: LockFlags,
+}
+
+// 2.2.26 SMB2 LOCK Request
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LockRequest {
+    ///  LockSequenceNumber (4 bits): In SMB 2.1 and SMB 3.x, a 4-bit integer value.
+    pub lock_sequence_number: u8,
+    ///  LockSequenceIndex (28 bits): In SMB 2.1 and SMB 3.x, a 28-bit integer value that MUST be
+    /// greater than or equal to 0 and less than or equal to 64.
+    pub lock_sequence_index: u32,
+    pub file_id: FileId,
+    pub locks: Vec<LockElement>,
+}
+
+// 2.2.27 SMB2 LOCK Response
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LockResponse;
+
+impl LockElement {
+    pub fn end(&self) -> u64 {
+        self.offset.saturating_add(self.length)
+    }
+
+    /// Whether this range and `offset`/`length` share a byte. Zero length ranges share none, so
+    /// they conflict with nothing, as in Windows.
+    pub fn overlaps(&self, offset: u64, length: u64) -> bool {
+        self.length != 0
+            && length != 0
+            && self.offset < offset.saturating_add(length)
+            && offset < self.end()
+    }
+}
+
+impl LockRequest {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 48)?;
+        let count = __!(r.field("count").u16());
+        let sequence = __!(r.field("sequence").u32());
+        let file_id = __!(r.field("file_id").file_id());
+
+        let mut locks = vec![];
+        for _ in 0..count {
+            let offset = __!(r.field("offset").u64());
+            let length = __!(r.field("length").u64());
+            let flags = LockFlags::from_bits_retain(__!(r.field("flags").u32()));
+            let _reserved = __!(r.field("reserved").u32());
+            locks.push(LockElement {
+                offset,
+                length,
+                flags,
+            });
+        }
+
+        Ok(Self {
+            lock_sequence_number: (sequence & 0xF) as u8,
+            lock_sequence_index: sequence >> 4,
+            file_id,
+            locks,
+        })
+    }
+}
+
+impl LockResponse {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 4)?;
+        Ok(Self)
+    }
+}
@@ -0,0 +1,178 @@
+pub mod close;
+pub mod create;
+pub mod error;
+pub mod ioctl;
+pub mod lock;
+pub mod negotiate;
+pub mod notify;
+pub mod oplock;
+pub mod read;
+pub mod session;
+pub mod setinfo;
+pub mod tree;
+pub mod write;
+
+use super::{
+    opcodes::Opcodes,
+    reader::{Reader, Span},
+    status::NtStatus,
+    SMBHeader,
+};
+use std::cell::RefCell;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Error {
+    ExpectedByte,
+    InvalidStructureSize(u16),
+    InvalidOffset,
+}
+
+/// Decoded command specific part of a message (what comes after the `SMBHeader`), commands that
+/// have no decoder yet are left as `Other`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Body {
+    NegotiateRequest(negotiate::NegotiateRequest),
+    NegotiateResponse(negotiate::NegotiateResponse),
+    SessionSetupRequest(session::SessionSetupRequest),
+    SessionSetupResponse(session::SessionSetupResponse),
+    TreeConnectRequest(tree::TreeConnectRequest),
+    TreeConnectResponse(tree::TreeConnectResponse),
+    CreateRequest(create::CreateRequest),
+    CreateResponse(create::CreateResponse),
+    CloseRequest(close::CloseRequest),
+    CloseResponse(close::CloseResponse),
+    ReadRequest(read::ReadRequest),
+    ReadResponse(read::ReadResponse),
+    WriteRequest(write::WriteRequest),
+    WriteResponse(write::WriteResponse),
+    IoctlRequest(ioctl::IoctlRequest),
+    IoctlResponse(ioctl::IoctlResponse),
+    LockRequest(lock::LockRequest),
+    LockResponse(lock::LockResponse),
+    /// Notification, acknowledgment and response, told apart by the header
+    OplockBreak(oplock::OplockBreak),
+    LeaseBreakNotification(oplock::LeaseBreakNotification),
+    /// Acknowledgment and response, told apart by the header
+    LeaseBreakAck(oplock::LeaseBreakAck),
+    NotifyRequest(notify::NotifyRequest),
+    NotifyResponse(notify::NotifyResponse),
+    SetInfoRequest(setinfo::SetInfoRequest),
+    SetInfoResponse(setinfo::SetInfoResponse),
+    /// SMB2 ERROR Response, for any command
+    Error(error::ErrorResponse),
+    Other,
+}
+
+/// Same as `?` on an `Option`, but returning `Error::ExpectedByte`, the equivalent of `smb::__`
+macro_rules! __ {
+    ($exp:expr) => {
+        match $exp {
+            Some(val) => val,
+            None => return Err($crate::smb::body::Error::ExpectedByte),
+        }
+    };
+}
+pub(crate) use __;
+
+impl Body {
+    pub fn parse(header: &SMBHeader, payload: &[u8]) -> Result<Self, Error> {
+        Self::parse_from(header, Reader::new(payload, header.hlen.into()))
+    }
+
+    /// Where the fields `parse` reads are, relative to the start of the header. When the body
+    /// doesn't parse, the ones read before the failure.
+    pub fn spans(header: &SMBHeader, payload: &[u8]) -> Vec<Span> {
+        let spans = RefCell::new(vec![]);
+        let r = Reader::new(payload, header.hlen.into()).recording(&spans);
+        let _ = Self::parse_from(header, r);
+        spans.into_inner()
+    }
+
+    fn parse_from(header: &SMBHeader, mut r: Reader) -> Result<Self, Error> {
+        let structure_size = __!(r.clone().u16());
+
+        // SMB2 ERROR Response, any command can get one when the status is not a success (this
+        // includes the interim STATUS_PENDING response). CHANGE_NOTIFY responses are also 9 bytes
+        // and can complete with STATUS_NOTIFY_ENUM_DIR/CLEANUP, so those aren't errors, neither are
+        // the 9 bytes SESSION_SETUP responses asking for another round trip, nor QUERY_INFO and
+        // QUERY_DIRECTORY responses with STATUS_BUFFER_OVERFLOW, which carry the data that fits.
+        let notify_completion = header.opcode == Opcodes::Notify
+            && matches!(
+                header.status(),
+                NtStatus::NOTIFY_CLEANUP | NtStatus::NOTIFY_ENUM_DIR
+            );
+        let more_processing = header.opcode == Opcodes::SessionSetup
+            && header.status() == NtStatus::MORE_PROCESSING_REQUIRED;
+        let truncated = matches!(header.opcode, Opcodes::GetInfo | Opcodes::Find)
+            && header.status() == NtStatus::BUFFER_OVERFLOW;
+        if header.is_response()
+            && structure_size == 9
+            && header.nt_status != 0
+            && !notify_completion
+            && !more_processing
+            && !truncated
+        {
+            return Ok(Self::Error(error::ErrorResponse::parse(
+                &mut r,
+                header.nt_status,
+            )?));
+        }
+
+        Ok(match (&header.opcode, header.is_response()) {
+            (Opcodes::NegotiateProtocol, false) => {
+                Self::NegotiateRequest(negotiate::NegotiateRequest::parse(&mut r)?)
+            }
+            (Opcodes::NegotiateProtocol, true) => {
+                Self::NegotiateResponse(negotiate::NegotiateResponse::parse(&mut r)?)
+            }
+            (Opcodes::SessionSetup, false) => {
+                Self::SessionSetupRequest(session::SessionSetupRequest::parse(&mut r)?)
+            }
+            (Opcodes::SessionSetup, true) => {
+                Self::SessionSetupResponse(session::SessionSetupResponse::parse(&mut r)?)
+            }
+            (Opcodes::TreeConnect, false) => {
+                Self::TreeConnectRequest(tree::TreeConnectRequest::parse(&mut r)?)
+            }
+            (Opcodes::TreeConnect, true) => {
+                Self::TreeConnectResponse(tree::TreeConnectResponse::parse(&mut r)?)
+            }
+            (Opcodes::Create, false) => Self::CreateRequest(create::CreateRequest::parse(&mut r)?),
+            (Opcodes::Create, true) => Self::CreateResponse(create::CreateResponse::parse(&mut r)?),
+            (Opcodes::Close, false) => Self::CloseRequest(close::CloseRequest::parse(&mut r)?),
+            (Opcodes::Close, true) => Self::CloseResponse(close::CloseResponse::parse(&mut r)?),
+            (Opcodes::Read, false) => Self::ReadRequest(read::ReadRequest::parse(&mut r)?),
+            (Opcodes::Read, true) => Self::ReadResponse(read::ReadResponse::parse(&mut r)?),
+            (Opcodes::Write, false) => Self::WriteRequest(write::WriteRequest::parse(&mut r)?),
+            (Opcodes::Write, true) => Self::WriteResponse(write::WriteResponse::parse(&mut r)?),
+            (Opcodes::Ioctl, false) => Self::IoctlRequest(ioctl::IoctlRequest::parse(&mut r)?),
+            (Opcodes::Ioctl, true) => Self::IoctlResponse(ioctl::IoctlResponse::parse(&mut r)?),
+            (Opcodes::Lock, false) => Self::LockRequest(lock::LockRequest::parse(&mut r)?),
+            (Opcodes::Lock, true) => Self::LockResponse(lock::LockResponse::parse(&mut r)?),
+            (Opcodes::Notify, false) => Self::NotifyRequest(notify::NotifyRequest::parse(&mut r)?),
+            (Opcodes::Notify, true) => Self::NotifyResponse(notify::NotifyResponse::parse(&mut r)?),
+            (Opcodes::SetInfo, false) => {
+                Self::SetInfoRequest(setinfo::SetInfoRequest::parse(&mut r)?)
+            }
+            (Opcodes::SetInfo, true) => {
+                Self::SetInfoResponse(setinfo::SetInfoResponse::parse(&mut r)?)
+            }
+            (Opcodes::Break, _) => match structure_size {
+                24 => Self::OplockBreak(oplock::OplockBreak::parse(&mut r)?),
+                44 => Self::LeaseBreakNotification(oplock::LeaseBreakNotification::parse(&mut r)?),
+                36 => Self::LeaseBreakAck(oplock::LeaseBreakAck::parse(&mut r)?),
+                size => return Err(Error::InvalidStructureSize(size)),
+            },
+            _ => Self::Other,
+        })
+    }
+}
+
+/// Reads the StructureSize field and checks it against the one the spec mandates.
+pub(crate) fn structure_size(r: &mut Reader, expected: u16) -> Result<(), Error> {
+    let size = __!(r.field("structure_size").u16());
+    if size != expected {
+        return Err(Error::InvalidStructureSize(size));
+    }
+    Ok(())
+}
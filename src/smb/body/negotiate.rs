@@ -0,0 +1,290 @@
+use bitflags::bitflags;
+use std::fmt::{Debug, Display};
+
+use super::{structure_size, Error, __};
+use crate::smb::{reader::Reader, types::Guid};
+
+bitflags! {
+    ///  SecurityMode (2 bytes): The security mode field specifies whether SMB signing is enabled or
+    /// required at the client/server.
+    #[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+    pub struct SecurityMode: u16 {
+        const SigningEnabled = 0x0001;
+        const SigningRequired = 0x0002;
+
+        const _ = !0;
+    }
+}
+
+bitflags! {
+    ///  Capabilities (4 bytes): global capabilities of the client/server.
+    #[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+    pub struct Capabilities: u32 {
+        const Dfs = 0x00000001;
+        const Leasing = 0x00000002;
+        const LargeMtu = 0x00000004;
+        const MultiChannel = 0x00000008;
+        const PersistentHandles = 0x00000010;
+        const DirectoryLeasing = 0x00000020;
+        const Encryption = 0x00000040;
+        const Notifications = 0x00000080;
+
+        const _ = !0;
+    }
+}
+
+/// Dialect revision number, printed the way people talk about them (`smb3.1.1`).
+#[derive(Copy, Clone, Eq, PartialEq, Hash, PartialOrd, Ord, Default)]
+pub struct Dialect(pub u16);
+
+impl Dialect {
+    pub const SMB202: Self = Self(0x0202);
+    pub const SMB21: Self = Self(0x0210);
+    pub const SMB30: Self = Self(0x0300);
+    pub const SMB302: Self = Self(0x0302);
+    pub const SMB311: Self = Self(0x0311);
+    /// Sent by servers answering a multi-protocol (SMB1) negotiate, asking for a second SMB2 one.
+    pub const WILDCARD: Self = Self(0x02FF);
+
+    /// In the SMB 3.x dialect family, the Status of a request header is ChannelSequence/Reserved.
+    pub fn is_smb3(&self) -> bool {
+        self.0 >= Self::SMB30.0 && *self != Self::WILDCARD
+    }
+}
+
+impl Display for Dialect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::SMB202 => write!(f, "smb2.0.2"),
+            Self::SMB21 => write!(f, "smb2.1"),
+            Self::SMB30 => write!(f, "smb3.0"),
+            Self::SMB302 => write!(f, "smb3.0.2"),
+            Self::SMB311 => write!(f, "smb3.1.1"),
+            Self::WILDCARD => write!(f, "smb2.???"),
+            Self(other) => write!(f, "dialect 0x{other:04x}"),
+        }
+    }
+}
+
+impl Debug for Dialect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+pub const PREAUTH_INTEGRITY_CAPABILITIES: u16 = 0x0001;
+pub const ENCRYPTION_CAPABILITIES: u16 = 0x0002;
+pub const COMPRESSION_CAPABILITIES: u16 = 0x0003;
+pub const NETNAME_NEGOTIATE_CONTEXT_ID: u16 = 0x0005;
+pub const TRANSPORT_CAPABILITIES: u16 = 0x0006;
+pub const RDMA_TRANSFORM_CAPABILITIES: u16 = 0x0007;
+pub const SIGNING_CAPABILITIES: u16 = 0x0008;
+
+// 2.2.3.1 SMB2 NEGOTIATE_CONTEXT Request Values
+#[derive(Clone, Eq, PartialEq)]
+pub struct NegotiateContext {
+    pub context_type: u16,
+    pub data: Vec<u8>,
+}
+
+impl Debug for NegotiateContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self.context_type {
+            PREAUTH_INTEGRITY_CAPABILITIES => "PREAUTH_INTEGRITY",
+            ENCRYPTION_CAPABILITIES => "ENCRYPTION",
+            COMPRESSION_CAPABILITIES => "COMPRESSION",
+            NETNAME_NEGOTIATE_CONTEXT_ID => "NETNAME",
+            TRANSPORT_CAPABILITIES => "TRANSPORT",
+            RDMA_TRANSFORM_CAPABILITIES => "RDMA_TRANSFORM",
+            SIGNING_CAPABILITIES => "SIGNING",
+            _ => return write!(f, "0x{:04x}({} bytes)", self.context_type, self.data.len()),
+        };
+        write!(f, "{name}")?;
+        if let Some(ids) = self.ids() {
+            write!(f, "{ids:?}")?;
+        }
+        Ok(())
+    }
+}
+
+impl NegotiateContext {
+    fn parse_list(r: &Reader, offset: usize, count: u16) -> Result<Vec<Self>, Error> {
+        let mut r = r.clone();
+        r.seek(offset).ok_or(Error::InvalidOffset)?;
+        let mut contexts = Vec::with_capacity(count.into());
+        for i in 0..count {
+            if i > 0 {
+                __!(r.align(8));
+            }
+            let context_type = __!(r.u16());
+            let len = __!(r.u16());
+            let _reserved = __!(r.u32());
+            if context_type == NETNAME_NEGOTIATE_CONTEXT_ID {
+                // the server name as the client knows it
+                r.field("netname");
+            }
+            contexts.push(Self {
+                context_type,
+                data: __!(r.bytes(len.into())).to_vec(),
+            });
+        }
+        Ok(contexts)
+    }
+
+    /// Cipher, signing algorithm or hash ids, for the contexts that are made of a count followed by
+    /// a list of 16 bit ids.
+    pub fn ids(&self) -> Option<Vec<u16>> {
+        let mut r = Reader::new(&self.data, 0);
+        match self.context_type {
+            ENCRYPTION_CAPABILITIES | SIGNING_CAPABILITIES => {}
+            // HashAlgorithmCount, SaltLength
+            PREAUTH_INTEGRITY_CAPABILITIES => {
+                let count = r.u16()?;
+                r.skip(2)?;
+                return (0..count).map(|_| r.u16()).collect();
+            }
+            _ => return None,
+        }
+        let count = r.u16()?;
+        (0..count).map(|_| r.u16()).collect()
+    }
+}
+
+/// Name of an SMB2_ENCRYPTION_CAPABILITIES cipher id.
+pub fn cipher_name(id: u16) -> &'static str {
+    match id {
+        0x0001 => "AES-128-CCM",
+        0x0002 => "AES-128-GCM",
+        0x0003 => "AES-256-CCM",
+        0x0004 => "AES-256-GCM",
+        _ => "UNKNOWN",
+    }
+}
+
+/// Name of an SMB2_SIGNING_CAPABILITIES algorithm id.
+pub fn signing_algorithm_name(id: u16) -> &'static str {
+    match id {
+        0x0000 => "HMAC-SHA256",
+        0x0001 => "AES-CMAC",
+        0x0002 => "AES-GMAC",
+        _ => "UNKNOWN",
+    }
+}
+
+// 2.2.3 SMB2 NEGOTIATE Request
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct NegotiateRequest {
+    pub security_mode: SecurityMode,
+    pub capabilities: Capabilities,
+    pub client_guid: Guid,
+    pub dialects: Vec<Dialect>,
+    /// Only when SMB 3.1.1 is offered.
+    pub contexts: Vec<NegotiateContext>,
+}
+
+// 2.2.4 SMB2 NEGOTIATE Response
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct NegotiateResponse {
+    pub security_mode: SecurityMode,
+    pub dialect: Dialect,
+    pub server_guid: Guid,
+    pub capabilities: Capabilities,
+    pub max_transact_size: u32,
+    pub max_read_size: u32,
+    pub max_write_size: u32,
+    /// GSS token (SPNEGO init) listing the authentication mechanisms the server accepts.
+    pub security_buffer_len: usize,
+    /// Only on SMB 3.1.1, the ones the server picked.
+    pub contexts: Vec<NegotiateContext>,
+}
+
+impl NegotiateRequest {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 36)?;
+        let dialect_count = __!(r.field("dialect_count").u16());
+        let security_mode = SecurityMode::from_bits_retain(__!(r.field("security_mode").u16()));
+        let _reserved = __!(r.field("reserved").u16());
+        let capabilities = Capabilities::from_bits_retain(__!(r.field("capabilities").u32()));
+        let client_guid = __!(r.field("client_guid").guid());
+        // NegotiateContextOffset/Count/Reserved2 on 3.1.1, ClientStartTime otherwise
+        let context_offset = __!(r.field("context_offset").u32());
+        let context_count = __!(r.field("context_count").u16());
+        let _reserved2 = __!(r.field("reserved2").u16());
+        let dialects: Vec<_> = (0..dialect_count)
+            .map(|_| r.u16().map(Dialect))
+            .collect::<Option<_>>()
+            .ok_or(Error::ExpectedByte)?;
+
+        let contexts = if dialects.contains(&Dialect::SMB311) && context_count > 0 {
+            NegotiateContext::parse_list(r, context_offset as usize, context_count)?
+        } else {
+            vec![]
+        };
+
+        Ok(Self {
+            security_mode,
+            capabilities,
+            client_guid,
+            dialects,
+            contexts,
+        })
+    }
+}
+
+impl NegotiateResponse {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 65)?;
+        let security_mode = SecurityMode::from_bits_retain(__!(r.field("security_mode").u16()));
+        let dialect = Dialect(__!(r.field("dialect").u16()));
+        let context_count = __!(r.field("context_count").u16());
+        let server_guid = __!(r.field("server_guid").guid());
+        let capabilities = Capabilities::from_bits_retain(__!(r.field("capabilities").u32()));
+        let max_transact_size = __!(r.field("max_transact_size").u32());
+        let max_read_size = __!(r.field("max_read_size").u32());
+        let max_write_size = __!(r.field("max_write_size").u32());
+        // SystemTime, ServerStartTime
+        __!(r.skip(8 * 2));
+        let security_buffer_offset = __!(r.field("security_buffer_offset").u16());
+        let security_buffer_len = __!(r.field("security_buffer_len").u16()).into();
+        let context_offset = __!(r.field("context_offset").u32());
+        r.field("security_buffer")
+            .slice_at(security_buffer_offset.into(), security_buffer_len)
+            .ok_or(Error::InvalidOffset)?;
+
+        let contexts = if dialect == Dialect::SMB311 && context_count > 0 {
+            NegotiateContext::parse_list(r, context_offset as usize, context_count)?
+        } else {
+            vec![]
+        };
+
+        Ok(Self {
+            security_mode,
+            dialect,
+            server_guid,
+            capabilities,
+            max_transact_size,
+            max_read_size,
+            max_write_size,
+            security_buffer_len,
+            contexts,
+        })
+    }
+
+    fn context_id(&self, context_type: u16) -> Option<u16> {
+        self.contexts
+            .iter()
+            .find(|c| c.context_type == context_type)?
+            .ids()?
+            .first()
+            .copied()
+    }
+
+    /// Cipher the server selected, for 3.1.1 (3.0 and 3.0.2 only have AES-128-CCM).
+    pub fn cipher(&self) -> Option<u16> {
+        self.context_id(ENCRYPTION_CAPABILITIES)
+    }
+
+    pub fn signing_algorithm(&self) -> Option<u16> {
+        self.context_id(SIGNING_CAPABILITIES)
+    }
+}
@@ -0,0 +1,136 @@
+use bitflags::bitflags;
+
+use super::{structure_size, Error, __};
+use crate::smb::{
+    reader::Reader,
+    types::{utf16le, FileId},
+};
+
+bitflags! {
+    ///  CompletionFilter (4 bytes): Specifies the types of changes to monitor.
+    #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+    pub struct CompletionFilter: u32 {
+        const FileName = 0x00000001;
+        const DirName = 0x00000002;
+        const Attributes = 0x00000004;
+        const Size = 0x00000008;
+        const LastWrite = 0x00000010;
+        const LastAccess = 0x00000020;
+        const Creation = 0x00000040;
+        const Ea = 0x00000080;
+        const Security = 0x00000100;
+        const StreamName = 0x00000200;
+        const StreamSize = 0x00000400;
+        const StreamWrite = 0x00000800;
+
+        const _ = !0;
+    }
+}
+
+///  SMB2_WATCH_TREE (0x0001): The request MUST monitor changes on any file or directory contained
+/// beneath the directory specified by FileId.
+pub const SMB2_WATCH_TREE: u16 = 0x0001;
+
+// 2.2.35 SMB2 CHANGE_NOTIFY Request
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct NotifyRequest {
+    pub flags: u16,
+    pub output_buffer_length: u32,
+    pub file_id: FileId,
+    pub completion_filter: CompletionFilter,
+}
+
+// 2.2.36 SMB2 CHANGE_NOTIFY Response
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct NotifyResponse {
+    pub entries: Vec<FileNotifyInformation>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum NotifyAction {
+    Added,
+    Removed,
+    Modified,
+    RenamedOldName,
+    RenamedNewName,
+    AddedStream,
+    RemovedStream,
+    ModifiedStream,
+    Unknown(u32),
+}
+
+// [MS-FSCC] 2.7.1 FILE_NOTIFY_INFORMATION
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FileNotifyInformation {
+    pub action: NotifyAction,
+    pub name: String,
+}
+
+impl NotifyRequest {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 32)?;
+        let flags = __!(r.field("flags").u16());
+        let output_buffer_length = __!(r.field("output_buffer_length").u32());
+        let file_id = __!(r.field("file_id").file_id());
+        let completion_filter =
+            CompletionFilter::from_bits_retain(__!(r.field("completion_filter").u32()));
+        Ok(Self {
+            flags,
+            output_buffer_length,
+            file_id,
+            completion_filter,
+        })
+    }
+
+    pub fn watch_tree(&self) -> bool {
+        self.flags & SMB2_WATCH_TREE != 0
+    }
+}
+
+impl NotifyResponse {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 9)?;
+        let offset = __!(r.field("offset").u16());
+        let length = __!(r.field("length").u32());
+        let buf = r
+            .field("buf")
+            .slice_at(offset.into(), length as usize)
+            .ok_or(Error::InvalidOffset)?;
+
+        let mut entries = vec![];
+        let mut start = 0usize;
+        while start < buf.len() {
+            let mut r = Reader::new(&buf[start..], 0);
+            let next = __!(r.u32()) as usize;
+            let action = NotifyAction::from(__!(r.u32()));
+            let name_len = __!(r.u32());
+            let name = __!(r.bytes(name_len as usize));
+            entries.push(FileNotifyInformation {
+                action,
+                name: utf16le(name),
+            });
+            if next == 0 {
+                break;
+            }
+            start += next;
+        }
+
+        Ok(Self { entries })
+    }
+}
+
+impl From<u32> for NotifyAction {
+    fn from(value: u32) -> Self {
+        match value {
+            1 => Self::Added,
+            2 => Self::Removed,
+            3 => Self::Modified,
+            4 => Self::RenamedOldName,
+            5 => Self::RenamedNewName,
+            6 => Self::AddedStream,
+            7 => Self::RemovedStream,
+            8 => Self::ModifiedStream,
+            other => Self::Unknown(other),
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use bitflags::bitflags;
+
+use super::{structure_size, Error, __};
+use crate::smb::{
+    reader::Reader,
+    types::{FileId, Guid},
+};
+
+bitflags! {
+    ///  LeaseState (4 bytes): The lease state, a combination of R (read), H (handle) and W (write)
+    /// caching.
+    #[derive(Copy, Clone, Eq, PartialEq, Default)]
+    pub struct LeaseState: u32 {
+        const ReadCaching = 0x01;
+        const HandleCaching = 0x02;
+        const WriteCaching = 0x04;
+
+        const _ = !0;
+    }
+}
+
+impl std::fmt::Debug for LeaseState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "NONE");
+        }
+        for (flag, c) in [
+            (Self::ReadCaching, 'R'),
+            (Self::HandleCaching, 'H'),
+            (Self::WriteCaching, 'W'),
+        ] {
+            if self.contains(flag) {
+                write!(f, "{c}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Name of a RequestedOplockLevel/OplockLevel value.
+pub fn oplock_level_name(level: u8) -> &'static str {
+    match level {
+        0x00 => "NONE",
+        0x01 => "LEVEL_II",
+        0x08 => "EXCLUSIVE",
+        0x09 => "BATCH",
+        0xFF => "LEASE",
+        _ => "UNKNOWN",
+    }
+}
+
+// 2.2.23.1 Oplock Break Notification, 2.2.24.1 Oplock Break Acknowledgment and 2.2.25.1 Oplock
+// Break Response, all three share the same layout
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct OplockBreak {
+    pub oplock_level: u8,
+    pub file_id: FileId,
+}
+
+// 2.2.23.2 Lease Break Notification
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LeaseBreakNotification {
+    pub new_epoch: u16,
+    ///  Flags (4 bytes): SMB2_NOTIFY_BREAK_LEASE_FLAG_ACK_REQUIRED (0x01), a Lease Break
+    /// Acknowledgment is required.
+    pub flags: u32,
+    pub lease_key: Guid,
+    pub current_state: LeaseState,
+    pub new_state: LeaseState,
+}
+
+// 2.2.24.2 Lease Break Acknowledgment and 2.2.25.2 Lease Break Response
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LeaseBreakAck {
+    pub flags: u32,
+    pub lease_key: Guid,
+    pub state: LeaseState,
+}
+
+// 2.2.13.2.8 SMB2_CREATE_REQUEST_LEASE and 2.2.13.2.10 SMB2_CREATE_REQUEST_LEASE_V2, also used for
+// the create response contexts
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LeaseContext {
+    pub lease_key: Guid,
+    pub state: LeaseState,
+    pub flags: u32,
+    /// Only on V2 contexts
+    pub parent_lease_key: Option<Guid>,
+    pub epoch: Option<u16>,
+}
+
+impl OplockBreak {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 24)?;
+        let oplock_level = __!(r.field("oplock_level").u8());
+        __!(r.skip(1 + 4));
+        Ok(Self {
+            oplock_level,
+            file_id: __!(r.field("file_id").file_id()),
+        })
+    }
+}
+
+impl LeaseBreakNotification {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 44)?;
+        let new_epoch = __!(r.field("new_epoch").u16());
+        let flags = __!(r.field("flags").u32());
+        let lease_key = __!(r.field("lease_key").guid());
+        let current_state = LeaseState::from_bits_retain(__!(r.field("current_state").u32()));
+        let new_state = LeaseState::from_bits_retain(__!(r.field("new_state").u32()));
+        Ok(Self {
+            new_epoch,
+            flags,
+            lease_key,
+            current_state,
+            new_state,
+        })
+    }
+}
+
+impl LeaseBreakAck {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 36)?;
+        let _reserved = __!(r.field("reserved").u16());
+        let flags = __!(r.field("flags").u32());
+        let lease_key = __!(r.field("lease_key").guid());
+        let state = LeaseState::from_bits_retain(__!(r.field("state").u32()));
+        Ok(Self {
+            flags,
+            lease_key,
+            state,
+        })
+    }
+}
+
+impl LeaseContext {
+    pub const NAME: &'static str = "RqLs";
+
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let mut r = Reader::new(data, 0);
+        let lease_key = r.guid()?;
+        let state = LeaseState::from_bits_retain(r.u32()?);
+        let flags = r.u32()?;
+        let _duration = r.u64()?;
+        let (parent_lease_key, epoch) = if data.len() >= 52 {
+            (Some(r.guid()?), Some(r.u16()?))
+        } else {
+            (None, None)
+        };
+        Some(Self {
+            lease_key,
+            state,
+            flags,
+            parent_lease_key,
+            epoch,
+        })
+    }
+
+    pub fn find(contexts: &[super::create::CreateContext]) -> Option<Self> {
+        contexts
+            .iter()
+            .find(|c| c.name == Self::NAME)
+            .and_then(|c| Self::parse(&c.data))
+    }
+}
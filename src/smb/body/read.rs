@@ -0,0 +1,78 @@
+use super::{structure_size, Error, __};
+use crate::smb::{reader::Reader, types::FileId};
+
+// 2.2.19 SMB2 READ Request
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ReadRequest {
+    pub flags: u8,
+    ///  Length (4 bytes): The length, in bytes, of the data to read from the specified file or pipe.
+    pub length: u32,
+    ///  Offset (8 bytes): The offset, in bytes, into the file from which the data MUST be read. If
+    /// the read is being executed on a pipe, the Offset MUST be set to 0 by the client and MUST be
+    /// ignored by the server.
+    pub offset: u64,
+    pub file_id: FileId,
+    pub minimum_count: u32,
+    pub channel: u32,
+    pub remaining_bytes: u32,
+}
+
+// 2.2.20 SMB2 READ Response
+#[derive(Clone, Eq, PartialEq)]
+pub struct ReadResponse {
+    pub data_remaining: u32,
+    pub data: Vec<u8>,
+}
+
+impl ReadRequest {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 49)?;
+        let _padding = __!(r.field("padding").u8());
+        let flags = __!(r.field("flags").u8());
+        let length = __!(r.field("length").u32());
+        let offset = __!(r.field("offset").u64());
+        let file_id = __!(r.field("file_id").file_id());
+        let minimum_count = __!(r.field("minimum_count").u32());
+        let channel = __!(r.field("channel").u32());
+        let remaining_bytes = __!(r.field("remaining_bytes").u32());
+
+        Ok(Self {
+            flags,
+            length,
+            offset,
+            file_id,
+            minimum_count,
+            channel,
+            remaining_bytes,
+        })
+    }
+}
+
+impl ReadResponse {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 17)?;
+        let data_offset = __!(r.field("data_offset").u8());
+        let _reserved = __!(r.field("reserved").u8());
+        let data_len = __!(r.field("data_len").u32());
+        let data_remaining = __!(r.field("data_remaining").u32());
+
+        let data = r
+            .field("data")
+            .slice_at(data_offset.into(), data_len as usize)
+            .ok_or(Error::InvalidOffset)?;
+
+        Ok(Self {
+            data_remaining,
+            data: data.to_vec(),
+        })
+    }
+}
+
+impl std::fmt::Debug for ReadResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReadResponse")
+            .field("data_remaining", &self.data_remaining)
+            .field("data_len", &self.data.len())
+            .finish()
+    }
+}
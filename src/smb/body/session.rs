@@ -0,0 +1,106 @@
+use bitflags::bitflags;
+use std::fmt::Debug;
+
+use super::{structure_size, Error, __};
+use crate::{ntlmssp, smb::reader::Reader};
+
+bitflags! {
+    ///  SessionFlags (2 bytes): A flags field that indicates additional information about the
+    /// session.
+    #[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+    pub struct SessionFlags: u16 {
+        const IsGuest = 0x0001;
+        const IsNull = 0x0002;
+        const EncryptData = 0x0004;
+
+        const _ = !0;
+    }
+}
+
+// 2.2.5 SMB2 SESSION_SETUP Request
+#[derive(Clone, Eq, PartialEq)]
+pub struct SessionSetupRequest {
+    ///  Flags (1 byte): SMB2_SESSION_FLAG_BINDING (0x01) when binding a new channel to an existing
+    /// session.
+    pub flags: u8,
+    pub security_mode: u8,
+    pub capabilities: u32,
+    pub previous_session_id: u64,
+    /// GSS token, usually SPNEGO wrapping NTLMSSP or Kerberos.
+    pub security_buffer: Vec<u8>,
+}
+
+// 2.2.6 SMB2 SESSION_SETUP Response
+#[derive(Clone, Eq, PartialEq)]
+pub struct SessionSetupResponse {
+    pub session_flags: SessionFlags,
+    pub security_buffer: Vec<u8>,
+}
+
+impl SessionSetupRequest {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 25)?;
+        let flags = __!(r.field("flags").u8());
+        let security_mode = __!(r.field("security_mode").u8());
+        let capabilities = __!(r.field("capabilities").u32());
+        let _channel = __!(r.field("channel").u32());
+        let offset = __!(r.field("offset").u16());
+        let len = __!(r.field("len").u16());
+        let previous_session_id = __!(r.field("previous_session_id").u64());
+        let security_buffer = r
+            .field("security_buffer")
+            .slice_at(offset.into(), len.into())
+            .ok_or(Error::InvalidOffset)?
+            .to_vec();
+        Ok(Self {
+            flags,
+            security_mode,
+            capabilities,
+            previous_session_id,
+            security_buffer,
+        })
+    }
+
+    pub fn binding(&self) -> bool {
+        self.flags & 0x01 != 0
+    }
+}
+
+impl SessionSetupResponse {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 9)?;
+        let session_flags = SessionFlags::from_bits_retain(__!(r.field("session_flags").u16()));
+        let offset = __!(r.field("offset").u16());
+        let len = __!(r.field("len").u16());
+        let security_buffer = r
+            .field("security_buffer")
+            .slice_at(offset.into(), len.into())
+            .ok_or(Error::InvalidOffset)?
+            .to_vec();
+        Ok(Self {
+            session_flags,
+            security_buffer,
+        })
+    }
+}
+
+impl Debug for SessionSetupRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionSetupRequest")
+            .field("flags", &self.flags)
+            .field("security_mode", &self.security_mode)
+            .field("capabilities", &self.capabilities)
+            .field("previous_session_id", &self.previous_session_id)
+            .field("auth", &ntlmssp::Token::find(&self.security_buffer))
+            .finish()
+    }
+}
+
+impl Debug for SessionSetupResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionSetupResponse")
+            .field("session_flags", &self.session_flags)
+            .field("auth", &ntlmssp::Token::find(&self.security_buffer))
+            .finish()
+    }
+}
@@ -0,0 +1,153 @@
+use super::{structure_size, Error, __};
+use crate::smb::{
+    reader::Reader,
+    types::{utf16le, FileId},
+};
+
+pub const SMB2_0_INFO_FILE: u8 = 0x01;
+pub const SMB2_0_INFO_FILESYSTEM: u8 = 0x02;
+pub const SMB2_0_INFO_SECURITY: u8 = 0x03;
+pub const SMB2_0_INFO_QUOTA: u8 = 0x04;
+
+// [MS-FSCC] 2.4 File Information Classes, the ones SET_INFO is used with
+pub const FILE_BASIC_INFORMATION: u8 = 4;
+pub const FILE_RENAME_INFORMATION: u8 = 10;
+pub const FILE_LINK_INFORMATION: u8 = 11;
+pub const FILE_DISPOSITION_INFORMATION: u8 = 13;
+pub const FILE_ALLOCATION_INFORMATION: u8 = 19;
+pub const FILE_END_OF_FILE_INFORMATION: u8 = 20;
+pub const FILE_DISPOSITION_INFORMATION_EX: u8 = 64;
+
+/// FileDispositionInformationEx flag asking for the file to be deleted.
+const FILE_DISPOSITION_DELETE: u32 = 0x00000001;
+
+/// Decoded SET_INFO buffer.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SetInfo {
+    /// FileBasicInformation, times of 0 are left unchanged.
+    Basic {
+        creation_time: u64,
+        last_access_time: u64,
+        last_write_time: u64,
+        change_time: u64,
+        file_attributes: u32,
+    },
+    /// FileRenameInformation (SMB2 flavor), `name` is relative to the share root.
+    Rename {
+        replace_if_exists: bool,
+        name: String,
+    },
+    /// FileLinkInformation, same layout as the rename.
+    Link {
+        replace_if_exists: bool,
+        name: String,
+    },
+    /// FileDispositionInformation(Ex), the file is deleted on last close if set.
+    Disposition {
+        delete: bool,
+    },
+    EndOfFile(u64),
+    Allocation(u64),
+    /// Security descriptor of `len` bytes, which parts of it are set is in `AdditionalInformation`.
+    Security {
+        len: usize,
+    },
+    /// Classes without a decoder, and filesystem and quota info, with their length.
+    Other(usize),
+}
+
+// 2.2.39 SMB2 SET_INFO Request
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SetInfoRequest {
+    ///  InfoType (1 byte): SMB2_0_INFO_FILE, SMB2_0_INFO_FILESYSTEM, SMB2_0_INFO_SECURITY or
+    /// SMB2_0_INFO_QUOTA.
+    pub info_type: u8,
+    pub file_info_class: u8,
+    ///  AdditionalInformation (4 bytes): for SMB2_0_INFO_SECURITY, which parts of the security
+    /// descriptor are set (OWNER 0x1, GROUP 0x2, DACL 0x4, SACL 0x8...).
+    pub additional_information: u32,
+    pub file_id: FileId,
+    pub info: SetInfo,
+}
+
+impl SetInfoRequest {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 33)?;
+        let info_type = __!(r.field("info_type").u8());
+        let file_info_class = __!(r.field("file_info_class").u8());
+        let len = __!(r.field("len").u32());
+        let offset = __!(r.field("offset").u16());
+        let _reserved = __!(r.field("reserved").u16());
+        let additional_information = __!(r.field("additional_information").u32());
+        let file_id = __!(r.field("file_id").file_id());
+        let raw = r
+            .field("raw")
+            .slice_at(offset.into(), len as usize)
+            .ok_or(Error::InvalidOffset)?;
+
+        let info = match (info_type, file_info_class) {
+            (SMB2_0_INFO_SECURITY, _) => SetInfo::Security { len: raw.len() },
+            (SMB2_0_INFO_FILE, class) => Self::parse_file_info(class, raw)?,
+            _ => SetInfo::Other(raw.len()),
+        };
+
+        Ok(Self {
+            info_type,
+            file_info_class,
+            additional_information,
+            file_id,
+            info,
+        })
+    }
+
+    fn parse_file_info(class: u8, raw: &[u8]) -> Result<SetInfo, Error> {
+        let mut r = Reader::new(raw, 0);
+        Ok(match class {
+            FILE_BASIC_INFORMATION => SetInfo::Basic {
+                creation_time: __!(r.u64()),
+                last_access_time: __!(r.u64()),
+                last_write_time: __!(r.u64()),
+                change_time: __!(r.u64()),
+                file_attributes: __!(r.u32()),
+            },
+            FILE_RENAME_INFORMATION | FILE_LINK_INFORMATION => {
+                let replace_if_exists = __!(r.u8()) != 0;
+                // Reserved, RootDirectory
+                __!(r.skip(7 + 8));
+                let len = __!(r.u32());
+                let name = utf16le(__!(r.bytes(len as usize)));
+                if class == FILE_RENAME_INFORMATION {
+                    SetInfo::Rename {
+                        replace_if_exists,
+                        name,
+                    }
+                } else {
+                    SetInfo::Link {
+                        replace_if_exists,
+                        name,
+                    }
+                }
+            }
+            FILE_DISPOSITION_INFORMATION => SetInfo::Disposition {
+                delete: __!(r.u8()) != 0,
+            },
+            FILE_DISPOSITION_INFORMATION_EX => SetInfo::Disposition {
+                delete: __!(r.u32()) & FILE_DISPOSITION_DELETE != 0,
+            },
+            FILE_END_OF_FILE_INFORMATION => SetInfo::EndOfFile(__!(r.u64())),
+            FILE_ALLOCATION_INFORMATION => SetInfo::Allocation(__!(r.u64())),
+            _ => SetInfo::Other(raw.len()),
+        })
+    }
+}
+
+// 2.2.40 SMB2 SET_INFO Response
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SetInfoResponse;
+
+impl SetInfoResponse {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 2)?;
+        Ok(Self)
+    }
+}
@@ -0,0 +1,65 @@
+use super::{structure_size, Error, __};
+use crate::smb::{reader::Reader, types::utf16le};
+
+/// ShareFlags bit requiring encryption of all the traffic to the share.
+pub const SHI1005_FLAGS_ENCRYPT_DATA: u32 = 0x00008000;
+
+// 2.2.9 SMB2 TREE_CONNECT Request
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TreeConnectRequest {
+    ///  Flags/Reserved (2 bytes): cluster reconnect, redirect to owner and extension present in
+    /// the SMB 3.1.1 dialect, reserved otherwise.
+    pub flags: u16,
+    /// Full share path, `\\server\share`.
+    pub path: String,
+}
+
+// 2.2.10 SMB2 TREE_CONNECT Response
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TreeConnectResponse {
+    ///  ShareType (1 byte): 0x01 disk, 0x02 named pipe, 0x03 printer.
+    pub share_type: u8,
+    pub share_flags: u32,
+    pub capabilities: u32,
+    pub maximal_access: u32,
+}
+
+impl TreeConnectRequest {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 9)?;
+        let flags = __!(r.field("flags").u16());
+        let path_offset = __!(r.field("path_offset").u16());
+        let path_length = __!(r.field("path_length").u16());
+        let path = r
+            .field("path")
+            .slice_at(path_offset.into(), path_length.into())
+            .ok_or(Error::InvalidOffset)?;
+        Ok(Self {
+            flags,
+            path: utf16le(path),
+        })
+    }
+
+    /// Share name, the last component of the path.
+    pub fn share(&self) -> &str {
+        self.path.rsplit('\\').next().unwrap_or(&self.path)
+    }
+}
+
+impl TreeConnectResponse {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 16)?;
+        let share_type = __!(r.field("share_type").u8());
+        let _reserved = __!(r.field("reserved").u8());
+        Ok(Self {
+            share_type,
+            share_flags: __!(r.field("share_flags").u32()),
+            capabilities: __!(r.field("capabilities").u32()),
+            maximal_access: __!(r.field("maximal_access").u32()),
+        })
+    }
+
+    pub fn encrypt_data(&self) -> bool {
+        self.share_flags & SHI1005_FLAGS_ENCRYPT_DATA != 0
+    }
+}
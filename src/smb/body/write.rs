@@ -0,0 +1,75 @@
+use super::{structure_size, Error, __};
+use crate::smb::{reader::Reader, types::FileId};
+
+// 2.2.21 SMB2 WRITE Request
+#[derive(Clone, Eq, PartialEq)]
+pub struct WriteRequest {
+    ///  Offset (8 bytes): The offset, in bytes, of where to write the data in the destination file. If
+    /// the write is being executed on a pipe, the Offset MUST be set to 0 by the client and MUST be
+    /// ignored by the server.
+    pub offset: u64,
+    pub file_id: FileId,
+    pub channel: u32,
+    pub remaining_bytes: u32,
+    pub flags: u32,
+    pub data: Vec<u8>,
+}
+
+// 2.2.22 SMB2 WRITE Response
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct WriteResponse {
+    ///  Count (4 bytes): The number of bytes written.
+    pub count: u32,
+    pub remaining: u32,
+}
+
+impl WriteRequest {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 49)?;
+        let data_offset = __!(r.field("data_offset").u16());
+        let length = __!(r.field("length").u32());
+        let offset = __!(r.field("offset").u64());
+        let file_id = __!(r.field("file_id").file_id());
+        let channel = __!(r.field("channel").u32());
+        let remaining_bytes = __!(r.field("remaining_bytes").u32());
+        let _channel_info_offset = __!(r.field("channel_info_offset").u16());
+        let _channel_info_len = __!(r.field("channel_info_len").u16());
+        let flags = __!(r.field("flags").u32());
+
+        let data = r
+            .field("data")
+            .slice_at(data_offset.into(), length as usize)
+            .ok_or(Error::InvalidOffset)?;
+
+        Ok(Self {
+            offset,
+            file_id,
+            channel,
+            remaining_bytes,
+            flags,
+            data: data.to_vec(),
+        })
+    }
+}
+
+impl WriteResponse {
+    pub fn parse(r: &mut Reader) -> Result<Self, Error> {
+        structure_size(r, 17)?;
+        let _reserved = __!(r.field("reserved").u16());
+        let count = __!(r.field("count").u32());
+        let remaining = __!(r.field("remaining").u32());
+
+        Ok(Self { count, remaining })
+    }
+}
+
+impl std::fmt::Debug for WriteRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WriteRequest")
+            .field("offset", &self.offset)
+            .field("file_id", &self.file_id)
+            .field("flags", &self.flags)
+            .field("data_len", &self.data.len())
+            .finish()
+    }
+}
@@ -1,5 +1,9 @@
+pub mod body;
 pub mod flags;
 pub mod opcodes;
+pub mod reader;
+pub mod status;
+pub mod types;
 
 use crate::prettify;
 use std::{cmp::Ordering, fmt::Debug};
@@ -137,20 +141,130 @@ impl SMBMsg {
             payload: drainer.collect(),
         })
     }
+
+    /// Parses every message of a TCP payload, which can hold several NetBIOS session messages,
+    /// each of them possibly a compound chain.
+    pub fn parse_packet(raw: &[u8]) -> Result<Vec<Self>, Error> {
+        let mut msgs = vec![];
+        let mut rest = raw;
+        while !rest.is_empty() {
+            let head: [u8; 4] = __!(rest.get(..4).and_then(|h| h.try_into().ok()));
+            if head[0] != 0 {
+                return Err(Error::NonZeroFirstByte);
+            }
+            let len = u32::from_be_bytes(head) as usize;
+            if len == 0 {
+                return Err(Error::ZeroHeaderMsg);
+            }
+            let session_msg = rest.get(4..4 + len).ok_or(Error::InvalidMessageLength)?;
+            msgs.extend(Self::parse_chain(session_msg)?);
+            rest = &rest[4 + len..];
+        }
+        Ok(msgs)
+    }
+
+    /// Splits a compounded request/response by its NextCommand offsets, every message's payload
+    /// ends where the next header starts.
+    pub fn parse_chain(mut raw: &[u8]) -> Result<Vec<Self>, Error> {
+        let mut msgs = vec![];
+        loop {
+            let header = SMBHeader::parse_from_raw(&mut raw.iter().copied())?;
+            let end = match header.chain_offset {
+                0 => raw.len(),
+                next => next as usize,
+            };
+            if end < header.hlen as usize || end > raw.len() {
+                return Err(Error::InvalidMessageLength);
+            }
+
+            let payload = raw[header.hlen as usize..end].to_vec();
+            let last = header.chain_offset == 0;
+            msgs.push(Self { header, payload });
+            if last {
+                return Ok(msgs);
+            }
+            raw = &raw[end..];
+        }
+    }
+
+    pub fn body(&self) -> Result<body::Body, body::Error> {
+        body::Body::parse(&self.header, &self.payload)
+    }
 }
 impl SMBHeader {
+    pub fn is_response(&self) -> bool {
+        self.flags.contains(flags::Flags::FlagsServer2Redir)
+    }
+
+    pub fn status(&self) -> status::NtStatus {
+        status::NtStatus(self.nt_status)
+    }
+
+    pub fn is_async(&self) -> bool {
+        self.flags.contains(flags::Flags::FlagsAsyncCommand)
+    }
+
+    /// Oplock and lease breaks sent by the server on its own have MessageId 0xFFFFFFFFFFFFFFFF.
+    pub fn is_unsolicited(&self) -> bool {
+        self.cmd_seq == u64::MAX
+    }
+
+    /// TreeId, async headers have the AsyncId in its place.
+    pub fn tree_id(&self) -> Option<u32> {
+        (!self.is_async()).then_some(self.tid)
+    }
+
+    /// ChannelSequence of a request, the low half of the Status field in the SMB 3.x dialect
+    /// family (which is plain Status, always 0, before that).
+    pub fn channel_sequence(&self, dialect: body::negotiate::Dialect) -> Option<u16> {
+        (!self.is_response() && dialect.is_smb3()).then_some(self.nt_status as u16)
+    }
+
+    /// Where each field of an SMB2 header is, relative to its start, for the annotated dumps.
+    pub fn spans(&self) -> Vec<reader::Span> {
+        let mut layout = vec![
+            ("magic", 4),
+            ("hlen", 2),
+            ("cred_charge", 2),
+            ("nt_status", 4),
+            ("opcode", 2),
+            ("cred_req_res", 2),
+            ("flags", 4),
+            ("chain_offset", 4),
+            ("cmd_seq", 8),
+        ];
+        if self.is_async() {
+            layout.push(("async_id", 8));
+        } else {
+            layout.extend([("pid", 4), ("tid", 4)]);
+        }
+        layout.extend([("uid", 8), ("signature", 16)]);
+
+        let mut start = 0;
+        layout
+            .into_iter()
+            .map(|(name, len)| {
+                start += len;
+                reader::Span {
+                    name,
+                    range: start - len..start,
+                }
+            })
+            .collect()
+    }
+
     pub fn parse_from_raw(it: &mut impl ExactSizeIterator<Item = u8>) -> Result<Self, Error> {
         let orig_len = it.len();
 
         let magic: [u8; 4] = __!(take_slice(it));
 
-        return if magic.cmp(&[0xFE, b'S', b'M', b'B']) == Ordering::Equal {
+        if magic.cmp(&[0xFE, b'S', b'M', b'B']) == Ordering::Equal {
             Self::parse_fe_smb(magic, it, orig_len)
         } else if magic.cmp(&[0xFF, b'S', b'M', b'B']) == Ordering::Equal {
             Self::parse_ff_smb(magic, it, orig_len)
         } else {
             Err(Error::InvalidMagic)
-        };
+        }
     }
 
     fn parse_fe_smb(
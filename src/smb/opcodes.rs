@@ -2,7 +2,7 @@ use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
 
 #[repr(u16)]
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
 #[derive(FromPrimitive)]
 pub enum Opcodes {
     NegotiateProtocol = 0x00,
@@ -39,3 +39,57 @@ impl TryFrom<u16> for Opcodes {
         FromPrimitive::from_u16(value).ok_or(Self::Error::UnknownOpcode)
     }
 }
+
+impl Opcodes {
+    /// Name in [MS-SMB2], `QUERY_DIRECTORY` for `Find`.
+    pub fn spec_name(&self) -> &'static str {
+        match self {
+            Self::NegotiateProtocol => "NEGOTIATE",
+            Self::SessionSetup => "SESSION_SETUP",
+            Self::SessionLogoff => "LOGOFF",
+            Self::TreeConnect => "TREE_CONNECT",
+            Self::TreeDisconnect => "TREE_DISCONNECT",
+            Self::Create => "CREATE",
+            Self::Close => "CLOSE",
+            Self::Flush => "FLUSH",
+            Self::Read => "READ",
+            Self::Write => "WRITE",
+            Self::Lock => "LOCK",
+            Self::Ioctl => "IOCTL",
+            Self::Cancel => "CANCEL",
+            Self::KeepAlive => "ECHO",
+            Self::Find => "QUERY_DIRECTORY",
+            Self::Notify => "CHANGE_NOTIFY",
+            Self::GetInfo => "QUERY_INFO",
+            Self::SetInfo => "SET_INFO",
+            Self::Break => "OPLOCK_BREAK",
+        }
+    }
+
+    /// Parses a command given by the name printed here (`Create`), by its [MS-SMB2] name
+    /// (`QUERY_DIRECTORY`) or by its number, case insensitive.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase().replace(['_', '-'], "");
+        if let Some(hex) = name.strip_prefix("0x") {
+            return u16::from_str_radix(hex, 16).ok()?.try_into().ok();
+        }
+        if let Ok(number) = name.parse::<u16>() {
+            return number.try_into().ok();
+        }
+        let spec = match name.as_str() {
+            "negotiate" => Some(Self::NegotiateProtocol),
+            "logoff" => Some(Self::SessionLogoff),
+            "echo" => Some(Self::KeepAlive),
+            "querydirectory" => Some(Self::Find),
+            "changenotify" => Some(Self::Notify),
+            "queryinfo" => Some(Self::GetInfo),
+            "oplockbreak" => Some(Self::Break),
+            _ => None,
+        };
+        spec.or_else(|| {
+            (0..=0x12)
+                .filter_map(|n| Self::try_from(n).ok())
+                .find(|op| format!("{op:?}").to_ascii_lowercase() == name)
+        })
+    }
+}
@@ -0,0 +1,151 @@
+use std::{
+    cell::{Cell, RefCell},
+    ops::Range,
+};
+
+use super::types::{FileId, Guid};
+
+/// Bytes a decoded field was read from, relative to the start of the message like the offsets.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Span {
+    pub name: &'static str,
+    pub range: Range<usize>,
+}
+
+/// Little endian cursor over a message body. SMB2 offsets (NameOffset, DataOffset, ...) are
+/// relative to the start of the SMB2 header while bodies are stored without it, so `base` is the
+/// amount of bytes that precede `buf` in the original message (usually `SMBHeader::hlen`).
+#[derive(Debug, Clone)]
+pub struct Reader<'a> {
+    buf: &'a [u8],
+    base: usize,
+    pos: usize,
+    /// Where the reads named with [`Reader::field`] go, shared with the clones.
+    spans: Option<&'a RefCell<Vec<Span>>>,
+    label: Cell<Option<&'static str>>,
+}
+
+macro_rules! read_le {
+    ($($name:ident: $ty:ty),* $(,)?) => {
+        $(
+            pub fn $name(&mut self) -> Option<$ty> {
+                Some(<$ty>::from_le_bytes(self.array()?))
+            }
+        )*
+    };
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buf: &'a [u8], base: usize) -> Self {
+        Self {
+            buf,
+            base,
+            pos: 0,
+            spans: None,
+            label: Cell::new(None),
+        }
+    }
+
+    /// Records the span of every named field read into `spans`.
+    pub fn recording(self, spans: &'a RefCell<Vec<Span>>) -> Self {
+        Self {
+            spans: Some(spans),
+            ..self
+        }
+    }
+
+    /// Names the next read (a number, an id, `bytes` or `slice_at`) for the recorded spans.
+    pub fn field(&mut self, name: &'static str) -> &mut Self {
+        self.label.set(Some(name));
+        self
+    }
+
+    fn record(&self, start: usize, len: usize) {
+        if let (Some(name), Some(spans)) = (self.label.take(), self.spans) {
+            let start = self.base + start;
+            spans.borrow_mut().push(Span {
+                name,
+                range: start..start + len,
+            });
+        }
+    }
+
+    /// Offset of the cursor relative to the start of the message (including `base`).
+    pub fn offset(&self) -> usize {
+        self.base + self.pos
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.buf.len().saturating_sub(self.pos)
+    }
+
+    pub fn skip(&mut self, n: usize) -> Option<()> {
+        self.bytes(n).map(|_| ())
+    }
+
+    /// Moves the cursor to `offset` bytes after the start of the message.
+    pub fn seek(&mut self, offset: usize) -> Option<()> {
+        let pos = offset.checked_sub(self.base)?;
+        if pos > self.buf.len() {
+            return None;
+        }
+        self.pos = pos;
+        Some(())
+    }
+
+    /// Moves the cursor forward until its offset is a multiple of `to`.
+    pub fn align(&mut self, to: usize) -> Option<()> {
+        let rem = self.offset() % to;
+        if rem != 0 {
+            self.skip(to - rem)?;
+        }
+        Some(())
+    }
+
+    pub fn bytes(&mut self, n: usize) -> Option<&'a [u8]> {
+        let end = self.pos.checked_add(n)?;
+        let slice = self.buf.get(self.pos..end)?;
+        self.record(self.pos, n);
+        self.pos = end;
+        Some(slice)
+    }
+
+    /// Slice of `len` bytes at `offset` (relative to the message start), without moving the
+    /// cursor. A zero `len` always succeeds, as zeroed offsets are common for empty buffers.
+    pub fn slice_at(&self, offset: usize, len: usize) -> Option<&'a [u8]> {
+        if len == 0 {
+            self.label.take();
+            return Some(&[]);
+        }
+        let start = offset.checked_sub(self.base)?;
+        let slice = self.buf.get(start..start.checked_add(len)?)?;
+        self.record(start, len);
+        Some(slice)
+    }
+
+    pub fn rest(&mut self) -> &'a [u8] {
+        let rest = self.buf.get(self.pos..).unwrap_or_default();
+        self.pos = self.buf.len();
+        rest
+    }
+
+    pub fn array<const L: usize>(&mut self) -> Option<[u8; L]> {
+        self.bytes(L)?.try_into().ok()
+    }
+
+    read_le!(u8: u8, u16: u16, u32: u32, u64: u64, u128: u128, i32: i32);
+
+    pub fn file_id(&mut self) -> Option<FileId> {
+        // in one read so that a named file id spans both halves
+        let raw: [u8; 16] = self.array()?;
+        let (persistent, volatile) = raw.split_at(8);
+        Some(FileId {
+            persistent: u64::from_le_bytes(persistent.try_into().ok()?),
+            volatile: u64::from_le_bytes(volatile.try_into().ok()?),
+        })
+    }
+
+    pub fn guid(&mut self) -> Option<Guid> {
+        Some(Guid(self.array()?))
+    }
+}
@@ -0,0 +1,216 @@
+use std::fmt::Debug;
+
+// [MS-ERREF] 2.3.1 NTSTATUS Values, only the ones that are likely to show up on SMB traffic
+const NAMES: &[(u32, &str)] = &[
+    (0x00000000, "STATUS_SUCCESS"),
+    (0x00000103, "STATUS_PENDING"),
+    (0x00000104, "STATUS_REPARSE"),
+    (0x00000105, "STATUS_MORE_ENTRIES"),
+    (0x00000107, "STATUS_SOME_NOT_MAPPED"),
+    (0x0000010B, "STATUS_NOTIFY_CLEANUP"),
+    (0x0000010C, "STATUS_NOTIFY_ENUM_DIR"),
+    (0x80000005, "STATUS_BUFFER_OVERFLOW"),
+    (0x80000006, "STATUS_NO_MORE_FILES"),
+    (0x8000001A, "STATUS_NO_MORE_ENTRIES"),
+    (0x8000002D, "STATUS_STOPPED_ON_SYMLINK"),
+    (0xC0000001, "STATUS_UNSUCCESSFUL"),
+    (0xC0000002, "STATUS_NOT_IMPLEMENTED"),
+    (0xC0000003, "STATUS_INVALID_INFO_CLASS"),
+    (0xC0000008, "STATUS_INVALID_HANDLE"),
+    (0xC000000D, "STATUS_INVALID_PARAMETER"),
+    (0xC000000E, "STATUS_NO_SUCH_DEVICE"),
+    (0xC000000F, "STATUS_NO_SUCH_FILE"),
+    (0xC0000010, "STATUS_INVALID_DEVICE_REQUEST"),
+    (0xC0000011, "STATUS_END_OF_FILE"),
+    (0xC0000016, "STATUS_MORE_PROCESSING_REQUIRED"),
+    (0xC0000017, "STATUS_NO_MEMORY"),
+    (0xC0000022, "STATUS_ACCESS_DENIED"),
+    (0xC0000023, "STATUS_BUFFER_TOO_SMALL"),
+    (0xC0000033, "STATUS_OBJECT_NAME_INVALID"),
+    (0xC0000034, "STATUS_OBJECT_NAME_NOT_FOUND"),
+    (0xC0000035, "STATUS_OBJECT_NAME_COLLISION"),
+    (0xC0000039, "STATUS_OBJECT_PATH_INVALID"),
+    (0xC000003A, "STATUS_OBJECT_PATH_NOT_FOUND"),
+    (0xC000003B, "STATUS_OBJECT_PATH_SYNTAX_BAD"),
+    (0xC0000043, "STATUS_SHARING_VIOLATION"),
+    (0xC0000054, "STATUS_FILE_LOCK_CONFLICT"),
+    (0xC0000055, "STATUS_LOCK_NOT_GRANTED"),
+    (0xC0000056, "STATUS_DELETE_PENDING"),
+    (0xC000005F, "STATUS_NO_SUCH_LOGON_SESSION"),
+    (0xC0000061, "STATUS_PRIVILEGE_NOT_HELD"),
+    (0xC0000064, "STATUS_NO_SUCH_USER"),
+    (0xC000006A, "STATUS_WRONG_PASSWORD"),
+    (0xC000006D, "STATUS_LOGON_FAILURE"),
+    (0xC000006E, "STATUS_ACCOUNT_RESTRICTION"),
+    (0xC000006F, "STATUS_INVALID_LOGON_HOURS"),
+    (0xC0000070, "STATUS_INVALID_WORKSTATION"),
+    (0xC0000071, "STATUS_PASSWORD_EXPIRED"),
+    (0xC0000072, "STATUS_ACCOUNT_DISABLED"),
+    (0xC0000073, "STATUS_NONE_MAPPED"),
+    (0xC000007E, "STATUS_RANGE_NOT_LOCKED"),
+    (0xC000007F, "STATUS_DISK_FULL"),
+    (0xC00000B5, "STATUS_IO_TIMEOUT"),
+    (0xC00000BA, "STATUS_FILE_IS_A_DIRECTORY"),
+    (0xC00000BB, "STATUS_NOT_SUPPORTED"),
+    (0xC00000C3, "STATUS_INVALID_NETWORK_RESPONSE"),
+    (0xC00000C9, "STATUS_NETWORK_NAME_DELETED"),
+    (0xC00000CA, "STATUS_NETWORK_ACCESS_DENIED"),
+    (0xC00000CC, "STATUS_BAD_NETWORK_NAME"),
+    (0xC00000D0, "STATUS_REQUEST_NOT_ACCEPTED"),
+    (0xC0000101, "STATUS_DIRECTORY_NOT_EMPTY"),
+    (0xC0000103, "STATUS_NOT_A_DIRECTORY"),
+    (0xC0000120, "STATUS_CANCELLED"),
+    (0xC0000128, "STATUS_FILE_CLOSED"),
+    (0xC000015B, "STATUS_LOGON_TYPE_NOT_GRANTED"),
+    (0xC0000184, "STATUS_INVALID_DEVICE_STATE"),
+    (0xC0000193, "STATUS_ACCOUNT_EXPIRED"),
+    (0xC0000199, "STATUS_NOLOGON_WORKSTATION_TRUST_ACCOUNT"),
+    (0xC0000203, "STATUS_USER_SESSION_DELETED"),
+    (0xC0000224, "STATUS_PASSWORD_MUST_CHANGE"),
+    (0xC0000225, "STATUS_NOT_FOUND"),
+    (0xC0000234, "STATUS_ACCOUNT_LOCKED_OUT"),
+    (0xC000023C, "STATUS_NETWORK_UNREACHABLE"),
+    (0xC0000241, "STATUS_CONNECTION_ABORTED"),
+    (0xC000035C, "STATUS_NETWORK_SESSION_EXPIRED"),
+    (0xC0000257, "STATUS_PATH_NOT_COVERED"),
+    (0xC000019C, "STATUS_FS_DRIVER_REQUIRED"),
+    (0xC0000466, "STATUS_SERVER_UNAVAILABLE"),
+    (0xC0000801, "STATUS_SMB_BAD_CLUSTER_DIALECT"),
+    (0xC05D0000, "STATUS_SMB_NO_PREAUTH_INTEGRITY_HASH_OVERLAP"),
+    (0xC05D0001, "STATUS_SMB_NO_SIGNING_ALGORITHM_OVERLAP"),
+    (0xC0000148, "STATUS_INVALID_LEVEL"),
+    (0xC000009A, "STATUS_INSUFFICIENT_RESOURCES"),
+    (0xC0000205, "STATUS_INSUFF_SERVER_RESOURCES"),
+    (0xC000020C, "STATUS_CONNECTION_DISCONNECTED"),
+    (0xC000022A, "STATUS_DUPLICATE_OBJECTID"),
+    (0xC0000240, "STATUS_REQUEST_ABORTED"),
+    (0xC0000275, "STATUS_NOT_A_REPARSE_POINT"),
+    (0xC00000AC, "STATUS_PIPE_NOT_AVAILABLE"),
+    (0xC00000AD, "STATUS_INVALID_PIPE_STATE"),
+    (0xC00000AE, "STATUS_PIPE_BUSY"),
+    (0xC00000B0, "STATUS_PIPE_DISCONNECTED"),
+    (0xC00000B1, "STATUS_PIPE_CLOSING"),
+    (0xC00000B3, "STATUS_PIPE_LISTENING"),
+    (0xC000014B, "STATUS_PIPE_BROKEN"),
+    (0xC00000D4, "STATUS_NOT_SAME_DEVICE"),
+    (0xC00000D5, "STATUS_FILE_RENAMED"),
+];
+
+/// What the common codes mean for SMB traffic, in the words of [MS-ERREF] where they're clear
+/// enough and with the usual cause otherwise.
+const DESCRIPTIONS: &[(u32, &str)] = &[
+    (0x00000103, "The operation went asynchronous, the final response comes later with the same MessageId."),
+    (0x0000010B, "The directory handle of the CHANGE_NOTIFY was closed, the watch is over."),
+    (0x0000010C, "Too many changes to fit in the buffer, the client has to enumerate the directory again."),
+    (0x80000005, "The data was too large for the buffer, what fits is returned and the client asks again."),
+    (0x80000006, "No more files match the search, the normal end of a directory listing."),
+    (0x8000002D, "The path goes through a symbolic link the client has to resolve itself."),
+    (0xC0000008, "The FileId doesn't refer to an open on the server, it was closed or belongs to another session."),
+    (0xC000000D, "The server rejected a field of the request as invalid."),
+    (0xC000000F, "The file doesn't exist."),
+    (0xC0000010, "The request is not valid for this file or device, often an FSCTL the share doesn't support."),
+    (0xC0000011, "The read starts at or after the end of the file."),
+    (0xC0000016, "Authentication needs another SESSION_SETUP round trip, not an error."),
+    (0xC0000022, "The user doesn't have the access the request needs on the file or share."),
+    (0xC0000023, "The buffer is too small for the response, the client asks again with a larger one."),
+    (0xC0000034, "The file or directory doesn't exist, the usual answer to probing opens."),
+    (0xC0000035, "A file of that name already exists and the create disposition didn't allow it."),
+    (0xC000003A, "A directory of the path doesn't exist."),
+    (0xC0000043, "Another open of the file doesn't share the access asked for, an application has it open."),
+    (0xC0000054, "The read or write hits a byte range another open has locked."),
+    (0xC0000055, "The lock conflicts with a lock another open holds, and the request asked not to wait."),
+    (0xC0000056, "The file is being deleted and can't be opened anymore."),
+    (0xC000006D, "The user name or password is wrong, or the account can't log on here."),
+    (0xC0000072, "The account is disabled."),
+    (0xC000007E, "The unlock doesn't match a lock this open holds."),
+    (0xC000007F, "The disk of the share is full."),
+    (0xC00000BA, "A file operation was asked on a directory."),
+    (0xC00000BB, "The server doesn't support the request, like a dialect feature or an information class."),
+    (0xC00000C9, "The share or session went away on the server, usually after a disconnect."),
+    (0xC00000CC, "The share doesn't exist on the server."),
+    (0xC0000101, "The directory can't be deleted as it still has files."),
+    (0xC0000103, "A directory operation was asked on a file."),
+    (0xC0000120, "The operation was cancelled, by a CANCEL or because its session or connection went away."),
+    (0xC0000128, "The open was closed while the operation was pending."),
+    (0xC0000203, "The session is gone on the server, the client has to log on again."),
+    (0xC0000225, "The object wasn't found, often a lease or an open the server doesn't know."),
+    (0xC0000234, "The account is locked out after too many wrong passwords."),
+    (0xC0000257, "The path is in a DFS link, the client has to ask for a referral and go to the target."),
+    (0xC000035C, "The Kerberos ticket of the session expired, the client has to reauthenticate."),
+    (0xC00000AC, "The named pipe has no instance available."),
+    (0xC00000B0, "The other end of the named pipe closed it."),
+];
+
+/// Symbolic name of an NTSTATUS code, if known.
+pub fn name(code: u32) -> Option<&'static str> {
+    NAMES.iter().find(|(c, _)| *c == code).map(|(_, n)| *n)
+}
+
+/// Reverse of [`name`], accepts the name with or without the `STATUS_` prefix (case insensitive).
+pub fn from_name(name: &str) -> Option<u32> {
+    let name = name.to_ascii_uppercase();
+    let name = name.strip_prefix("STATUS_").unwrap_or(&name);
+    NAMES
+        .iter()
+        .find(|(_, n)| &n["STATUS_".len()..] == name)
+        .map(|(c, _)| *c)
+}
+
+/// NTSTATUS wrapper to print codes by name.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct NtStatus(pub u32);
+
+impl NtStatus {
+    pub const SUCCESS: Self = Self(0);
+    pub const PENDING: Self = Self(0x00000103);
+    pub const NOTIFY_CLEANUP: Self = Self(0x0000010B);
+    pub const NOTIFY_ENUM_DIR: Self = Self(0x0000010C);
+    pub const BUFFER_OVERFLOW: Self = Self(0x80000005);
+    pub const STOPPED_ON_SYMLINK: Self = Self(0x8000002D);
+    pub const MORE_PROCESSING_REQUIRED: Self = Self(0xC0000016);
+    pub const ACCESS_DENIED: Self = Self(0xC0000022);
+    pub const BUFFER_TOO_SMALL: Self = Self(0xC0000023);
+    pub const FILE_LOCK_CONFLICT: Self = Self(0xC0000054);
+    pub const LOCK_NOT_GRANTED: Self = Self(0xC0000055);
+    pub const BAD_NETWORK_NAME: Self = Self(0xC00000CC);
+
+    /// Parses a status given by name (see [`from_name`]) or as a hex code (`0xC0000022`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        let code = match name.strip_prefix("0x").or(name.strip_prefix("0X")) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => from_name(name)?,
+        };
+        Some(Self(code))
+    }
+
+    pub fn name(&self) -> Option<&'static str> {
+        name(self.0)
+    }
+
+    /// What the status means, for the common ones.
+    pub fn description(&self) -> Option<&'static str> {
+        DESCRIPTIONS
+            .iter()
+            .find(|(code, _)| *code == self.0)
+            .map(|(_, text)| *text)
+    }
+
+    /// Success and informational severities, warnings (`0x8...`) are not counted as success as
+    /// `STATUS_BUFFER_OVERFLOW` and friends usually hint at something interesting.
+    pub fn is_success(&self) -> bool {
+        self.0 >> 30 <= 1
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.0 >> 30 == 3
+    }
+}
+
+impl Debug for NtStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{name}"),
+            None => write!(f, "0x{:08X}", self.0),
+        }
+    }
+}
@@ -10,6 +10,7 @@ pub enum FlagError {
     InvalidFlagChar(char),
 }
 
+#[allow(clippy::wrong_self_convention)]
 pub trait FlagMask {
     fn as_flag_bits(self) -> u8;
     fn as_flags(self) -> FlagCollection;
@@ -80,7 +81,7 @@ impl From<Flag> for char {
     }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Eq, PartialEq, Default)]
 pub struct FlagCollection(u8);
 
 impl FlagCollection {
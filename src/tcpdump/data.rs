@@ -11,7 +11,7 @@ pub struct Data {
 
 impl Data {
     pub fn parse_data_parts(data: Vec<u8>) -> Result<Self, Error> {
-        let ipv_and_ihl = data.get(0).ok_or(Error::ExpectedByte)?;
+        let ipv_and_ihl = data.first().ok_or(Error::ExpectedByte)?;
         let ipv = ipv_and_ihl >> 4;
         if ipv != 0x4 {
             return Err(Error::UnsupportedIPVersion);
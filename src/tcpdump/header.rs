@@ -82,19 +82,16 @@ fn parse_tcpdump_sockaddr(str_addr: &mut str) -> Result<net::SocketAddr, Error>
     str_addr.parse().map_err(|_| Error::InvalidSocketAddr)
 }
 
-fn parse_tail(
-    tail: &str,
-) -> Result<
-    (
-        tcp::flags::FlagCollection,
-        Option<u32>,
-        Option<u32>,
-        u16,
-        Option<String>,
-        u128,
-    ),
-    Error,
-> {
+type HeaderTail = (
+    tcp::flags::FlagCollection,
+    Option<u32>,
+    Option<u32>,
+    u16,
+    Option<String>,
+    u128,
+);
+
+fn parse_tail(tail: &str) -> Result<HeaderTail, Error> {
     let mut flags = None;
     let mut seq = None;
     let mut ack = None;
@@ -4,7 +4,10 @@ pub mod header;
 
 use data::Data;
 use header::Header;
-use std::io::{self, BufRead};
+use std::{
+    io::{self, BufRead},
+    net::SocketAddr,
+};
 
 /// Same as `?` (in fact copied from its' old macro) but wraps the error branch in `Some`, made for
 /// the case shown in the iter below
@@ -53,13 +56,63 @@ pub struct TcpdumpMsg {
     pub data: Data,
 }
 
+impl TcpdumpMsg {
+    /// The segment as `tcpdump -n -x` prints it, what `TcpdumpIter` reads back. `None` for IPv6,
+    /// which the text reader doesn't support.
+    pub fn to_text(&self) -> Option<String> {
+        let addr = |addr: SocketAddr| match addr {
+            SocketAddr::V4(addr) => Some(format!("{}.{}", addr.ip(), addr.port())),
+            SocketAddr::V6(_) => None,
+        };
+        let header = &self.header;
+        let mut text = format!(
+            "{} IP {} > {}: Flags [{:?}]",
+            header.time.format("%H:%M:%S%.6f"),
+            addr(header.src)?,
+            addr(header.dst)?,
+            header.flags
+        );
+        if let Some(seq) = header.seq {
+            let len = self.data.data.len() as u32;
+            if len > 0 {
+                text += &format!(", seq {seq}:{}", seq.wrapping_add(len));
+            } else {
+                text += &format!(", seq {seq}");
+            }
+        }
+        if let Some(ack) = header.ack {
+            text += &format!(", ack {ack}");
+        }
+        text += &format!(", win {}", header.win);
+        if let Some(options) = &header.options {
+            text += &format!(", options {options}");
+        }
+        text += &format!(", length {}", header.length);
+
+        let bytes = [
+            self.data.ip_header.as_slice(),
+            &self.data.tcp_header,
+            &self.data.data,
+        ]
+        .concat();
+        for (i, line) in bytes.chunks(16).enumerate() {
+            let words: Vec<String> = line
+                .chunks(2)
+                .map(|word| word.iter().map(|b| format!("{b:02x}")).collect())
+                .collect();
+            text += &format!("\n\t0x{:04x}:  {}", i * 16, words.join(" "));
+        }
+        Some(text)
+    }
+}
+
 pub struct TcpdumpIter<T: BufRead> {
     stream: T,
     header: Option<Header>,
 }
 
 impl<T: BufRead> TcpdumpIter<T> {
-    fn new(val: T) -> Self {
+    pub fn new(val: T) -> Self {
         Self {
             stream: val,
             header: None,
@@ -0,0 +1,455 @@
+//! Interactive browser (`--tui`), a terminal Wireshark for SMB2: the messages in a list, the
+//! selected one decoded as a tree and dumped as bytes under it. Keys move through the list,
+//! jump between a request and its response, narrow the list to one connection, or filter it
+//! live with the expressions of `--filter`, on top of the filter options of the command line.
+pub mod term;
+
+use chrono::NaiveTime;
+use std::{fmt::Display, io};
+
+use crate::{
+    analysis::ConnKey,
+    cli,
+    filter::{fields::Message, Expr},
+    prettify::{
+        byte::hexdump,
+        color::{paint, Role},
+        json::{self, Json},
+    },
+    smb::{body::Body, status::NtStatus, SMBHeader},
+    tcpdump::header::Header,
+};
+use term::{fit, Key, Terminal, RESET, REVERSE};
+
+/// Columns of the bytes pane, a hexdump line.
+const BYTES_WIDTH: usize = 78;
+
+const HELP: &str = "q quit  ↑↓ move  tab pane  r request/response  c connection  / filter";
+
+#[derive(Debug)]
+pub enum Error {
+    /// No terminal to browse on, or `stty` failed.
+    Terminal(io::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Terminal(err) => write!(f, "can't drive the terminal: {err}"),
+        }
+    }
+}
+
+/// A message of the capture with what the analysis made of it.
+pub struct Entry {
+    pub frame: usize,
+    pub time: NaiveTime,
+    pub conn: ConnKey,
+    pub tcp: Header,
+    pub header: SMBHeader,
+    /// `Body::Other` when it didn't parse, `body_error` says why.
+    pub body: Body,
+    pub body_error: Option<String>,
+    /// For `smb2.path` on CREATE responses, see `filter::fields::Message`.
+    pub create_name: Option<String>,
+    pub payload: Vec<u8>,
+    /// The operation the message is part of, see `prettify::ops::message`.
+    pub summary: String,
+    pub context: String,
+    pub events: Vec<String>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Pane {
+    List,
+    Tree,
+    Bytes,
+}
+
+struct Browser {
+    entries: Vec<Entry>,
+    title: String,
+    /// The command line's, the typed expression applies on top of it.
+    filter: cli::Filter,
+    expr: Option<Expr>,
+    typed: String,
+    follow: Option<ConnKey>,
+    /// Indices of the entries passing the filter.
+    visible: Vec<usize>,
+    /// Position in `visible`, and of the first line shown.
+    selected: usize,
+    top: usize,
+    focus: Pane,
+    tree_scroll: usize,
+    bytes_scroll: usize,
+    /// The expression in force before editing it, to go back to.
+    editing: Option<(String, Option<Expr>)>,
+    /// Shown in place of the help until the next key.
+    notice: Option<String>,
+    /// Rows of the list pane at the last draw, what a page is.
+    page: usize,
+}
+
+/// Lines of `value` indented by depth, `name` being its key in the parent.
+fn tree(name: &str, value: &Json, depth: usize, lines: &mut Vec<String>) {
+    let indent = "  ".repeat(depth);
+    match value {
+        Json::Object(fields) if !fields.is_empty() => {
+            lines.push(format!("{indent}{name}"));
+            for (key, value) in fields {
+                tree(key, value, depth + 1, lines);
+            }
+        }
+        Json::Array(items) if !items.is_empty() => {
+            lines.push(format!("{indent}{name} [{}]", items.len()));
+            for (i, value) in items.iter().enumerate() {
+                tree(&i.to_string(), value, depth + 1, lines);
+            }
+        }
+        value => lines.push(format!("{indent}{name}: {value}")),
+    }
+}
+
+impl Browser {
+    fn new(entries: Vec<Entry>, filter: cli::Filter, title: String) -> Self {
+        let mut browser = Self {
+            entries,
+            title,
+            filter,
+            expr: None,
+            typed: String::new(),
+            follow: None,
+            visible: vec![],
+            selected: 0,
+            top: 0,
+            focus: Pane::List,
+            tree_scroll: 0,
+            bytes_scroll: 0,
+            editing: None,
+            notice: None,
+            page: 1,
+        };
+        browser.refilter();
+        browser
+    }
+
+    fn passes(&self, entry: &Entry) -> bool {
+        let msg = Message {
+            frame: entry.frame,
+            tcp: &entry.tcp,
+            header: &entry.header,
+            body: &entry.body,
+            create_name: entry.create_name.as_deref(),
+        };
+        self.follow.is_none_or(|conn| conn == entry.conn)
+            && self
+                .filter
+                .packet(entry.frame, entry.tcp.src, entry.tcp.dst)
+            && self.filter.message(&msg)
+            && self.expr.as_ref().is_none_or(|expr| expr.matches(&msg))
+    }
+
+    /// Recomputes the list, keeping the selected message or the first one after it.
+    fn refilter(&mut self) {
+        let current = self.visible.get(self.selected).copied().unwrap_or(0);
+        self.visible = (0..self.entries.len())
+            .filter(|&i| self.passes(&self.entries[i]))
+            .collect();
+        self.selected = self
+            .visible
+            .iter()
+            .position(|&i| i >= current)
+            .unwrap_or(self.visible.len().saturating_sub(1));
+        self.reset_panes();
+    }
+
+    fn reset_panes(&mut self) {
+        self.tree_scroll = 0;
+        self.bytes_scroll = 0;
+    }
+
+    fn current(&self) -> Option<&Entry> {
+        self.visible.get(self.selected).map(|&i| &self.entries[i])
+    }
+
+    fn select(&mut self, position: usize) {
+        let position = position.min(self.visible.len().saturating_sub(1));
+        if position != self.selected {
+            self.selected = position;
+            self.reset_panes();
+        }
+    }
+
+    /// The response of the selected request, the final one if it went async, or the request of
+    /// the selected response.
+    fn counterpart(&self) -> Option<usize> {
+        let index = *self.visible.get(self.selected)?;
+        let entry = &self.entries[index];
+        let same = |other: &Entry| {
+            other.conn == entry.conn
+                && other.header.cmd_seq == entry.header.cmd_seq
+                && other.header.is_response() != entry.header.is_response()
+        };
+        if entry.header.is_response() {
+            (0..index).rev().find(|&i| same(&self.entries[i]))
+        } else {
+            let mut responses = (index + 1..self.entries.len()).filter(|&i| same(&self.entries[i]));
+            let first = responses.next()?;
+            let interim = |i: usize| {
+                let header = &self.entries[i].header;
+                header.is_async() && header.status() == NtStatus::PENDING
+            };
+            Some(if interim(first) {
+                responses.find(|&i| !interim(i)).unwrap_or(first)
+            } else {
+                first
+            })
+        }
+    }
+
+    fn scroll(&mut self, by: isize) {
+        let scroll = match self.focus {
+            Pane::List => {
+                let position = self.selected.saturating_add_signed(by);
+                self.select(position);
+                return;
+            }
+            Pane::Tree => &mut self.tree_scroll,
+            Pane::Bytes => &mut self.bytes_scroll,
+        };
+        *scroll = scroll.saturating_add_signed(by);
+    }
+
+    /// Handles a key, false to quit.
+    fn key(&mut self, key: Key) -> bool {
+        self.notice = None;
+        if let Some((typed, expr)) = &mut self.editing {
+            match key {
+                Key::Enter => self.editing = None,
+                Key::Esc => {
+                    self.typed = std::mem::take(typed);
+                    self.expr = expr.take();
+                    self.editing = None;
+                    self.refilter();
+                }
+                Key::Backspace | Key::Char(_) => {
+                    match key {
+                        Key::Char(c) => self.typed.push(c),
+                        _ => drop(self.typed.pop()),
+                    }
+                    // applied as typed, whenever it makes sense so far
+                    let parsed = match self.typed.trim() {
+                        "" => Ok(None),
+                        text => Expr::parse(text).map(Some),
+                    };
+                    match parsed {
+                        Ok(expr) => {
+                            self.expr = expr;
+                            self.refilter();
+                        }
+                        Err(err) => self.notice = Some(err.to_string()),
+                    }
+                }
+                _ => {}
+            }
+            return true;
+        }
+
+        let page = self.page as isize;
+        match key {
+            Key::Char('q') | Key::Ctrl('c') => return false,
+            Key::Up | Key::Char('k') => self.scroll(-1),
+            Key::Down | Key::Char('j') => self.scroll(1),
+            Key::PageUp => self.scroll(-page),
+            Key::PageDown | Key::Char(' ') => self.scroll(page),
+            Key::Home | Key::Char('g') => self.select(0),
+            Key::End | Key::Char('G') => self.select(usize::MAX),
+            Key::Tab => {
+                self.focus = match self.focus {
+                    Pane::List => Pane::Tree,
+                    Pane::Tree => Pane::Bytes,
+                    Pane::Bytes => Pane::List,
+                }
+            }
+            Key::Char('r') => match self.counterpart() {
+                Some(index) => match self.visible.iter().position(|&i| i == index) {
+                    Some(position) => self.select(position),
+                    None => {
+                        self.notice = Some(format!(
+                            "frame {} is filtered out",
+                            self.entries[index].frame
+                        ))
+                    }
+                },
+                None => self.notice = Some("not in the capture".to_owned()),
+            },
+            Key::Char('c') => {
+                self.follow = match self.follow {
+                    Some(_) => None,
+                    None => self.current().map(|entry| entry.conn),
+                };
+                self.refilter();
+            }
+            Key::Char('/') => self.editing = Some((self.typed.clone(), self.expr.clone())),
+            _ => {}
+        }
+        true
+    }
+
+    fn row(&self, index: usize, width: usize, selected: bool) -> String {
+        let entry = &self.entries[index];
+        let direction = if entry.header.is_response() {
+            paint(Role::Response, "S→C")
+        } else {
+            paint(Role::Request, "C→S")
+        };
+        let mut row = format!(
+            "{:>6} {} {direction} {:>21} {:>21}  {}",
+            entry.frame,
+            entry.time.format("%H:%M:%S%.6f"),
+            entry.tcp.src,
+            entry.tcp.dst,
+            entry.summary,
+        );
+        if !entry.context.is_empty() {
+            row += &format!("  {}", paint(Role::Context, &entry.context));
+        }
+        if selected {
+            format!("{REVERSE}{}{RESET}", fit(&row, width, false))
+        } else {
+            fit(&row, width, true)
+        }
+    }
+
+    /// `lines` from `scroll` under a title bar, `height` lines in all.
+    fn pane(
+        &self,
+        pane: Pane,
+        title: &str,
+        lines: &[String],
+        width: usize,
+        height: usize,
+    ) -> Vec<String> {
+        let scroll = match pane {
+            Pane::Tree => self.tree_scroll,
+            _ => self.bytes_scroll,
+        };
+        let title = if self.focus == pane {
+            format!(
+                "{REVERSE}{}{RESET}",
+                fit(&format!(" {title}"), width, false)
+            )
+        } else {
+            paint(
+                Role::Dim,
+                fit(&format!("── {title} {}", "─".repeat(width)), width, false),
+            )
+        };
+        let mut out = vec![title];
+        out.extend(
+            lines
+                .iter()
+                .skip(scroll)
+                .chain(std::iter::repeat(&String::new()))
+                .take(height.saturating_sub(1))
+                .map(|line| fit(line, width, true)),
+        );
+        out
+    }
+
+    fn render(&mut self, rows: usize, cols: usize) -> Vec<String> {
+        // title, then the list, the panes under it and the status line
+        let list_height = (rows.saturating_sub(2) / 2).max(1);
+        self.page = list_height;
+        let bottom = rows.saturating_sub(2 + list_height);
+        if self.selected < self.top {
+            self.top = self.selected;
+        } else if self.selected >= self.top + list_height {
+            self.top = self.selected + 1 - list_height;
+        }
+
+        let mut title = format!(
+            " {}  {}/{} messages",
+            self.title,
+            self.visible.len(),
+            self.entries.len()
+        );
+        if let Some(conn) = self.follow {
+            title += &format!("  connection {} ↔ {}", conn.client, conn.server);
+        }
+        let list_title = if self.focus == Pane::List {
+            format!("{REVERSE}{}{RESET}", fit(&title, cols, false))
+        } else {
+            paint(Role::Dim, fit(&title, cols, false))
+        };
+        let mut screen = vec![list_title];
+        for line in 0..list_height {
+            let position = self.top + line;
+            screen.push(match self.visible.get(position) {
+                Some(&index) => self.row(index, cols, position == self.selected),
+                None => fit("", cols, false),
+            });
+        }
+
+        let (mut decoded, mut bytes) = (vec![], vec![]);
+        if let Some(entry) = self.current() {
+            tree("smb2", &json::header(&entry.header), 0, &mut decoded);
+            match &entry.body_error {
+                Some(err) => decoded.push(paint(Role::BodyError, format!("body: {err}"))),
+                None => tree("body", &json::body(&entry.body), 0, &mut decoded),
+            }
+            decoded.extend(entry.events.iter().cloned());
+            let dump = hexdump(&entry.payload, entry.header.hlen.into(), false);
+            bytes.extend(
+                dump.lines()
+                    .filter(|line| !line.is_empty())
+                    .map(str::to_owned),
+            );
+        }
+        self.tree_scroll = self.tree_scroll.min(decoded.len().saturating_sub(1));
+        self.bytes_scroll = self.bytes_scroll.min(bytes.len().saturating_sub(1));
+        if cols >= BYTES_WIDTH * 2 {
+            let tree_width = cols - BYTES_WIDTH - 1;
+            let left = self.pane(Pane::Tree, "decoded", &decoded, tree_width, bottom);
+            let right = self.pane(Pane::Bytes, "bytes", &bytes, BYTES_WIDTH, bottom);
+            for (left, right) in left.into_iter().zip(right) {
+                screen.push(format!("{left}{}{right}", paint(Role::Dim, "│")));
+            }
+        } else {
+            let tree_height = bottom / 2;
+            screen.extend(self.pane(Pane::Tree, "decoded", &decoded, cols, tree_height));
+            screen.extend(self.pane(Pane::Bytes, "bytes", &bytes, cols, bottom - tree_height));
+        }
+
+        let status = match (&self.editing, &self.notice) {
+            (Some(_), notice) => {
+                let notice = notice.as_ref().map_or_else(String::new, |notice| {
+                    paint(Role::Warning, format!("  ({notice})"))
+                });
+                format!("filter: {}█{notice}", self.typed)
+            }
+            (None, Some(notice)) => paint(Role::Warning, notice),
+            (None, None) => HELP.to_owned(),
+        };
+        screen.push(fit(&status, cols, true));
+        screen.truncate(rows);
+        screen
+    }
+}
+
+/// Browses `entries` until the user quits, `filter` being the command line's and `title` what
+/// the capture is.
+pub fn run(entries: Vec<Entry>, filter: cli::Filter, title: String) -> Result<(), Error> {
+    let mut term = Terminal::open().map_err(Error::Terminal)?;
+    let mut browser = Browser::new(entries, filter, title);
+    loop {
+        let (rows, cols) = term.size().map_err(Error::Terminal)?;
+        let screen = browser.render(rows, cols);
+        term.draw(&screen).map_err(Error::Terminal)?;
+        for key in term.keys().map_err(Error::Terminal)? {
+            if !browser.key(key) {
+                return Ok(());
+            }
+        }
+    }
+}
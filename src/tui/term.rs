@@ -0,0 +1,232 @@
+//! The controlling terminal, driven with `stty` and ANSI sequences: raw mode and the alternate
+//! screen while browsing, keys as they are typed, whole screens drawn at once. Going through
+//! `/dev/tty` leaves stdin free for the capture and works the same over SSH.
+use std::{
+    ffi::{c_int, c_ulong},
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+    os::fd::AsRawFd,
+    panic::{self, PanicHookInfo},
+    process::{Command, Stdio},
+    sync::Arc,
+};
+
+/// Alternate screen and hidden cursor, and back.
+const ENTER: &str = "\x1b[?1049h\x1b[?25l";
+const LEAVE: &str = "\x1b[?25h\x1b[?1049l";
+pub const REVERSE: &str = "\x1b[7m";
+pub const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Key {
+    Char(char),
+    /// Control and a letter, `Ctrl('c')`.
+    Ctrl(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Enter,
+    Tab,
+    Backspace,
+    Esc,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const TIOCGWINSZ: c_ulong = 0x5413;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+const TIOCGWINSZ: c_ulong = 0x40087468;
+
+/// `struct winsize` of the TIOCGWINSZ ioctl.
+#[repr(C)]
+#[derive(Default)]
+struct WinSize {
+    rows: u16,
+    cols: u16,
+    x_pixels: u16,
+    y_pixels: u16,
+}
+
+extern "C" {
+    fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+}
+
+type PanicHook = dyn Fn(&PanicHookInfo) + Sync + Send;
+
+/// Raw mode until dropped, even on a panic.
+pub struct Terminal {
+    tty: File,
+    /// `stty -g` before raw mode.
+    saved: String,
+    /// The panic hook before ours, put back when done.
+    hook: Arc<Box<PanicHook>>,
+}
+
+fn stty(tty: &File, args: &[&str]) -> io::Result<String> {
+    let out = Command::new("stty")
+        .args(args)
+        .stdin(tty.try_clone()?)
+        .stderr(Stdio::inherit())
+        .output()?;
+    if !out.status.success() {
+        return Err(io::Error::other(format!("stty {} failed", args.join(" "))));
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).trim().to_owned())
+}
+
+impl Terminal {
+    pub fn open() -> io::Result<Self> {
+        let tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+        let saved = stty(&tty, &["-g"])?;
+        stty(&tty, &["raw", "-echo"])?;
+
+        // the default hook prints the message before unwinding gets to drop the terminal, so it
+        // would land on the alternate screen and be wiped with it
+        let hook: Arc<Box<PanicHook>> = Arc::new(panic::take_hook());
+        let (previous, restore_tty, restore) = (hook.clone(), tty.try_clone()?, saved.clone());
+        panic::set_hook(Box::new(move |info| {
+            leave(&restore_tty, &restore);
+            previous(info);
+        }));
+
+        let mut term = Self { tty, saved, hook };
+        term.tty.write_all(ENTER.as_bytes())?;
+        Ok(term)
+    }
+
+    /// Rows and columns.
+    pub fn size(&self) -> io::Result<(usize, usize)> {
+        let mut size = WinSize::default();
+        // SAFETY: TIOCGWINSZ fills in a `struct winsize`, which `WinSize` has the layout of
+        if unsafe { ioctl(self.tty.as_raw_fd(), TIOCGWINSZ, &mut size as *mut WinSize) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        match (usize::from(size.rows), usize::from(size.cols)) {
+            (rows, cols) if rows > 0 && cols > 0 => Ok((rows, cols)),
+            (rows, cols) => Err(io::Error::other(format!("terminal size {rows}x{cols}"))),
+        }
+    }
+
+    /// Replaces the screen with `lines`, which are already cut to its width.
+    pub fn draw(&mut self, lines: &[String]) -> io::Result<()> {
+        let mut screen = String::from("\x1b[H");
+        screen += &lines.join("\x1b[K\r\n");
+        screen += "\x1b[K\x1b[J";
+        self.tty.write_all(screen.as_bytes())?;
+        self.tty.flush()
+    }
+
+    /// Waits for input, several keys when they come at once (a paste).
+    pub fn keys(&mut self) -> io::Result<Vec<Key>> {
+        let mut buf = [0; 64];
+        loop {
+            let n = self.tty.read(&mut buf)?;
+            if n > 0 {
+                return Ok(parse(&buf[..n]));
+            }
+        }
+    }
+}
+
+/// Back to the screen and mode the terminal was in.
+fn leave(mut tty: &File, saved: &str) {
+    let _ = tty.write_all(LEAVE.as_bytes());
+    let _ = stty(tty, &[saved]);
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            // the hook did it already
+            return;
+        }
+        leave(&self.tty, &self.saved);
+        let previous = self.hook.clone();
+        panic::set_hook(Box::new(move |info| previous(info)));
+    }
+}
+
+/// Keys of a read. A lone escape is the Esc key, escape sequences arrive in one read.
+fn parse(mut bytes: &[u8]) -> Vec<Key> {
+    let mut keys = vec![];
+    loop {
+        let (key, len) = match bytes {
+            [] => break,
+            [0x1b, b'[' | b'O', b'A', ..] => (Key::Up, 3),
+            [0x1b, b'[' | b'O', b'B', ..] => (Key::Down, 3),
+            [0x1b, b'[' | b'O', b'C', ..] => (Key::Right, 3),
+            [0x1b, b'[' | b'O', b'D', ..] => (Key::Left, 3),
+            [0x1b, b'[' | b'O', b'H', ..] => (Key::Home, 3),
+            [0x1b, b'[' | b'O', b'F', ..] => (Key::End, 3),
+            [0x1b, b'[', b'1' | b'7', b'~', ..] => (Key::Home, 4),
+            [0x1b, b'[', b'4' | b'8', b'~', ..] => (Key::End, 4),
+            [0x1b, b'[', b'5', b'~', ..] => (Key::PageUp, 4),
+            [0x1b, b'[', b'6', b'~', ..] => (Key::PageDown, 4),
+            // an unknown sequence, skipped up to its final byte
+            [0x1b, b'[', rest @ ..] => {
+                let end = rest.iter().position(|b| (0x40..=0x7e).contains(b));
+                bytes = &bytes[end.map_or(bytes.len(), |end| end + 3)..];
+                continue;
+            }
+            [0x1b, ..] => (Key::Esc, 1),
+            [b'\r' | b'\n', ..] => (Key::Enter, 1),
+            [b'\t', ..] => (Key::Tab, 1),
+            [0x7f | 0x08, ..] => (Key::Backspace, 1),
+            [b @ 0x01..=0x1a, ..] => (Key::Ctrl((b'a' + b - 1) as char), 1),
+            [b, ..] => {
+                let len = match b {
+                    0xc0..=0xdf => 2,
+                    0xe0..=0xef => 3,
+                    0xf0..=0xf7 => 4,
+                    _ => 1,
+                }
+                .min(bytes.len());
+                match std::str::from_utf8(&bytes[..len]) {
+                    Ok(text) => (Key::Char(text.chars().next().expect("a char")), len),
+                    Err(_) => (Key::Char(char::REPLACEMENT_CHARACTER), len),
+                }
+            }
+        };
+        keys.push(key);
+        bytes = &bytes[len..];
+    }
+    keys
+}
+
+/// `text` cut or padded to `width` columns, ANSI styles kept if `styled` and dropped otherwise.
+pub fn fit(text: &str, width: usize, styled: bool) -> String {
+    let mut out = String::new();
+    let mut shown = 0;
+    let mut open = false;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            let mut sequence = String::from(c);
+            for c in chars.by_ref() {
+                sequence.push(c);
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            if styled {
+                open = sequence != RESET;
+                out += &sequence;
+            }
+            continue;
+        }
+        if shown == width {
+            break;
+        }
+        // tabs and other control characters would move the cursor
+        out.push(if c.is_control() { ' ' } else { c });
+        shown += 1;
+    }
+    if open {
+        out += RESET;
+    }
+    out + &" ".repeat(width - shown)
+}